@@ -3,8 +3,10 @@
 //! 方案 C：渐变遮罩动画（优化版）
 //! 使用简单的半透明窗口 + 快速渐变
 
+use crate::config::AnimationConfig;
+use crate::desktop::Direction;
+use std::cell::RefCell;
 use std::time::{Duration, Instant};
-use std::thread;
 use windows::core::PCWSTR;
 use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
 use windows::Win32::Graphics::Gdi::{
@@ -13,103 +15,87 @@ use windows::Win32::Graphics::Gdi::{
 };
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::WindowsAndMessaging::{
-    CreateWindowExW, DefWindowProcW, DestroyWindow, GetSystemMetrics,
-    RegisterClassW, SetLayeredWindowAttributes, ShowWindow,
+    CreateWindowExW, DefWindowProcW, DestroyWindow, GetSystemMetrics, KillTimer,
+    RegisterClassW, SetLayeredWindowAttributes, SetTimer, ShowWindow,
     CS_HREDRAW, CS_VREDRAW, LWA_ALPHA,
     SM_CXSCREEN, SM_CYSCREEN, SW_HIDE,
-    WM_DESTROY, WM_PAINT, WNDCLASSW,
-    WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
-    WS_POPUP, SetWindowPos, HWND_TOPMOST, SWP_SHOWWINDOW,
+    WM_DESTROY, WM_PAINT, WM_TIMER, WNDCLASSW,
+    WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+    WS_POPUP, SetWindowPos, HWND_TOPMOST, SWP_NOACTIVATE, SWP_SHOWWINDOW,
 };
 use tracing::debug;
 
-/// 动画方向
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Direction {
-    Left,
-    Right,
-}
+/// 动画帧计时器
+const FRAME_TIMER_ID: usize = 1;
 
-/// 动画配置
-const ANIMATION_DURATION_MS: u64 = 200;  // 动画时长
-const MAX_ALPHA: u8 = 220;               // 最大透明度
-const FRAME_DURATION_MS: u64 = 16;       // ~60fps
+/// 正在播放的动画（只在 UI 线程访问）
+struct Playback {
+    direction: Direction,
+    start: Instant,
+    settings: AnimationConfig,
+    screen_width: i32,
+    screen_height: i32,
+}
 
-// 全局状态
-static mut CURRENT_DIRECTION: Direction = Direction::Right;
+thread_local! {
+    static PLAYBACK: RefCell<Option<Playback>> = const { RefCell::new(None) };
+}
 
 /// 动画窗口管理器
+///
+/// 动画由窗口计时器驱动，不阻塞消息循环；桌面切换由后台线程在遮罩淡入后执行
 pub struct AnimationOverlay {
     hwnd: Option<HWND>,
+    settings: AnimationConfig,
     screen_width: i32,
     screen_height: i32,
 }
 
 impl AnimationOverlay {
     /// 创建动画管理器
-    pub fn new() -> Self {
+    pub fn new(settings: AnimationConfig) -> Self {
         let screen_width = unsafe { GetSystemMetrics(SM_CXSCREEN) };
         let screen_height = unsafe { GetSystemMetrics(SM_CYSCREEN) };
         
         Self {
             hwnd: None,
+            settings,
             screen_width,
             screen_height,
         }
     }
     
-    /// 播放切换动画
-    pub fn play<F>(&mut self, direction: Direction, switch_fn: F)
-    where
-        F: FnOnce(),
-    {
-        debug!("播放切换动画: {:?}", direction);
-        
-        // 设置当前方向
-        unsafe {
-            CURRENT_DIRECTION = direction;
-        }
-        
-        // 创建遮罩窗口（快速，不做复杂绘制）
-        if let Err(e) = self.create_overlay_window() {
-            tracing::warn!("创建动画窗口失败: {:?}, 直接切换", e);
-            switch_fn();
+    /// 开始播放切换动画（立即返回，正在播放的动画从头开始）
+    pub fn start(&mut self, direction: Direction) {
+        if !self.settings.enabled {
             return;
         }
         
-        let start = Instant::now();
-        let duration = Duration::from_millis(ANIMATION_DURATION_MS);
-        let switch_point = Duration::from_millis(ANIMATION_DURATION_MS * 35 / 100); // 35% 时切换
-        let mut switched = false;
-        let mut switch_fn = Some(switch_fn);
+        debug!("播放切换动画: {:?}", direction);
         
-        // 动画循环
-        while start.elapsed() < duration {
-            let progress = start.elapsed().as_secs_f32() / duration.as_secs_f32();
-            
-            // 更新遮罩
-            self.update_overlay(direction, progress);
-            
-            // 在切换点执行切换
-            if !switched && start.elapsed() >= switch_point {
-                if let Some(f) = switch_fn.take() {
-                    f();
-                }
-                switched = true;
+        // 创建遮罩窗口（快速，不做复杂绘制）
+        if self.hwnd.is_none() {
+            if let Err(e) = self.create_overlay_window() {
+                tracing::warn!("创建动画窗口失败: {:?}", e);
+                return;
             }
-            
-            thread::sleep(Duration::from_millis(FRAME_DURATION_MS));
-        }
-        
-        // 确保切换已执行
-        if let Some(f) = switch_fn.take() {
-            f();
         }
+        let Some(hwnd) = self.hwnd else { return };
         
-        // 销毁遮罩窗口
-        self.destroy_overlay_window();
+        PLAYBACK.with(|playback| {
+            *playback.borrow_mut() = Some(Playback {
+                direction,
+                start: Instant::now(),
+                settings: self.settings.clone(),
+                screen_width: self.screen_width,
+                screen_height: self.screen_height,
+            });
+        });
         
-        debug!("切换动画完成");
+        unsafe {
+            SetTimer(hwnd, FRAME_TIMER_ID, self.settings.frame_duration_ms as u32, None);
+        }
+        advance(hwnd);
     }
     
     /// 创建遮罩窗口
@@ -131,7 +117,7 @@ impl AnimationOverlay {
             
             // 创建窗口（初始在屏幕外）
             let hwnd = CreateWindowExW(
-                WS_EX_LAYERED | WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+                WS_EX_LAYERED | WS_EX_TOOLWINDOW | WS_EX_TOPMOST | WS_EX_NOACTIVATE,
                 PCWSTR(class_name.as_ptr()),
                 PCWSTR::null(),
                 WS_POPUP,
@@ -154,50 +140,6 @@ impl AnimationOverlay {
         }
     }
     
-    /// 更新遮罩
-    fn update_overlay(&self, direction: Direction, progress: f32) {
-        let Some(hwnd) = self.hwnd else { return };
-        
-        let eased = ease_out_cubic(progress);
-        
-        // 透明度：快速淡入，缓慢淡出
-        let alpha = if progress < 0.35 {
-            (MAX_ALPHA as f32 * (progress / 0.35)) as u8
-        } else {
-            (MAX_ALPHA as f32 * (1.0 - (progress - 0.35) / 0.65)) as u8
-        };
-        
-        // 位置计算
-        let x = match direction {
-            Direction::Left => {
-                let start = -self.screen_width;
-                let end = self.screen_width;
-                start + ((end - start) as f32 * eased) as i32
-            }
-            Direction::Right => {
-                let start = self.screen_width;
-                let end = -self.screen_width;
-                start + ((end - start) as f32 * eased) as i32
-            }
-        };
-        
-        unsafe {
-            let _ = SetLayeredWindowAttributes(hwnd, None, alpha, LWA_ALPHA);
-            
-            let _ = SetWindowPos(
-                hwnd,
-                HWND_TOPMOST,
-                x,
-                0,
-                self.screen_width,
-                self.screen_height,
-                SWP_SHOWWINDOW,
-            );
-            
-            let _ = InvalidateRect(hwnd, None, false);
-        }
-    }
-    
     /// 销毁遮罩窗口
     fn destroy_overlay_window(&mut self) {
         if let Some(hwnd) = self.hwnd.take() {
@@ -212,6 +154,80 @@ impl AnimationOverlay {
 impl Drop for AnimationOverlay {
     fn drop(&mut self) {
         self.destroy_overlay_window();
+        PLAYBACK.with(|playback| playback.borrow_mut().take());
+    }
+}
+
+/// 按经过的时间更新遮罩，播放完毕时隐藏遮罩并停止计时器
+fn advance(hwnd: HWND) {
+    let frame = PLAYBACK.with(|playback| {
+        playback.borrow().as_ref().map(|p| {
+            let duration = Duration::from_millis(p.settings.duration_ms);
+            let progress = p.start.elapsed().as_secs_f32() / duration.as_secs_f32().max(f32::EPSILON);
+            (progress, p.direction, p.settings.max_alpha, p.screen_width, p.screen_height)
+        })
+    });
+    
+    match frame {
+        Some((progress, direction, max_alpha, width, height)) if progress < 1.0 => {
+            update_overlay(hwnd, direction, progress, max_alpha, width, height);
+        }
+        _ => unsafe {
+            let _ = KillTimer(hwnd, FRAME_TIMER_ID);
+            let _ = ShowWindow(hwnd, SW_HIDE);
+            PLAYBACK.with(|playback| playback.borrow_mut().take());
+            debug!("切换动画完成");
+        },
+    }
+}
+
+/// 更新遮罩
+fn update_overlay(
+    hwnd: HWND,
+    direction: Direction,
+    progress: f32,
+    max_alpha: u8,
+    screen_width: i32,
+    screen_height: i32,
+) {
+    let eased = ease_out_cubic(progress);
+    
+    // 透明度：快速淡入，缓慢淡出
+    let max_alpha = max_alpha as f32;
+    let alpha = if progress < 0.35 {
+        (max_alpha * (progress / 0.35)) as u8
+    } else {
+        (max_alpha * (1.0 - (progress - 0.35) / 0.65)) as u8
+    };
+    
+    // 位置计算
+    let x = match direction {
+        Direction::Left => {
+            let start = -screen_width;
+            let end = screen_width;
+            start + ((end - start) as f32 * eased) as i32
+        }
+        Direction::Right => {
+            let start = screen_width;
+            let end = -screen_width;
+            start + ((end - start) as f32 * eased) as i32
+        }
+    };
+    
+    unsafe {
+        let _ = SetLayeredWindowAttributes(hwnd, None, alpha, LWA_ALPHA);
+        
+        let _ = SetWindowPos(
+            hwnd,
+            HWND_TOPMOST,
+            x,
+            0,
+            screen_width,
+            screen_height,
+            SWP_SHOWWINDOW | SWP_NOACTIVATE,
+        );
+        
+        let _ = InvalidateRect(hwnd, None, false);
     }
 }
 
@@ -231,14 +247,16 @@ unsafe extern "system" fn overlay_window_proc(
             let height = GetSystemMetrics(SM_CYSCREEN);
             
             // 使用 GradientFill 绘制水平渐变
-            let direction = CURRENT_DIRECTION;
+            let direction = PLAYBACK
+                .with(|playback| playback.borrow().as_ref().map(|p| p.direction))
+                .unwrap_or(Direction::Right);
             
             let (left_color, right_color) = match direction {
                 Direction::Left => (0x0000u16, 0xFF00u16),  // 黑 -> 透明
                 Direction::Right => (0xFF00u16, 0x0000u16), // 透明 -> 黑
             };
             
-            let mut vertices = [
+            let vertices = [
                 TRIVERTEX {
                     x: 0,
                     y: 0,
@@ -274,6 +292,10 @@ unsafe extern "system" fn overlay_window_proc(
             let _ = EndPaint(hwnd, &ps);
             LRESULT(0)
         }
+        WM_TIMER if wparam.0 == FRAME_TIMER_ID => {
+            advance(hwnd);
+            LRESULT(0)
+        }
         WM_DESTROY => LRESULT(0),
         _ => DefWindowProcW(hwnd, msg, wparam, lparam),
     }
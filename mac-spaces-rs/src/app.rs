@@ -0,0 +1,448 @@
+//! 应用主体
+//!
+//! 初始化虚拟桌面 API、钩子和托盘，运行 Win32 消息循环
+
+use crate::animation::AnimationOverlay;
+use crate::backend::DesktopBackend;
+use crate::config::{Config, SpacePlacement};
+use crate::desktop::{self, Direction};
+use crate::executor::{Action, Executor, Report, SpaceWorker};
+use crate::fullscreen::ChangeDebouncer;
+use crate::hooks::{self, WindowEvent};
+use crate::hotkey::{HotkeyEvent, HotkeyManager, WM_HOTKEY_EVENT};
+use crate::indicator::SpaceIndicator;
+use crate::ipc::{self, Response};
+use crate::keymap::Keymap;
+use crate::pipe::{self, NamedPipeTransport};
+use crate::registry::SpaceRegistry;
+use crate::state::{self, StateStore};
+use crate::tray;
+use crate::vda::{Capability, VirtualDesktopAccessor};
+use crate::wait::SystemClock;
+use crate::window::WindowHelper;
+use anyhow::Result;
+use muda::MenuEvent;
+use single_instance::SingleInstance;
+use std::env;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DispatchMessageW, GetMessageW, KillTimer,
+    PostMessageW, RegisterClassW, SetTimer, TranslateMessage, CS_HREDRAW, CS_VREDRAW,
+    CW_USEDEFAULT, MSG, WINDOW_EX_STYLE, WM_DESTROY, WM_TIMER, WM_USER, WNDCLASSW,
+    WS_OVERLAPPED,
+};
+
+/// DLL 在当前桌面切换时投递的消息
+const WM_DESKTOP_CHANGED: u32 = WM_USER + 101;
+
+/// 后台线程发回结果时投递的消息
+const WM_EXECUTOR_REPORT: u32 = WM_USER + 102;
+
+/// 定时检查桌面列表的计时器
+const DESKTOP_POLL_TIMER_ID: usize = 1;
+
+/// 定时清理失效空间的计时器
+const SWEEP_TIMER_ID: usize = 2;
+
+/// 合并窗口变化的计时器（有等待检查的窗口时才运行）
+const CHANGE_DEBOUNCE_TIMER_ID: usize = 3;
+
+/// 初始化各模块并运行消息循环
+pub fn run() -> Result<()> {
+    // 初始化日志
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new("info"))
+        )
+        .init();
+    
+    info!("MacSpaces v0.3.0 启动中...");
+    
+    // 单实例检查
+    let instance = SingleInstance::new("MacSpaces-Rust-v0.3.0")?;
+    if !instance.is_single() {
+        warn!("MacSpaces 已经在运行");
+        return Ok(());
+    }
+    
+    // 加载配置（出错时提示并使用默认配置）
+    let config = match Config::load() {
+        Ok((config, Some(path))) => {
+            info!("配置文件: {}", path.display());
+            config
+        }
+        Ok((config, None)) => {
+            info!("未找到配置文件，使用默认配置");
+            config
+        }
+        Err(e) => {
+            error!("加载配置失败: {}", e);
+            show_message_box("MacSpaces 配置错误", &format!("{}\n\n将使用默认配置。", e));
+            Config::default()
+        }
+    };
+    
+    // 获取 DLL 路径
+    let dll_path = get_dll_path()?;
+    info!("DLL 路径: {}", dll_path.display());
+    
+    // 初始化虚拟桌面 API
+    let vda = VirtualDesktopAccessor::new(&dll_path)?;
+    info!("VirtualDesktopAccessor 加载成功，桌面数量: {}", vda.get_desktop_count());
+    for (capability, supported) in vda.capabilities() {
+        if !supported {
+            warn!("DLL 不支持可选功能 {:?}，相关功能将被禁用", capability);
+        }
+    }
+    if config.behavior.space_placement == SpacePlacement::AfterOriginal
+        && !vda.supports(Capability::DesktopOrdering)
+    {
+        warn!("配置了 space_placement = \"after_original\"，但 DLL 没有导出 MoveDesktop，全屏空间将追加在最后");
+    }
+    
+    // 创建动画管理器
+    let mut animator = AnimationOverlay::new(config.animation.clone());
+    
+    // 创建空间指示器
+    let indicator = SpaceIndicator::new(config.indicator.clone())?;
+    
+    // 创建消息窗口（用于接收钩子线程和后台线程的 PostMessage）
+    let main_hwnd = create_message_window()?;
+    info!("消息窗口已创建: {:?}", main_hwnd);
+    let main_hwnd_raw = main_hwnd.0 as isize;
+    
+    // 设置快捷键钩子（在独立线程中运行）
+    let keymap = Keymap::from_config(&config.hotkeys)?;
+    for (chord, event) in keymap.bindings() {
+        info!("快捷键: {} -> {:?}", chord, event);
+    }
+    let _hotkey_manager = HotkeyManager::new(main_hwnd, keymap);
+    
+    // 后台线程独占虚拟桌面 API 和空间注册表，消息循环只提交动作
+    let worker_config = config.clone();
+    let executor = Executor::spawn(
+        move || {
+            // 监视在系统中发生的桌面变化：切换时由 DLL 通知，新建、删除和重命名靠定时检查
+            let main_hwnd = HWND(main_hwnd_raw as *mut std::ffi::c_void);
+            if vda.supports(Capability::Notifications)
+                && vda.register_post_message_hook(main_hwnd, WM_DESKTOP_CHANGED)
+            {
+                info!("已注册桌面切换通知");
+            }
+            
+            // 创建空间注册表，并恢复上次运行时的全屏空间
+            let registry = load_registry(&vda);
+            let mut worker = SpaceWorker::new(&vda, registry, worker_config, Box::new(SystemClock));
+            move |action: Action, report: &mut dyn FnMut(Report)| {
+                worker.handle(&vda, &WindowHelper, action, report);
+            }
+        },
+        move || unsafe {
+            let _ = PostMessageW(
+                HWND(main_hwnd_raw as *mut std::ffi::c_void),
+                WM_EXECUTOR_REPORT,
+                WPARAM(0),
+                LPARAM(0),
+            );
+        },
+    );
+    // 命令行控制：服务线程接受命名管道上的命令，交给后台线程执行并等待结果
+    let requester = executor.requester();
+    match ipc::spawn_server(&NamedPipeTransport, move |command| {
+        Response::from_reports(requester.query(command.action()))
+    }) {
+        Ok(_) => info!("命令行控制: {}", pipe::PIPE_NAME),
+        Err(e) => warn!("无法创建命名管道，命令行控制不可用: {}", e),
+    }
+    unsafe {
+        SetTimer(main_hwnd, DESKTOP_POLL_TIMER_ID, config.timing.desktop_poll_ms as u32, None);
+        SetTimer(main_hwnd, SWEEP_TIMER_ID, config.timing.sweep_interval_ms as u32, None);
+    }
+    
+    // 设置窗口事件钩子
+    let (window_tx, window_rx) = mpsc::channel();
+    let _window_hook =
+        hooks::WindowEventHook::new(window_tx, config.behavior.auto_fullscreen_space)?;
+    let debounce_ms = config.timing.change_debounce_ms;
+    let mut changes = ChangeDebouncer::new(Duration::from_millis(debounce_ms));
+    
+    // 创建托盘图标
+    let tray = tray::TrayManager::new()?;
+    
+    // 第一次同步，在托盘菜单中显示当前桌面
+    executor.submit(Action::SyncDesktops);
+    
+    info!("MacSpaces 初始化完成");
+    
+    // 消息循环
+    unsafe {
+        let mut msg = MSG::default();
+        
+        loop {
+            let ret = GetMessageW(&mut msg, None, 0, 0);
+            
+            if !ret.as_bool() {
+                break;
+            }
+            
+            // 桌面切换通知或定时检查
+            if msg.message == WM_DESKTOP_CHANGED
+                || (msg.message == WM_TIMER
+                    && msg.hwnd == main_hwnd
+                    && msg.wParam.0 == DESKTOP_POLL_TIMER_ID)
+            {
+                executor.submit(Action::SyncDesktops);
+            }
+            
+            // 定时清理失效空间
+            if msg.message == WM_TIMER && msg.hwnd == main_hwnd && msg.wParam.0 == SWEEP_TIMER_ID {
+                executor.submit(Action::SweepSpaces);
+            }
+            
+            // 变化已经稳定的窗口交给后台线程检查全屏状态
+            if msg.message == WM_TIMER
+                && msg.hwnd == main_hwnd
+                && msg.wParam.0 == CHANGE_DEBOUNCE_TIMER_ID
+            {
+                for hwnd in changes.take_due(Instant::now()) {
+                    executor.submit(Action::WindowChanged(hwnd));
+                }
+                if changes.is_empty() {
+                    let _ = KillTimer(main_hwnd, CHANGE_DEBOUNCE_TIMER_ID);
+                }
+            }
+            
+            // 处理快捷键事件
+            if msg.message == WM_HOTKEY_EVENT {
+                if let Some(event) = HotkeyEvent::from_wparam(msg.wParam.0) {
+                    info!("快捷键: {:?}", event);
+                    
+                    executor.submit(match event {
+                        HotkeyEvent::SwitchLeft => Action::Switch { steps: -1 },
+                        HotkeyEvent::SwitchRight => Action::Switch { steps: 1 },
+                        HotkeyEvent::ToggleFullscreen => Action::ToggleFullscreen,
+                        HotkeyEvent::MoveWindowLeft => Action::MoveWindow(Direction::Left),
+                        HotkeyEvent::MoveWindowRight => Action::MoveWindow(Direction::Right),
+                        HotkeyEvent::SendToSpace => Action::SendToSpace,
+                        HotkeyEvent::SplitView => Action::SplitView,
+                        HotkeyEvent::SplitLeft => Action::MoveSplit(Direction::Left),
+                        HotkeyEvent::SplitRight => Action::MoveSplit(Direction::Right),
+                        HotkeyEvent::GoTo(n) => Action::GoTo(n as i32 - 1),
+                    });
+                }
+            }
+            
+            // 处理窗口事件
+            while let Ok(event) = window_rx.try_recv() {
+                match event {
+                    WindowEvent::Destroyed(hwnd) => {
+                        changes.forget(hwnd.0 as isize);
+                        executor.submit(Action::WindowClosed(hwnd.0 as isize));
+                    }
+                    WindowEvent::Changed(hwnd) => {
+                        if changes.is_empty() {
+                            SetTimer(main_hwnd, CHANGE_DEBOUNCE_TIMER_ID, debounce_ms as u32, None);
+                        }
+                        changes.note(hwnd.0 as isize, Instant::now());
+                    }
+                    WindowEvent::Minimized(hwnd) | WindowEvent::Hidden(hwnd) => {
+                        executor.submit(Action::WindowHidden(hwnd.0 as isize));
+                    }
+                    WindowEvent::Restored(hwnd) | WindowEvent::Shown(hwnd) => {
+                        executor.submit(Action::WindowShown(hwnd.0 as isize));
+                    }
+                }
+            }
+            
+            // 处理后台线程发回的结果
+            if msg.message == WM_EXECUTOR_REPORT {
+                while let Some(report) = executor.try_recv() {
+                    match report {
+                        Report::Animate(direction) => animator.start(direction),
+                        Report::ShowIndicator(snapshot) => indicator.show(&snapshot),
+                        Report::CurrentDesktop(label) => tray.show_current_desktop(&label),
+                        Report::Failed(message) => tray.report_error(&message),
+                        Report::Info { title, text } => show_message_box(title, &text),
+                    }
+                }
+            }
+            
+            // 处理托盘菜单事件
+            if let Ok(event) = MenuEvent::receiver().try_recv() {
+                if event.id == tray.menu_exit.id() {
+                    info!("用户请求退出");
+                    break;
+                } else if event.id == tray.menu_show_info.id() {
+                    executor.submit(Action::DescribeDesktops);
+                } else if event.id == tray.menu_show_registry.id() {
+                    executor.submit(Action::DescribeRegistry);
+                } else if event.id == tray.menu_reload.id() {
+                    info!("重新加载请求（暂不支持）");
+                }
+            }
+            
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+    
+    unsafe {
+        let _ = KillTimer(main_hwnd, DESKTOP_POLL_TIMER_ID);
+        let _ = KillTimer(main_hwnd, SWEEP_TIMER_ID);
+        let _ = KillTimer(main_hwnd, CHANGE_DEBOUNCE_TIMER_ID);
+    }
+    
+    // 等待后台线程执行完已提交的动作（虚拟桌面 API 随之释放并取消通知）
+    drop(executor);
+    
+    info!("MacSpaces 退出");
+    Ok(())
+}
+
+/// `mac_spaces ctl ...`：向运行中的实例发送命令，返回进程退出码
+///
+/// 程序是 GUI 子系统，没有自己的控制台，输出写到启动它的命令行窗口
+pub fn run_ctl(args: &[String]) -> i32 {
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+    ipc::run_ctl(&NamedPipeTransport, args)
+}
+
+/// 创建关联状态文件的空间注册表，并接管或清理上次运行时留下的空间
+fn load_registry(vda: &VirtualDesktopAccessor) -> SpaceRegistry {
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.to_path_buf()));
+    let appdata = env::var_os("APPDATA").map(PathBuf::from);
+    
+    let Some(path) = state::default_path(appdata.as_deref(), exe_dir.as_deref()) else {
+        warn!("无法确定状态文件位置，全屏空间不会在重启后恢复");
+        return SpaceRegistry::new();
+    };
+    info!("状态文件: {}", path.display());
+    
+    let store = StateStore::new(path);
+    let saved = match store.load() {
+        Ok(saved) => saved,
+        Err(e) => {
+            warn!("读取状态文件失败，忽略上次的状态: {}", e);
+            None
+        }
+    };
+    
+    let mut registry = SpaceRegistry::with_store(store);
+    if let Some(saved) = saved {
+        let (adopted, removed) = desktop::restore_spaces(vda, &WindowHelper, &mut registry, &saved);
+        info!("恢复全屏空间: 接管 {} 个，删除失效桌面 {} 个", adopted, removed);
+    }
+    registry
+}
+
+/// 创建消息窗口（隐藏窗口，仅用于接收消息）
+fn create_message_window() -> Result<HWND> {
+    unsafe {
+        let instance = GetModuleHandleW(None)?;
+        
+        // 注册窗口类
+        let class_name = wide_string("MacSpacesMessageWindow");
+        
+        let wc = WNDCLASSW {
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(message_window_proc),
+            hInstance: instance.into(),
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+            ..Default::default()
+        };
+        
+        RegisterClassW(&wc);
+        
+        // 创建隐藏窗口
+        let hwnd = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            PCWSTR(class_name.as_ptr()),
+            PCWSTR::null(),
+            WS_OVERLAPPED,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            0,
+            0,
+            None,
+            None,
+            instance,
+            None,
+        )?;
+        
+        Ok(hwnd)
+    }
+}
+
+/// 消息窗口过程
+unsafe extern "system" fn message_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_DESTROY => {
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// 转换为宽字符串
+fn wide_string(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// 获取 DLL 路径
+fn get_dll_path() -> Result<PathBuf> {
+    let exe_dir = env::current_exe()?
+        .parent()
+        .map(|p| p.to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."));
+    
+    // 尝试多个可能的路径
+    let candidates = [
+        exe_dir.join("assets").join("VirtualDesktopAccessor.dll"),
+        exe_dir.join("VirtualDesktopAccessor.dll"),
+        PathBuf::from("assets").join("VirtualDesktopAccessor.dll"),
+    ];
+    
+    for path in &candidates {
+        if path.exists() {
+            return Ok(path.clone());
+        }
+    }
+    
+    // 默认返回第一个候选路径（让后续加载报错）
+    Ok(candidates[0].clone())
+}
+
+/// 显示消息框
+fn show_message_box(title: &str, message: &str) {
+    use windows::Win32::UI::WindowsAndMessaging::{MessageBoxW, MB_ICONINFORMATION, MB_OK};
+    
+    let title_wide = wide_string(title);
+    let msg_wide = wide_string(message);
+    
+    unsafe {
+        MessageBoxW(
+            None,
+            PCWSTR(msg_wide.as_ptr()),
+            PCWSTR(title_wide.as_ptr()),
+            MB_OK | MB_ICONINFORMATION,
+        );
+    }
+}
@@ -0,0 +1,959 @@
+//! 桌面后端抽象
+//!
+//! 将虚拟桌面操作和窗口操作抽象为 trait：
+//! - 真实实现：`VirtualDesktopAccessor`（vda.rs）和 `WindowHelper`（window.rs）
+//! - 测试实现：`fake::FakeBackend`，纯内存模拟，可在非 Windows 平台运行
+
+use crate::fullscreen::WindowSnapshot;
+use crate::keymap::Chord;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(windows)]
+pub use windows::Win32::Foundation::HWND;
+
+/// 窗口句柄（非 Windows 平台下的替身，仅用于单元测试）
+#[cfg(not(windows))]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HWND(pub *mut std::ffi::c_void);
+
+/// 窗口的描述信息（用于持久化和重启后识别窗口）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowDetails {
+    /// 所属进程的可执行文件路径（无法获取时为空）
+    pub process_path: String,
+    /// 窗口类名
+    pub class_name: String,
+    /// 窗口标题
+    pub title: String,
+}
+
+impl WindowDetails {
+    /// 可执行文件名（如 `chrome.exe`，无法获取进程路径时为空）
+    pub fn process_name(&self) -> &str {
+        self.process_path.rsplit(['\\', '/']).next().unwrap_or_default()
+    }
+
+    /// 是否是同一个应用的窗口（比较进程路径和类名，标题可能随时变化）
+    pub fn same_app(&self, other: &WindowDetails) -> bool {
+        self.process_path.eq_ignore_ascii_case(&other.process_path)
+            && self.class_name == other.class_name
+    }
+}
+
+/// 去掉文件名末尾的 `.exe`（不区分大小写）
+pub fn exe_stem(name: &str) -> &str {
+    match name.len().checked_sub(4) {
+        Some(end) if name.is_char_boundary(end) && name[end..].eq_ignore_ascii_case(".exe") => {
+            &name[..end]
+        }
+        _ => name,
+    }
+}
+
+/// 窗口的身份：与句柄一起唯一确定一个窗口
+///
+/// 窗口销毁后句柄会被系统复用，只比较句柄可能把新窗口当成已关闭的窗口；
+/// 进程 ID 同样会被复用，所以还要比较进程的创建时间。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowIdentity {
+    /// 所属进程 ID（0 表示未知，如旧版本的状态文件）
+    pub pid: u32,
+    /// 进程创建时间（FILETIME，无法获取时为 0）
+    pub process_started: u64,
+    /// 窗口类名
+    pub class_name: String,
+}
+
+impl WindowIdentity {
+    /// 当前窗口是否仍是记录的那一个（未知身份总是匹配）
+    pub fn matches(&self, current: &WindowIdentity) -> bool {
+        self.pid == 0 || self == current
+    }
+}
+
+/// 窗口在屏幕上的矩形（像素，不含右边和下边）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl WindowRect {
+    /// 平移
+    pub fn offset(&self, dx: i32, dy: i32) -> Self {
+        Self {
+            left: self.left + dx,
+            top: self.top + dy,
+            right: self.right + dx,
+            bottom: self.bottom + dy,
+        }
+    }
+
+    /// 平移到 `bounds` 之内，比 `bounds` 大时从左上角开始截断
+    pub fn clamp_to(&self, bounds: &WindowRect) -> Self {
+        let width = (self.right - self.left).min(bounds.right - bounds.left);
+        let height = (self.bottom - self.top).min(bounds.bottom - bounds.top);
+        let left = self.left.clamp(bounds.left, bounds.right - width);
+        let top = self.top.clamp(bounds.top, bounds.bottom - height);
+        Self {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        }
+    }
+}
+
+/// 屏幕上的点（像素）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowPoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// 窗口的位置、大小和显示状态（对应 `WINDOWPLACEMENT`），以及所在显示器的矩形
+///
+/// 进入全屏空间前记录，退出时原样恢复，包括是否最大化。
+/// 位置是工作区坐标（相对于显示器工作区的左上角，任务栏在上方或左侧时与屏幕坐标不同），
+/// `(-1, -1)` 表示系统未设置的位置。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowPlacement {
+    /// 是否最大化
+    pub maximized: bool,
+    /// `WINDOWPLACEMENT.flags`（如最小化后还原为最大化）
+    pub flags: u32,
+    /// 最小化时的位置
+    pub min_position: WindowPoint,
+    /// 最大化时的位置
+    pub max_position: WindowPoint,
+    /// 非最大化时的矩形
+    pub normal_position: WindowRect,
+    /// 所在显示器的矩形
+    pub monitor: WindowRect,
+    /// 所在显示器的工作区（不含任务栏，屏幕坐标；旧版本的状态文件中没有，为空矩形）
+    #[serde(default)]
+    pub work_area: WindowRect,
+}
+
+impl WindowPlacement {
+    /// 移到另一个显示器上（原来的显示器已断开、分辨率或任务栏位置已改变时使用），
+    /// 保持窗口相对显示器左上角的位置，放不下时截断到工作区范围内
+    pub fn moved_to(&self, monitor: WindowRect, work_area: WindowRect) -> Self {
+        let old_work = self.saved_work_area();
+        // 工作区坐标 -> 屏幕坐标，随显示器平移，再换算成新工作区的坐标
+        let dx = monitor.left - self.monitor.left;
+        let dy = monitor.top - self.monitor.top;
+        let (to_x, to_y) = (old_work.left + dx - work_area.left, old_work.top + dy - work_area.top);
+        let shift = |p: WindowPoint| {
+            if p.x == -1 && p.y == -1 {
+                return p;
+            }
+            WindowPoint {
+                x: p.x + to_x,
+                y: p.y + to_y,
+            }
+        };
+        let normal_position = self
+            .normal_position
+            .offset(old_work.left + dx, old_work.top + dy)
+            .clamp_to(&work_area)
+            .offset(-work_area.left, -work_area.top);
+        Self {
+            min_position: shift(self.min_position),
+            max_position: shift(self.max_position),
+            normal_position,
+            monitor,
+            work_area,
+            ..*self
+        }
+    }
+
+    /// 记录的工作区，旧版本的状态文件中没有时视为整个显示器
+    fn saved_work_area(&self) -> WindowRect {
+        if self.work_area == WindowRect::default() {
+            self.monitor
+        } else {
+            self.work_area
+        }
+    }
+}
+
+/// 窗口去掉边框之前的样式
+///
+/// 无边框全屏退出时据此恢复（位置和大小由 `WindowPlacement` 恢复）；
+/// 记录在空间注册表中，程序重启后仍然可以恢复。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowFrame {
+    /// 窗口样式（`GWL_STYLE`）
+    pub style: u32,
+    /// 扩展样式（`GWL_EXSTYLE`）
+    pub ex_style: u32,
+}
+
+/// 虚拟桌面的 GUID
+///
+/// 索引会随用户在系统中创建、删除或重排桌面而变化，GUID 在桌面的整个生命周期内不变。
+/// 数值布局与 `windows::core::GUID::to_u128` 一致，文本形式为
+/// `XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DesktopId(pub u128);
+
+impl fmt::Display for DesktopId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = self.0;
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-{:04X}-{:012X}",
+            (v >> 96) as u32,
+            (v >> 80) as u16,
+            (v >> 64) as u16,
+            (v >> 48) as u16,
+            v & 0xFFFF_FFFF_FFFF
+        )
+    }
+}
+
+impl FromStr for DesktopId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().trim_start_matches('{').trim_end_matches('}');
+        let groups: Vec<&str> = trimmed.split('-').collect();
+        let lengths = [8, 4, 4, 4, 12];
+        if groups.len() != lengths.len()
+            || groups.iter().zip(lengths).any(|(g, len)| g.len() != len)
+        {
+            return Err(format!("无效的桌面 GUID: {}", s));
+        }
+        u128::from_str_radix(&groups.concat(), 16)
+            .map(DesktopId)
+            .map_err(|_| format!("无效的桌面 GUID: {}", s))
+    }
+}
+
+impl Serialize for DesktopId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DesktopId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 虚拟桌面后端
+///
+/// 与 VirtualDesktopAccessor.dll 导出的函数一一对应，桌面索引均为 0-based。
+/// 需要长期保存的桌面引用使用 `DesktopId`，只在调用时解析为索引。
+pub trait DesktopBackend {
+    /// 获取桌面总数
+    fn get_desktop_count(&self) -> i32;
+
+    /// 获取当前桌面索引
+    fn get_current_desktop(&self) -> i32;
+
+    /// 切换到指定桌面
+    fn go_to_desktop(&self, index: i32);
+
+    /// 创建新桌面（追加在最后）
+    fn create_desktop(&self);
+
+    /// 删除指定桌面，其上的窗口移动到 `fallback`
+    fn remove_desktop(&self, index: i32, fallback: i32);
+
+    /// 将窗口移动到指定桌面
+    fn move_window_to_desktop(&self, hwnd: HWND, index: i32);
+
+    /// 获取窗口所在的桌面索引（失败返回 -1）
+    fn get_window_desktop(&self, hwnd: HWND) -> i32;
+
+    /// 获取指定索引处桌面的 GUID（索引无效时返回 None）
+    fn get_desktop_id(&self, index: i32) -> Option<DesktopId>;
+
+    /// 获取 GUID 对应桌面的当前索引（桌面已不存在时返回 None）
+    fn get_desktop_index(&self, id: DesktopId) -> Option<i32>;
+
+    /// 获取桌面名称（未命名时为空字符串，DLL 不支持或索引无效时返回 None）
+    fn get_desktop_name(&self, index: i32) -> Option<String>;
+
+    /// 设置桌面名称（DLL 不支持或索引无效时返回 false）
+    fn set_desktop_name(&self, index: i32, name: &str) -> bool;
+
+    /// 将桌面移动到 `target` 位置，其他桌面依次后移或前移
+    /// （DLL 不支持或索引无效时返回 false，桌面顺序不变）
+    fn move_desktop(&self, index: i32, target: i32) -> bool;
+}
+
+/// 窗口操作后端
+pub trait WindowBackend {
+    /// 获取当前活动窗口
+    fn get_active(&self) -> HWND;
+
+    /// 检查窗口是否有效
+    fn is_valid(&self, hwnd: HWND) -> bool;
+
+    /// 检查窗口是否最大化
+    fn is_maximized(&self, hwnd: HWND) -> bool;
+
+    /// 最大化窗口
+    fn maximize(&self, hwnd: HWND);
+
+    /// 还原窗口
+    fn restore(&self, hwnd: HWND);
+
+    /// 发送组合键（切换应用自身的全屏模式，如 F11）
+    fn send_chord(&self, chord: Chord);
+
+    /// 去掉窗口的标题栏和可调边框并铺满所在显示器，返回原来的样式
+    /// （窗口无效或拒绝修改时返回 None）
+    fn make_borderless(&self, hwnd: HWND) -> Option<WindowFrame>;
+
+    /// 恢复 `make_borderless` 之前的样式，不改变位置和大小（失败时返回 false）
+    fn restore_frame(&self, hwnd: HWND, frame: &WindowFrame) -> bool;
+
+    /// 获取窗口的位置、大小、是否最大化和所在显示器（窗口无效时返回 None）
+    fn get_placement(&self, hwnd: HWND) -> Option<WindowPlacement>;
+
+    /// 恢复窗口的位置、大小和最大化状态；原来的显示器已不存在时移到最近的显示器
+    /// （失败时返回 false）
+    fn set_placement(&self, hwnd: HWND, placement: &WindowPlacement) -> bool;
+
+    /// 获取窗口的进程路径、类名和标题
+    fn details(&self, hwnd: HWND) -> WindowDetails;
+
+    /// 获取判断窗口是否全屏所需的样式、矩形和所在显示器（窗口无效时返回 None）
+    fn snapshot(&self, hwnd: HWND) -> Option<WindowSnapshot>;
+
+    /// 获取窗口的进程 ID、进程创建时间和类名（窗口无效时返回 None）
+    fn identity(&self, hwnd: HWND) -> Option<WindowIdentity>;
+
+    /// 移动窗口并调整大小（窗口应处于还原状态，失败时返回 false）
+    fn set_rect(&self, hwnd: HWND, rect: &WindowRect) -> bool;
+
+    /// 获取窗口所在显示器的工作区（不含任务栏，窗口无效时返回 None）
+    fn work_area(&self, hwnd: HWND) -> Option<WindowRect>;
+
+    /// Z 序中位于 `hwnd` 之下的下一个可见的应用窗口（通常是之前的活动窗口，可能在其他桌面上）
+    fn next_window(&self, hwnd: HWND) -> Option<HWND>;
+}
+
+/// 内存模拟后端（测试用）
+#[cfg(test)]
+pub mod fake {
+    use super::{
+        DesktopBackend, DesktopId, WindowBackend, WindowDetails, WindowFrame, WindowIdentity,
+        WindowPlacement, WindowPoint, WindowRect, HWND,
+    };
+    use crate::fullscreen::WindowSnapshot;
+    use crate::keymap::Chord;
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet};
+
+    /// 构造测试用窗口句柄
+    pub fn hwnd(n: isize) -> HWND {
+        HWND(n as *mut std::ffi::c_void)
+    }
+
+    /// 模拟显示器的矩形
+    pub const MONITOR: WindowRect = WindowRect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1080,
+    };
+
+    /// 新窗口的初始矩形
+    pub const WINDOW_RECT: WindowRect = WindowRect {
+        left: 100,
+        top: 100,
+        right: 900,
+        bottom: 700,
+    };
+
+    /// 模拟显示器的工作区（去掉底部 40 像素的任务栏）
+    pub const WORK_AREA: WindowRect = WindowRect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+
+    /// 模拟窗口的初始样式（WS_OVERLAPPEDWINDOW | WS_VISIBLE）
+    const WINDOW_STYLE: u32 = 0x10CF_0000;
+
+    /// 去掉边框后的样式（去掉 WS_CAPTION 和 WS_THICKFRAME）
+    const BORDERLESS_STYLE: u32 = WINDOW_STYLE & !0x00C4_0000;
+
+    /// 最大化时附加的样式（WS_MAXIMIZE）
+    const MAXIMIZED_STYLE: u32 = 0x0100_0000;
+
+    /// 模拟窗口
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct FakeWindow {
+        /// 所在桌面
+        pub desktop: DesktopId,
+        /// 是否最大化
+        pub maximized: bool,
+        /// 是否去掉了边框
+        pub borderless: bool,
+        /// 窗口矩形
+        pub rect: WindowRect,
+        /// 进程路径、类名和标题
+        pub details: WindowDetails,
+        /// 所属进程 ID（每个新窗口都不同，模拟关闭后句柄被新窗口复用）
+        pub pid: u32,
+    }
+
+    #[derive(Debug, Default)]
+    struct State {
+        /// 按顺序排列的桌面
+        desktops: Vec<DesktopId>,
+        names: HashMap<DesktopId, String>,
+        next_id: u128,
+        next_pid: u32,
+        current: usize,
+        windows: HashMap<isize, FakeWindow>,
+        /// 窗口的 Z 序（最上面的在前）
+        z_order: Vec<isize>,
+        active: Option<isize>,
+        /// 发送过的组合键（按顺序）
+        sent_chords: Vec<Chord>,
+        /// 模拟不支持 MoveDesktop 的 DLL
+        move_desktop_unsupported: bool,
+        /// 模拟不支持桌面名称的 DLL
+        names_unsupported: bool,
+        /// 无响应的窗口（不处理最大化、还原和修改边框）
+        unresponsive: HashSet<isize>,
+        /// 以管理员权限运行的窗口（无法移动到其他桌面）
+        elevated: HashSet<isize>,
+    }
+
+    impl State {
+        fn new_id(&mut self) -> DesktopId {
+            self.next_id += 1;
+            DesktopId(0xD000 + self.next_id)
+        }
+
+        fn index_of(&self, id: DesktopId) -> Option<usize> {
+            self.desktops.iter().position(|d| *d == id)
+        }
+    }
+
+    /// 内存中的虚拟桌面模型
+    ///
+    /// 同时实现 `DesktopBackend` 和 `WindowBackend`，行为尽量贴近 DLL：
+    /// 桌面按 GUID 记录，删除桌面时窗口移动到 fallback，后面桌面的索引依次前移
+    pub struct FakeBackend {
+        state: RefCell<State>,
+    }
+
+    impl FakeBackend {
+        /// 创建包含 `desktop_count` 个桌面的模型，当前桌面为 0
+        pub fn new(desktop_count: i32) -> Self {
+            let mut state = State::default();
+            for _ in 0..desktop_count {
+                let id = state.new_id();
+                state.desktops.push(id);
+            }
+            Self {
+                state: RefCell::new(state),
+            }
+        }
+
+        /// 在指定桌面上添加一个窗口
+        pub fn add_window(&self, hwnd: HWND, desktop: i32) {
+            let mut state = self.state.borrow_mut();
+            let desktop = state.desktops[desktop as usize];
+            state.next_pid += 1;
+            let pid = 1000 + state.next_pid;
+            state.z_order.insert(0, hwnd.0 as isize);
+            state.windows.insert(
+                hwnd.0 as isize,
+                FakeWindow {
+                    desktop,
+                    maximized: false,
+                    borderless: false,
+                    rect: WINDOW_RECT,
+                    details: WindowDetails {
+                        process_path: format!("C:\\Apps\\app{:x}.exe", hwnd.0 as isize),
+                        class_name: "FakeWindow".to_string(),
+                        title: String::new(),
+                    },
+                    pid,
+                },
+            );
+        }
+
+        /// 修改窗口的描述信息
+        pub fn set_details(&self, hwnd: HWND, details: WindowDetails) {
+            if let Some(window) = self.state.borrow_mut().windows.get_mut(&(hwnd.0 as isize)) {
+                window.details = details;
+            }
+        }
+
+        /// 关闭窗口
+        pub fn close_window(&self, hwnd: HWND) {
+            let mut state = self.state.borrow_mut();
+            state.windows.remove(&(hwnd.0 as isize));
+            state.z_order.retain(|raw| *raw != hwnd.0 as isize);
+            if state.active == Some(hwnd.0 as isize) {
+                state.active = None;
+            }
+        }
+
+        /// 设置活动窗口（同时移到 Z 序最上面）
+        pub fn set_active(&self, hwnd: HWND) {
+            let mut state = self.state.borrow_mut();
+            let raw = hwnd.0 as isize;
+            state.active = Some(raw);
+            if let Some(position) = state.z_order.iter().position(|r| *r == raw) {
+                state.z_order.remove(position);
+                state.z_order.insert(0, raw);
+            }
+        }
+
+        /// 获取窗口状态
+        pub fn window(&self, hwnd: HWND) -> Option<FakeWindow> {
+            self.state.borrow().windows.get(&(hwnd.0 as isize)).cloned()
+        }
+
+        /// F11 被按下的次数
+        pub fn f11_presses(&self) -> usize {
+            let state = self.state.borrow();
+            state.sent_chords.iter().filter(|c| **c == Chord::F11).count()
+        }
+
+        /// 发送过的全部组合键
+        pub fn sent_chords(&self) -> Vec<Chord> {
+            self.state.borrow().sent_chords.clone()
+        }
+
+        /// 模拟应用自己进入或退出全屏（如双击视频播放器）
+        pub fn set_app_fullscreen(&self, hwnd: HWND, fullscreen: bool) {
+            if let Some(window) = self.state.borrow_mut().windows.get_mut(&(hwnd.0 as isize)) {
+                window.borderless = fullscreen;
+                window.rect = if fullscreen { MONITOR } else { WINDOW_RECT };
+            }
+        }
+
+        /// 模拟用户在系统中重命名桌面
+        pub fn rename_desktop(&self, index: i32, name: &str) {
+            let mut state = self.state.borrow_mut();
+            let id = state.desktops[index as usize];
+            state.names.insert(id, name.to_string());
+        }
+
+        /// 模拟窗口无响应：之后的最大化、还原和修改边框都不生效
+        pub fn set_unresponsive(&self, hwnd: HWND) {
+            self.state.borrow_mut().unresponsive.insert(hwnd.0 as isize);
+        }
+
+        /// 模拟以管理员权限运行的窗口：之后移动到其他桌面都不生效
+        pub fn set_elevated(&self, hwnd: HWND) {
+            self.state.borrow_mut().elevated.insert(hwnd.0 as isize);
+        }
+
+        /// 模拟不支持调整桌面顺序的 DLL
+        pub fn disable_move_desktop(&self) {
+            self.state.borrow_mut().move_desktop_unsupported = true;
+        }
+
+        /// 模拟不支持桌面名称的 DLL
+        pub fn disable_desktop_names(&self) {
+            self.state.borrow_mut().names_unsupported = true;
+        }
+
+        /// 模拟用户在系统中拖动桌面调整顺序
+        pub fn reorder_desktop(&self, from: i32, to: i32) {
+            let mut state = self.state.borrow_mut();
+            let current = state.desktops[state.current];
+            let id = state.desktops.remove(from as usize);
+            state.desktops.insert(to as usize, id);
+            state.current = state.index_of(current).unwrap();
+        }
+    }
+
+    impl DesktopBackend for FakeBackend {
+        fn get_desktop_count(&self) -> i32 {
+            self.state.borrow().desktops.len() as i32
+        }
+
+        fn get_current_desktop(&self) -> i32 {
+            self.state.borrow().current as i32
+        }
+
+        fn go_to_desktop(&self, index: i32) {
+            let mut state = self.state.borrow_mut();
+            if index >= 0 && (index as usize) < state.desktops.len() {
+                state.current = index as usize;
+            }
+        }
+
+        fn create_desktop(&self) {
+            let mut state = self.state.borrow_mut();
+            let id = state.new_id();
+            state.desktops.push(id);
+        }
+
+        fn remove_desktop(&self, index: i32, fallback: i32) {
+            let mut state = self.state.borrow_mut();
+            let count = state.desktops.len() as i32;
+            if count <= 1 || index < 0 || index >= count || fallback < 0 || fallback >= count {
+                return;
+            }
+
+            // 窗口和当前桌面移到 fallback，后面桌面的索引自然前移
+            let removed = state.desktops[index as usize];
+            let fallback_id = state.desktops[fallback as usize];
+            for window in state.windows.values_mut() {
+                if window.desktop == removed {
+                    window.desktop = fallback_id;
+                }
+            }
+            let current = state.desktops[state.current];
+            let current = if current == removed { fallback_id } else { current };
+
+            state.desktops.remove(index as usize);
+            state.current = state.index_of(current).unwrap();
+        }
+
+        fn move_window_to_desktop(&self, hwnd: HWND, index: i32) {
+            let Some(id) = self.get_desktop_id(index) else {
+                return;
+            };
+            let mut state = self.state.borrow_mut();
+            if state.elevated.contains(&(hwnd.0 as isize)) {
+                return;
+            }
+            if let Some(window) = state.windows.get_mut(&(hwnd.0 as isize)) {
+                window.desktop = id;
+            }
+        }
+
+        fn get_window_desktop(&self, hwnd: HWND) -> i32 {
+            self.window(hwnd)
+                .and_then(|w| self.get_desktop_index(w.desktop))
+                .unwrap_or(-1)
+        }
+
+        fn get_desktop_id(&self, index: i32) -> Option<DesktopId> {
+            if index < 0 {
+                return None;
+            }
+            self.state.borrow().desktops.get(index as usize).copied()
+        }
+
+        fn get_desktop_index(&self, id: DesktopId) -> Option<i32> {
+            self.state.borrow().index_of(id).map(|i| i as i32)
+        }
+
+        fn get_desktop_name(&self, index: i32) -> Option<String> {
+            let id = self.get_desktop_id(index)?;
+            let state = self.state.borrow();
+            if state.names_unsupported {
+                return None;
+            }
+            Some(state.names.get(&id).cloned().unwrap_or_default())
+        }
+
+        fn set_desktop_name(&self, index: i32, name: &str) -> bool {
+            if self.state.borrow().names_unsupported || self.get_desktop_id(index).is_none() {
+                return false;
+            }
+            self.rename_desktop(index, name);
+            true
+        }
+
+        fn move_desktop(&self, index: i32, target: i32) -> bool {
+            let count = self.get_desktop_count();
+            if self.state.borrow().move_desktop_unsupported
+                || !(0..count).contains(&index)
+                || !(0..count).contains(&target)
+            {
+                return false;
+            }
+            self.reorder_desktop(index, target);
+            true
+        }
+    }
+
+    impl WindowBackend for FakeBackend {
+        fn get_active(&self) -> HWND {
+            match self.state.borrow().active {
+                Some(raw) => hwnd(raw),
+                None => HWND(std::ptr::null_mut()),
+            }
+        }
+
+        fn is_valid(&self, hwnd: HWND) -> bool {
+            self.state.borrow().windows.contains_key(&(hwnd.0 as isize))
+        }
+
+        fn is_maximized(&self, hwnd: HWND) -> bool {
+            self.window(hwnd).map(|w| w.maximized).unwrap_or(false)
+        }
+
+        fn maximize(&self, hwnd: HWND) {
+            let mut state = self.state.borrow_mut();
+            if state.unresponsive.contains(&(hwnd.0 as isize)) {
+                return;
+            }
+            if let Some(window) = state.windows.get_mut(&(hwnd.0 as isize)) {
+                window.maximized = true;
+            }
+        }
+
+        fn restore(&self, hwnd: HWND) {
+            let mut state = self.state.borrow_mut();
+            if state.unresponsive.contains(&(hwnd.0 as isize)) {
+                return;
+            }
+            if let Some(window) = state.windows.get_mut(&(hwnd.0 as isize)) {
+                window.maximized = false;
+            }
+        }
+
+        fn send_chord(&self, chord: Chord) {
+            self.state.borrow_mut().sent_chords.push(chord);
+        }
+
+        fn make_borderless(&self, hwnd: HWND) -> Option<WindowFrame> {
+            let mut state = self.state.borrow_mut();
+            if state.unresponsive.contains(&(hwnd.0 as isize)) {
+                return None;
+            }
+            let window = state.windows.get_mut(&(hwnd.0 as isize))?;
+            window.borderless = true;
+            window.rect = MONITOR;
+            Some(WindowFrame {
+                style: WINDOW_STYLE,
+                ex_style: 0,
+            })
+        }
+
+        fn restore_frame(&self, hwnd: HWND, _frame: &WindowFrame) -> bool {
+            let mut state = self.state.borrow_mut();
+            if state.unresponsive.contains(&(hwnd.0 as isize)) {
+                return false;
+            }
+            match state.windows.get_mut(&(hwnd.0 as isize)) {
+                Some(window) => {
+                    window.borderless = false;
+                    true
+                }
+                None => false,
+            }
+        }
+
+        fn get_placement(&self, hwnd: HWND) -> Option<WindowPlacement> {
+            let window = self.window(hwnd)?;
+            Some(WindowPlacement {
+                maximized: window.maximized,
+                flags: 0,
+                min_position: WindowPoint { x: -1, y: -1 },
+                max_position: WindowPoint { x: -1, y: -1 },
+                normal_position: window.rect,
+                monitor: MONITOR,
+                work_area: WORK_AREA,
+            })
+        }
+
+        fn set_rect(&self, hwnd: HWND, rect: &WindowRect) -> bool {
+            let mut state = self.state.borrow_mut();
+            if state.unresponsive.contains(&(hwnd.0 as isize)) {
+                return true;
+            }
+            match state.windows.get_mut(&(hwnd.0 as isize)) {
+                Some(window) => {
+                    window.rect = *rect;
+                    true
+                }
+                None => false,
+            }
+        }
+
+        fn work_area(&self, hwnd: HWND) -> Option<WindowRect> {
+            self.window(hwnd).map(|_| WORK_AREA)
+        }
+
+        fn next_window(&self, hwnd: HWND) -> Option<HWND> {
+            let state = self.state.borrow();
+            let position = state.z_order.iter().position(|r| *r == hwnd.0 as isize)?;
+            state.z_order.get(position + 1).map(|raw| super::fake::hwnd(*raw))
+        }
+
+        fn identity(&self, hwnd: HWND) -> Option<WindowIdentity> {
+            let window = self.window(hwnd)?;
+            Some(WindowIdentity {
+                pid: window.pid,
+                process_started: 1,
+                class_name: window.details.class_name,
+            })
+        }
+
+        fn set_placement(&self, hwnd: HWND, placement: &WindowPlacement) -> bool {
+            let mut state = self.state.borrow_mut();
+            // 与最大化和还原一样，无响应的窗口接受请求但不处理
+            let ignored = state.unresponsive.contains(&(hwnd.0 as isize));
+            match state.windows.get_mut(&(hwnd.0 as isize)) {
+                Some(_) if ignored => true,
+                Some(window) => {
+                    window.maximized = placement.maximized;
+                    window.rect = placement.normal_position;
+                    true
+                }
+                None => false,
+            }
+        }
+
+        fn details(&self, hwnd: HWND) -> WindowDetails {
+            self.window(hwnd).map(|w| w.details).unwrap_or_default()
+        }
+
+        fn snapshot(&self, hwnd: HWND) -> Option<WindowSnapshot> {
+            let window = self.window(hwnd)?;
+            let style = if window.borderless { BORDERLESS_STYLE } else { WINDOW_STYLE };
+            let maximized = if window.maximized { MAXIMIZED_STYLE } else { 0 };
+            Some(WindowSnapshot {
+                class_name: window.details.class_name,
+                style: style | maximized,
+                ex_style: 0,
+                visible: true,
+                minimized: false,
+                rect: window.rect,
+                monitor: MONITOR,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::{hwnd, FakeBackend};
+    use super::*;
+
+    #[test]
+    fn test_fake_remove_desktop_moves_windows_to_fallback() {
+        let backend = FakeBackend::new(4);
+        backend.add_window(hwnd(0x100), 2);
+        backend.add_window(hwnd(0x200), 3);
+        backend.go_to_desktop(2);
+
+        backend.remove_desktop(2, 1);
+
+        assert_eq!(backend.get_desktop_count(), 3);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(backend.get_window_desktop(hwnd(0x100)), 1);
+        assert_eq!(backend.get_window_desktop(hwnd(0x200)), 2);
+    }
+
+    #[test]
+    fn test_fake_desktop_ids_survive_reorder() {
+        let backend = FakeBackend::new(3);
+        let ids: Vec<_> = (0..3).map(|i| backend.get_desktop_id(i).unwrap()).collect();
+        backend.add_window(hwnd(0x100), 0);
+
+        backend.reorder_desktop(0, 2);
+
+        assert_eq!(backend.get_desktop_index(ids[0]), Some(2));
+        assert_eq!(backend.get_desktop_index(ids[1]), Some(0));
+        assert_eq!(backend.get_window_desktop(hwnd(0x100)), 2);
+        assert_eq!(backend.get_current_desktop(), 2);
+
+        backend.remove_desktop(2, 0);
+        assert_eq!(backend.get_desktop_index(ids[0]), None);
+        assert_eq!(backend.get_desktop_id(2), None);
+    }
+
+    #[test]
+    fn test_desktop_id_text_round_trip() {
+        let id = DesktopId(0x1234_5678_9ABC_DEF0_0FED_CBA9_8765_4321);
+        let text = id.to_string();
+        assert_eq!(text, "12345678-9ABC-DEF0-0FED-CBA987654321");
+        assert_eq!(text.parse(), Ok(id));
+        assert_eq!(format!("{{{}}}", text.to_lowercase()).parse(), Ok(id));
+        assert!("12345678-9ABC-DEF0-0FED".parse::<DesktopId>().is_err());
+        assert!("1234567G-9ABC-DEF0-0FED-CBA987654321".parse::<DesktopId>().is_err());
+    }
+
+    #[test]
+    fn test_fake_keeps_last_desktop() {
+        let backend = FakeBackend::new(1);
+        backend.remove_desktop(0, 0);
+        assert_eq!(backend.get_desktop_count(), 1);
+    }
+
+    #[test]
+    fn test_placement_moves_to_another_monitor() {
+        let rect = |left, top, right, bottom| WindowRect {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        // 副屏在主屏右侧，任务栏在底部：工作区坐标的原点就是显示器左上角
+        let placement = WindowPlacement {
+            maximized: true,
+            flags: 0,
+            min_position: WindowPoint { x: -1, y: -1 },
+            max_position: WindowPoint { x: 0, y: 0 },
+            normal_position: rect(100, 100, 900, 700),
+            monitor: rect(1920, 0, 3840, 1080),
+            work_area: rect(1920, 0, 3840, 1040),
+        };
+
+        // 副屏断开后移到主屏，保持相对位置和最大化状态，未设置的最小化位置保持不变
+        let moved = placement.moved_to(rect(0, 0, 1920, 1080), rect(0, 0, 1920, 1040));
+        assert_eq!(moved.normal_position, rect(100, 100, 900, 700));
+        assert_eq!(moved.max_position, WindowPoint { x: 0, y: 0 });
+        assert_eq!(moved.min_position, WindowPoint { x: -1, y: -1 });
+        assert!(moved.maximized);
+
+        // 新显示器放不下时截断到工作区范围内
+        let moved = placement.moved_to(rect(0, 0, 640, 480), rect(0, 0, 640, 440));
+        assert_eq!(moved.normal_position, rect(0, 0, 640, 440));
+    }
+
+    #[test]
+    fn test_placement_uses_work_area_coordinates() {
+        let rect = |left, top, right, bottom| WindowRect {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        // 原显示器任务栏在左侧（宽 60），窗口在屏幕上位于 (160, 100)
+        let placement = WindowPlacement {
+            maximized: false,
+            flags: 0,
+            min_position: WindowPoint { x: -1, y: -1 },
+            max_position: WindowPoint { x: -1, y: -1 },
+            normal_position: rect(100, 100, 900, 700),
+            monitor: rect(1920, 0, 3840, 1080),
+            work_area: rect(1980, 0, 3840, 1080),
+        };
+
+        // 新显示器任务栏在顶部（高 40）：屏幕上仍位于显示器的 (160, 100)
+        let moved = placement.moved_to(rect(0, 0, 1920, 1080), rect(0, 40, 1920, 1080));
+        assert_eq!(moved.normal_position, rect(160, 60, 960, 660));
+        assert_eq!(moved.work_area, rect(0, 40, 1920, 1080));
+
+        // 截断时不会跑到任务栏下面
+        let high = WindowPlacement {
+            normal_position: rect(0, -20, 800, 580),
+            ..placement
+        };
+        let moved = high.moved_to(rect(0, 0, 1920, 1080), rect(0, 40, 1920, 1080));
+        assert_eq!(moved.normal_position, rect(60, 0, 860, 600));
+    }
+}
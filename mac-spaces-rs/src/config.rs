@@ -0,0 +1,719 @@
+//! 配置文件
+//!
+//! 从 TOML 文件加载快捷键、时序和动画等可调参数。查找顺序：
+//! 1. 程序所在目录下的 `mac_spaces.toml`
+//! 2. `%APPDATA%\MacSpaces\config.toml`
+//!
+//! 找不到配置文件时使用默认值。所有字段都是可选的，未知字段会报错以便发现拼写错误。
+
+use crate::keymap::Keymap;
+use crate::rules::RuleSet;
+use crate::split;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// 当前支持的配置文件版本
+pub const CONFIG_VERSION: u32 = 1;
+
+/// 程序目录下的配置文件名
+pub const CONFIG_FILE_NAME: &str = "mac_spaces.toml";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("无法读取配置文件 {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("配置文件 {path} 格式错误: {message}")]
+    Parse { path: PathBuf, message: String },
+
+    #[error("不支持的配置文件版本 {found}（当前支持版本 {supported}）")]
+    UnsupportedVersion { found: u32, supported: u32 },
+
+    #[error("配置项 {field} 无效: {message}")]
+    Invalid { field: &'static str, message: String },
+}
+
+/// 全部配置
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// 配置文件版本
+    pub version: u32,
+    /// 快捷键
+    pub hotkeys: HotkeyConfig,
+    /// 时序
+    pub timing: TimingConfig,
+    /// 切换动画
+    pub animation: AnimationConfig,
+    /// 行为选项
+    pub behavior: BehaviorConfig,
+    /// 空间指示器
+    pub indicator: IndicatorConfig,
+    /// 应用规则（按顺序匹配，第一条匹配的规则生效，匹配逻辑见 rules.rs）
+    pub rules: Vec<RuleConfig>,
+}
+
+/// 快捷键配置（组合键字符串，例如 `Win+Ctrl+Left`，语法见 keymap.rs）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HotkeyConfig {
+    /// 切换到左边的桌面
+    pub switch_left: String,
+    /// 切换到右边的桌面
+    pub switch_right: String,
+    /// 切换全屏空间
+    pub toggle_fullscreen: String,
+    /// 将活动窗口移动到左边的桌面
+    pub move_window_left: String,
+    /// 将活动窗口移动到右边的桌面
+    pub move_window_right: String,
+    /// 将活动窗口加入全屏空间
+    pub send_to_space: String,
+    /// 活动窗口和它下面的窗口进入分屏
+    pub split_view: String,
+    /// 分屏的分隔线左移
+    pub split_left: String,
+    /// 分屏的分隔线右移
+    pub split_right: String,
+    /// 直接切换到第 N 个桌面，列表第 i 项对应桌面 #i（空列表表示禁用）
+    pub go_to: Vec<String>,
+}
+
+/// 时序配置
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimingConfig {
+    /// 切换桌面后的等待时间（毫秒），用于等待系统动画完成
+    pub switch_delay_ms: u64,
+    /// 检查桌面列表变化的间隔（毫秒），用于发现在系统中新建、删除或重命名的桌面
+    pub desktop_poll_ms: u64,
+    /// 清理失效空间（窗口已不存在或句柄已被其他窗口复用）的间隔（毫秒）
+    pub sweep_interval_ms: u64,
+    /// 自动全屏空间：窗口位置或状态最后一次变化后等待多久（毫秒）再检查是否全屏，
+    /// 期间同一窗口的多次变化只检查一次
+    pub change_debounce_ms: u64,
+    /// 进入/退出全屏空间时，等待每一步生效（桌面已创建、窗口已移动……）的最长时间（毫秒）
+    pub wait_timeout_ms: u64,
+    /// 等待期间检查状态的间隔（毫秒）
+    pub wait_poll_ms: u64,
+}
+
+/// 切换动画配置
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnimationConfig {
+    /// 是否播放遮罩动画
+    pub enabled: bool,
+    /// 动画时长（毫秒）
+    pub duration_ms: u64,
+    /// 遮罩最大不透明度（0-255）
+    pub max_alpha: u8,
+    /// 每帧间隔（毫秒）
+    pub frame_duration_ms: u64,
+}
+
+/// 行为选项
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BehaviorConfig {
+    /// 移动窗口到相邻桌面后是否跟随切换过去
+    pub follow_moved_window: bool,
+    /// 循环切换：在最后一个桌面向右切换回到第一个，反之亦然
+    pub cyclic_switch: bool,
+    /// 全屏空间的桌面插入位置
+    pub space_placement: SpacePlacement,
+    /// 全屏空间的桌面名称，`{app}` 替换为应用名（为空时不命名）
+    pub space_name: String,
+    /// 应用自己进入全屏（如双击视频播放器）时自动创建全屏空间，退出全屏时删除
+    pub auto_fullscreen_space: bool,
+    /// 全屏空间的窗口最小化或隐藏（如最小化到托盘）时如何处理空间
+    pub hidden_window: HiddenWindowPolicy,
+    /// 分屏时左侧窗口所占的宽度比例
+    pub split_ratio: f64,
+    /// 每次移动分隔线改变的比例
+    pub split_step: f64,
+}
+
+/// 全屏空间的桌面插入位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpacePlacement {
+    /// 紧跟在原桌面右侧（类似 Mac），需要 DLL 提供 MoveDesktop，不支持时退回到最后
+    AfterOriginal,
+    /// 追加在所有桌面最后（默认，随附的 DLL 不能调整桌面顺序）
+    End,
+}
+
+/// 全屏空间的窗口最小化或隐藏时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HiddenWindowPolicy {
+    /// 保留空间（桌面留空）
+    Keep,
+    /// 收起空间：删除桌面但记住空间，窗口恢复显示时重新创建
+    Collapse,
+    /// 删除空间，窗口恢复显示后留在原桌面
+    Remove,
+}
+
+/// 单条应用规则
+///
+/// `process`、`class`、`title` 至少指定一项，指定的条件全部满足时规则匹配
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleConfig {
+    /// 进程名，如 `"chrome.exe"`（不区分大小写，可省略 `.exe`）
+    pub process: Option<String>,
+    /// 窗口类名（完全一致）
+    pub class: Option<String>,
+    /// 窗口标题（正则表达式，匹配标题的任意部分）
+    pub title: Option<String>,
+    /// 不为匹配的窗口创建全屏空间
+    pub exclude: bool,
+    /// 全屏方式
+    pub method: FullscreenMethodConfig,
+    /// `method = "keys"` 时发送的组合键（语法同快捷键）
+    pub keys: Option<String>,
+    /// 切换桌面后的等待时间（毫秒），覆盖 `timing.switch_delay_ms`
+    pub switch_delay_ms: Option<u64>,
+    /// 发送全屏按键后留给应用处理的时间（毫秒）
+    pub settle_ms: Option<u64>,
+}
+
+/// 全屏方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FullscreenMethodConfig {
+    /// 最大化后发送 F11
+    #[default]
+    F11,
+    /// 最大化后发送 `keys` 指定的组合键
+    Keys,
+    /// 只最大化，不发送按键
+    Maximize,
+    /// 去掉标题栏和边框，铺满显示器
+    Borderless,
+}
+
+/// 空间指示器位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndicatorPosition {
+    /// 屏幕顶部居中
+    Top,
+    /// 屏幕底部居中（任务栏上方）
+    Bottom,
+}
+
+/// 颜色，配置文件中写作 `"#RRGGBB"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6)
+            .ok_or_else(|| format!("颜色应写作 #RRGGBB: {}", s))?;
+        let value = u32::from_str_radix(hex, 16).map_err(|_| format!("无效的颜色: {}", s))?;
+        Ok(Self::rgb((value >> 16) as u8, (value >> 8) as u8, value as u8))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// 空间指示器配置（切换桌面时短暂显示的小圆点）
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndicatorConfig {
+    /// 是否显示指示器
+    pub enabled: bool,
+    /// 显示位置
+    pub position: IndicatorPosition,
+    /// 与屏幕边缘的距离（像素）
+    pub margin: i32,
+    /// 圆点直径（像素）
+    pub dot_size: i32,
+    /// 全屏空间图标大小（像素）
+    pub icon_size: i32,
+    /// 圆点之间的间距（像素）
+    pub spacing: i32,
+    /// 圆点与背景边缘的距离（像素）
+    pub padding: i32,
+    /// 完全显示的时长（毫秒）
+    pub display_ms: u64,
+    /// 淡出时长（毫秒）
+    pub fade_ms: u64,
+    /// 最大不透明度（0-255）
+    pub opacity: u8,
+    /// 背景颜色
+    pub background_color: Color,
+    /// 当前桌面圆点颜色
+    pub active_color: Color,
+    /// 其他桌面圆点颜色
+    pub inactive_color: Color,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            hotkeys: HotkeyConfig::default(),
+            timing: TimingConfig::default(),
+            animation: AnimationConfig::default(),
+            behavior: BehaviorConfig::default(),
+            indicator: IndicatorConfig::default(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            switch_left: "Win+Left".to_string(),
+            switch_right: "Win+Right".to_string(),
+            toggle_fullscreen: "Win+F".to_string(),
+            move_window_left: "Win+Shift+Left".to_string(),
+            move_window_right: "Win+Shift+Right".to_string(),
+            send_to_space: "Win+Shift+F".to_string(),
+            split_view: "Win+Alt+F".to_string(),
+            split_left: "Win+Alt+Shift+Left".to_string(),
+            split_right: "Win+Alt+Shift+Right".to_string(),
+            go_to: (1..=9).map(|n| format!("Win+{}", n)).collect(),
+        }
+    }
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Self {
+            switch_delay_ms: 150,
+            desktop_poll_ms: 1000,
+            sweep_interval_ms: 30_000,
+            change_debounce_ms: 150,
+            wait_timeout_ms: 2000,
+            wait_poll_ms: 10,
+        }
+    }
+}
+
+impl Default for BehaviorConfig {
+    fn default() -> Self {
+        Self {
+            follow_moved_window: true,
+            cyclic_switch: false,
+            space_placement: SpacePlacement::End,
+            space_name: "{app} — 全屏".to_string(),
+            auto_fullscreen_space: false,
+            hidden_window: HiddenWindowPolicy::Collapse,
+            split_ratio: split::DEFAULT_RATIO,
+            split_step: 0.05,
+        }
+    }
+}
+
+impl Default for AnimationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            duration_ms: 200,
+            max_alpha: 220,
+            frame_duration_ms: 16, // ~60fps
+        }
+    }
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            position: IndicatorPosition::Top,
+            margin: 24,
+            dot_size: 10,
+            icon_size: 20,
+            spacing: 10,
+            padding: 10,
+            display_ms: 800,
+            fade_ms: 300,
+            opacity: 220,
+            background_color: Color::rgb(0x1E, 0x1E, 0x1E),
+            active_color: Color::rgb(0xFF, 0xFF, 0xFF),
+            inactive_color: Color::rgb(0x70, 0x70, 0x70),
+        }
+    }
+}
+
+impl Config {
+    /// 从默认位置加载配置，返回配置和实际使用的文件路径（未找到文件时为 None）
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn load() -> Result<(Self, Option<PathBuf>), ConfigError> {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf));
+        let appdata = std::env::var_os("APPDATA").map(PathBuf::from);
+
+        for path in candidate_paths(exe_dir.as_deref(), appdata.as_deref()) {
+            if path.exists() {
+                let config = Self::load_file(&path)?;
+                return Ok((config, Some(path)));
+            }
+        }
+
+        Ok((Self::default(), None))
+    }
+
+    /// 从指定文件加载配置
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn load_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        Self::parse_at(&text, path)
+    }
+
+    /// 解析并校验配置文本
+    #[cfg(test)]
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        Self::parse_at(text, Path::new("<text>"))
+    }
+
+    fn parse_at(text: &str, path: &Path) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(text).map_err(|e| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message: e.to_string().trim_end().to_string(),
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 校验配置取值
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.version == 0 || self.version > CONFIG_VERSION {
+            return Err(ConfigError::UnsupportedVersion {
+                found: self.version,
+                supported: CONFIG_VERSION,
+            });
+        }
+
+        Keymap::from_config(&self.hotkeys)?;
+        RuleSet::from_config(&self.rules)?;
+
+        if self.timing.switch_delay_ms > 2000 {
+            return Err(ConfigError::Invalid {
+                field: "timing.switch_delay_ms",
+                message: "不能超过 2000 毫秒".to_string(),
+            });
+        }
+
+        if !(100..=60_000).contains(&self.timing.desktop_poll_ms) {
+            return Err(ConfigError::Invalid {
+                field: "timing.desktop_poll_ms",
+                message: "必须在 100 到 60000 毫秒之间".to_string(),
+            });
+        }
+
+        if !(1_000..=3_600_000).contains(&self.timing.sweep_interval_ms) {
+            return Err(ConfigError::Invalid {
+                field: "timing.sweep_interval_ms",
+                message: "必须在 1000 到 3600000 毫秒之间".to_string(),
+            });
+        }
+
+        if !(10..=2000).contains(&self.timing.change_debounce_ms) {
+            return Err(ConfigError::Invalid {
+                field: "timing.change_debounce_ms",
+                message: "必须在 10 到 2000 毫秒之间".to_string(),
+            });
+        }
+
+        if !(10..=10_000).contains(&self.timing.wait_timeout_ms) {
+            return Err(ConfigError::Invalid {
+                field: "timing.wait_timeout_ms",
+                message: "必须在 10 到 10000 毫秒之间".to_string(),
+            });
+        }
+
+        if self.timing.wait_poll_ms == 0 || self.timing.wait_poll_ms > self.timing.wait_timeout_ms {
+            return Err(ConfigError::Invalid {
+                field: "timing.wait_poll_ms",
+                message: "必须大于 0 且不超过 wait_timeout_ms".to_string(),
+            });
+        }
+
+        if !(split::MIN_RATIO..=split::MAX_RATIO).contains(&self.behavior.split_ratio) {
+            return Err(ConfigError::Invalid {
+                field: "behavior.split_ratio",
+                message: format!("必须在 {} 到 {} 之间", split::MIN_RATIO, split::MAX_RATIO),
+            });
+        }
+
+        if !(self.behavior.split_step > 0.0 && self.behavior.split_step <= 0.5) {
+            return Err(ConfigError::Invalid {
+                field: "behavior.split_step",
+                message: "必须大于 0 且不超过 0.5".to_string(),
+            });
+        }
+
+        let animation = &self.animation;
+        if animation.frame_duration_ms == 0 || animation.frame_duration_ms > 100 {
+            return Err(ConfigError::Invalid {
+                field: "animation.frame_duration_ms",
+                message: "必须在 1 到 100 毫秒之间".to_string(),
+            });
+        }
+        if animation.duration_ms < animation.frame_duration_ms || animation.duration_ms > 2000 {
+            return Err(ConfigError::Invalid {
+                field: "animation.duration_ms",
+                message: format!(
+                    "必须在 {} 到 2000 毫秒之间",
+                    animation.frame_duration_ms
+                ),
+            });
+        }
+
+        let indicator = &self.indicator;
+        for (field, value) in [
+            ("indicator.dot_size", indicator.dot_size),
+            ("indicator.icon_size", indicator.icon_size),
+        ] {
+            if !(1..=128).contains(&value) {
+                return Err(ConfigError::Invalid {
+                    field,
+                    message: "必须在 1 到 128 像素之间".to_string(),
+                });
+            }
+        }
+        for (field, value) in [
+            ("indicator.margin", indicator.margin),
+            ("indicator.spacing", indicator.spacing),
+            ("indicator.padding", indicator.padding),
+        ] {
+            if !(0..=500).contains(&value) {
+                return Err(ConfigError::Invalid {
+                    field,
+                    message: "必须在 0 到 500 像素之间".to_string(),
+                });
+            }
+        }
+        for (field, value) in [
+            ("indicator.display_ms", indicator.display_ms),
+            ("indicator.fade_ms", indicator.fade_ms),
+        ] {
+            if value > 10_000 {
+                return Err(ConfigError::Invalid {
+                    field,
+                    message: "不能超过 10000 毫秒".to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 配置文件候选路径（按优先级排序）
+pub fn candidate_paths(exe_dir: Option<&Path>, appdata: Option<&Path>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(dir) = exe_dir {
+        paths.push(dir.join(CONFIG_FILE_NAME));
+    }
+    if let Some(dir) = appdata {
+        paths.push(dir.join("MacSpaces").join("config.toml"));
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_file_uses_defaults() {
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_partial_override() {
+        let config = Config::parse(
+            r#"
+            version = 1
+
+            [timing]
+            switch_delay_ms = 80
+
+            [animation]
+            enabled = false
+
+            [behavior]
+            hidden_window = "remove"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.timing.switch_delay_ms, 80);
+        assert_eq!(config.behavior.hidden_window, HiddenWindowPolicy::Remove);
+        assert!(!config.behavior.auto_fullscreen_space);
+        assert!(!config.animation.enabled);
+        assert_eq!(config.animation.duration_ms, 200);
+        assert_eq!(config.hotkeys, HotkeyConfig::default());
+    }
+
+    #[test]
+    fn test_unknown_field_is_rejected() {
+        let err = Config::parse("[timing]\nswitch_dealy_ms = 80\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }));
+        assert!(err.to_string().contains("switch_dealy_ms"));
+    }
+
+    #[test]
+    fn test_wrong_type_is_rejected() {
+        let err = Config::parse("[animation]\nmax_alpha = 300\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let err = Config::parse("version = 2\n").unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::UnsupportedVersion { found: 2, supported: CONFIG_VERSION }
+        ));
+    }
+
+    #[test]
+    fn test_invalid_timing() {
+        let err = Config::parse("[animation]\nframe_duration_ms = 0\n").unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid { field: "animation.frame_duration_ms", .. }
+        ));
+
+        let err = Config::parse("[timing]\nswitch_delay_ms = 5000\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "timing.switch_delay_ms", .. }));
+
+        let err = Config::parse("[timing]\nchange_debounce_ms = 0\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "timing.change_debounce_ms", .. }));
+
+        let err = Config::parse("[timing]\nwait_timeout_ms = 100\nwait_poll_ms = 200\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "timing.wait_poll_ms", .. }));
+
+        let err = Config::parse("[behavior]\nsplit_ratio = 0.9\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "behavior.split_ratio", .. }));
+
+        let err = Config::parse("[behavior]\nsplit_step = 0\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "behavior.split_step", .. }));
+    }
+
+    #[test]
+    fn test_invalid_and_conflicting_hotkeys() {
+        let err = Config::parse("[hotkeys]\nswitch_left = \"Win+Foo\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "hotkeys.switch_left", .. }));
+
+        let err = Config::parse("[hotkeys]\ntoggle_fullscreen = \"Win+Right\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "hotkeys.toggle_fullscreen", .. }));
+    }
+
+    #[test]
+    fn test_custom_chords() {
+        let config = Config::parse(
+            "[hotkeys]\nswitch_left = \"Alt+Win+Left\"\ntoggle_fullscreen = \"Win+Ctrl+Shift+F\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.hotkeys.switch_left, "Alt+Win+Left");
+    }
+
+    #[test]
+    fn test_indicator_settings() {
+        let config = Config::parse(
+            "[indicator]\nposition = \"bottom\"\nactive_color = \"#00aaFF\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.indicator.position, IndicatorPosition::Bottom);
+        assert_eq!(config.indicator.active_color, Color::rgb(0x00, 0xAA, 0xFF));
+        assert_eq!(config.indicator.active_color.to_string(), "#00AAFF");
+
+        let err = Config::parse("[indicator]\nactive_color = \"white\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }));
+        let err = Config::parse("[indicator]\nposition = \"left\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }));
+        let err = Config::parse("[indicator]\ndot_size = 0\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "indicator.dot_size", .. }));
+    }
+
+    #[test]
+    fn test_rules_table() {
+        let config = Config::parse(
+            r#"
+            [[rules]]
+            process = "code.exe"
+            method = "maximize"
+
+            [[rules]]
+            class = "mintty"
+            method = "keys"
+            keys = "Alt+Enter"
+            settle_ms = 200
+
+            [[rules]]
+            title = "(?i)remote desktop"
+            exclude = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.rules.len(), 3);
+        assert_eq!(config.rules[0].method, FullscreenMethodConfig::Maximize);
+        assert_eq!(config.rules[1].keys.as_deref(), Some("Alt+Enter"));
+        assert!(config.rules[2].exclude);
+        assert_eq!(config.rules[2].method, FullscreenMethodConfig::F11);
+
+        let err = Config::parse("[[rules]]\nprocess = \"a.exe\"\nmethod = \"zoom\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }));
+        let err = Config::parse("[[rules]]\ntitle = \"[\"\n").unwrap_err();
+        assert!(matches!(err, ConfigError::Invalid { field: "rules.title", .. }));
+    }
+
+    #[test]
+    fn test_candidate_paths_order() {
+        let paths = candidate_paths(Some(Path::new("exe")), Some(Path::new("appdata")));
+        assert_eq!(
+            paths,
+            vec![
+                Path::new("exe").join(CONFIG_FILE_NAME),
+                Path::new("appdata").join("MacSpaces").join("config.toml"),
+            ]
+        );
+        assert!(candidate_paths(None, None).is_empty());
+    }
+}
@@ -2,234 +2,2437 @@
 //! 
 //! 封装虚拟桌面的高级操作
 
-use crate::registry::SpaceRegistry;
-use crate::vda::VirtualDesktopAccessor;
-use crate::window::WindowHelper;
-use std::thread;
+use crate::backend::{
+    exe_stem, DesktopBackend, DesktopId, WindowBackend, WindowDetails, WindowFrame,
+    WindowPlacement, HWND,
+};
+use crate::config::{Config, HiddenWindowPolicy, SpacePlacement};
+use crate::registry::{SpaceInfo, SpaceMember, SpaceRegistry};
+use crate::fullscreen;
+use crate::keymap::Chord;
+use crate::rules::{AppBehavior, FullscreenMethod, RuleSet};
+use crate::split;
+use crate::state::SavedState;
+use crate::transition::{StepError, Transaction, TransitionError, TransitionKind};
+use crate::wait::{Clock, Waiter};
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tracing::{debug, info, warn};
-use windows::Win32::Foundation::HWND;
-use windows::Win32::UI::Input::KeyboardAndMouse::{
-    SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, VK_F11,
-};
 
-/// 切换延迟（等待动画完成）
-const SWITCH_DELAY_MS: u64 = 150;
+/// 方向（切换桌面、移动窗口和动画共用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+/// 相邻桌面的索引，越界时返回 None
+fn neighbor(current: i32, count: i32, direction: Direction) -> Option<i32> {
+    let target = match direction {
+        Direction::Left => current - 1,
+        Direction::Right => current + 1,
+    };
+    (target >= 0 && target < count).then_some(target)
+}
 
-/// 检查是否可以向左切换
-pub fn can_switch_left(vda: &VirtualDesktopAccessor) -> bool {
-    vda.get_current_desktop() > 0
+/// 按方向切换时的目标桌面索引，无法切换时返回 None
+///
+/// `cyclic` 为 true 时首尾相接：最后一个桌面向右回到第一个，第一个桌面向左到最后一个。
+/// 只有一个桌面时无论是否循环都不切换。
+pub fn switch_target(current: i32, count: i32, direction: Direction, cyclic: bool) -> Option<i32> {
+    neighbor(current, count, direction).or_else(|| {
+        (cyclic && count > 1).then(|| match direction {
+            Direction::Left => count - 1,
+            Direction::Right => 0,
+        })
+    })
 }
 
-/// 检查是否可以向右切换
-pub fn can_switch_right(vda: &VirtualDesktopAccessor) -> bool {
-    let current = vda.get_current_desktop();
-    let count = vda.get_desktop_count();
-    current < count - 1
+/// 连续按方向切换 `steps` 次后的目标桌面（正数向右，负数向左），最终没有移动时返回 None
+///
+/// 非循环模式在首尾桌面停下，循环模式绕回另一端
+pub fn switch_target_by(current: i32, count: i32, steps: i32, cyclic: bool) -> Option<i32> {
+    let direction = if steps > 0 { Direction::Right } else { Direction::Left };
+    let mut target = current;
+    for _ in 0..steps.unsigned_abs() {
+        match switch_target(target, count, direction, cyclic) {
+            Some(next) => target = next,
+            None => break,
+        }
+    }
+    (target != current).then_some(target)
 }
 
-/// 切换到左边的桌面
-pub fn switch_left(vda: &VirtualDesktopAccessor) -> bool {
-    let current = vda.get_current_desktop();
+/// 将目标桌面索引限制在有效范围内
+fn clamp_index(index: i32, count: i32) -> i32 {
+    index.clamp(0, (count - 1).max(0))
+}
+
+/// 切换到目标桌面时的动画方向（已经在目标桌面时返回 None）
+///
+/// 目标索引超出范围时按最近的有效桌面计算
+pub fn direction_to(backend: &dyn DesktopBackend, index: i32) -> Option<Direction> {
+    let current = backend.get_current_desktop();
+    let target = clamp_index(index, backend.get_desktop_count());
+    match target.cmp(&current) {
+        std::cmp::Ordering::Less => Some(Direction::Left),
+        std::cmp::Ordering::Greater => Some(Direction::Right),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// 切换到指定桌面（0-based，超出范围时切换到最近的有效桌面）
+///
+/// 已经在目标桌面时不做任何操作并返回 false
+pub fn switch_to(backend: &dyn DesktopBackend, index: i32) -> bool {
+    let current = backend.get_current_desktop();
+    let target = clamp_index(index, backend.get_desktop_count());
     
-    if current > 0 {
-        debug!("切换到左边桌面: {} -> {}", current, current - 1);
-        vda.go_to_desktop(current - 1);
-        true
-    } else {
-        debug!("已经是第一个桌面");
-        false
+    if target == current {
+        debug!("已经在桌面 #{}", current + 1);
+        return false;
+    }
+    
+    debug!("切换到桌面: {} -> {}", current, target);
+    backend.go_to_desktop(target);
+    true
+}
+
+/// 删除桌面时窗口的去处：优先使用 `preferred`，否则使用相邻桌面
+fn fallback_for(index: i32, preferred: Option<i32>) -> i32 {
+    match preferred {
+        Some(p) if p != index => p,
+        _ if index > 0 => index - 1,
+        _ => index + 1,
     }
 }
 
-/// 切换到右边的桌面
-pub fn switch_right(vda: &VirtualDesktopAccessor) -> bool {
-    let current = vda.get_current_desktop();
-    let count = vda.get_desktop_count();
+/// 删除空间创建的桌面（桌面已被用户删除时什么都不做）
+///
+/// 窗口移动到 `fallback` 对应的桌面，`fallback` 也不存在时移动到相邻桌面
+fn remove_space_desktop(backend: &dyn DesktopBackend, desktop: DesktopId, fallback: Option<DesktopId>) {
+    let Some(index) = backend.get_desktop_index(desktop) else {
+        debug!("空间桌面 {} 已不存在", desktop);
+        return;
+    };
+    if backend.get_desktop_count() <= 1 {
+        return;
+    }
     
-    if current < count - 1 {
-        debug!("切换到右边桌面: {} -> {}", current, current + 1);
-        vda.go_to_desktop(current + 1);
-        true
-    } else {
-        debug!("已经是最后一个桌面");
-        false
+    let preferred = fallback.and_then(|id| backend.get_desktop_index(id));
+    backend.remove_desktop(index, fallback_for(index, preferred));
+}
+
+/// 检查活动窗口是否可以移动到相邻桌面
+pub fn can_move_active_window(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    direction: Direction,
+) -> bool {
+    let hwnd = windows.get_active();
+    windows.is_valid(hwnd)
+        && neighbor(backend.get_window_desktop(hwnd), backend.get_desktop_count(), direction).is_some()
+}
+
+/// 将活动窗口移动到相邻桌面
+///
+/// 配置了 `follow_moved_window` 时同时切换到目标桌面。
+/// 如果窗口拥有全屏空间，先退出应用全屏；空间里没有其他窗口时移出后该空间变空：
+/// 删除空间桌面并从注册表移除，此时总会切换到目标桌面（原桌面已不存在）。
+/// 空间里还有其他窗口时窗口只是离开空间。分屏空间先结束分屏，再移动窗口。
+pub fn move_active_window(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    rules: &RuleSet,
+    clock: &dyn Clock,
+    direction: Direction,
+) -> bool {
+    let hwnd = windows.get_active();
+    
+    if !windows.is_valid(hwnd) {
+        warn!("无效的窗口");
+        return false;
+    }
+    
+    if let Some(info) = registry.get(windows, hwnd).filter(|s| s.split.is_some()).cloned() {
+        end_split_view(backend, windows, registry, config, clock, &info);
+    }
+    
+    let current = backend.get_window_desktop(hwnd);
+    let Some(target) = neighbor(current, backend.get_desktop_count(), direction) else {
+        debug!("窗口已经在边缘桌面: #{}", current + 1);
+        return false;
+    };
+    
+    info!("移动窗口到相邻桌面: hwnd={:?}, {} -> {}", hwnd, current, target);
+    let waiter = waiter(config, clock);
+    
+    // 拥有全屏空间的窗口先按进入时的方式退出应用全屏
+    let space = registry.get(windows, hwnd).cloned();
+    let behavior = match &space {
+        Some(info) if info.hwnd == hwnd => rules.resolve(&info.window, &config.timing),
+        _ => rules.resolve(&windows.details(hwnd), &config.timing),
+    };
+    if let Some(info) = space.as_ref().filter(|info| info.hwnd == hwnd) {
+        if let Some(chord) = exit_chord(windows, info, &behavior) {
+            windows.send_chord(chord);
+            waiter.sleep(behavior.settle);
+        }
+        if let Some(frame) = &info.frame {
+            windows.restore_frame(hwnd, frame);
+        }
+        match &info.placement {
+            Some(placement) => {
+                windows.set_placement(hwnd, placement);
+            }
+            None if !info.app_initiated => windows.restore(hwnd),
+            None => {}
+        }
+    }
+    
+    let target_id = backend.get_desktop_id(target);
+    backend.move_window_to_desktop(hwnd, target);
+    
+    // 空间里还有其他窗口时只离开空间，否则空间随之变空
+    let emptied = match space {
+        Some(info) if !registry.leave(windows, hwnd) => Some(info),
+        _ => None,
+    };
+    
+    if config.behavior.follow_moved_window || emptied.is_some() {
+        backend.go_to_desktop(target);
+        waiter.sleep(behavior.switch_delay);
     }
+    
+    // 删除已经变空的全屏空间
+    if let Some(info) = emptied {
+        remove_space_desktop(backend, info.created_desktop, target_id);
+        registry.remove_by_desktop(info.created_desktop);
+        info!("全屏空间已随窗口移出而删除");
+    }
+    
+    true
 }
 
-/// 切换全屏空间
-pub fn toggle_fullscreen(vda: &VirtualDesktopAccessor, registry: &mut SpaceRegistry) {
-    let hwnd = WindowHelper::get_active();
+/// 把活动窗口加入全屏空间（`space` 为空间的桌面），并切换到该空间
+///
+/// 窗口只是移到空间桌面上，不改变大小和状态；已经在其他空间中的窗口不能加入，
+/// 分屏空间也不能再加入窗口。
+/// 成功时返回 true。
+pub fn send_active_window_to_space(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    clock: &dyn Clock,
+    space: DesktopId,
+) -> bool {
+    let hwnd = windows.get_active();
+    if !windows.is_valid(hwnd) {
+        warn!("无效的窗口");
+        return false;
+    }
+    if let Some(info) = registry.get(windows, hwnd) {
+        if info.created_desktop != space {
+            warn!("窗口已在另一个全屏空间中: hwnd={:?}", hwnd);
+        }
+        return false;
+    }
+    if registry.get_by_desktop(space).is_some_and(|s| s.split.is_some()) {
+        warn!("分屏空间不能再加入窗口: 桌面 {}", space);
+        return false;
+    }
+    let Some(index) = backend.get_desktop_index(space) else {
+        warn!("全屏空间的桌面已不存在: {}", space);
+        return false;
+    };
     
-    if !WindowHelper::is_valid(hwnd) {
+    info!("窗口加入全屏空间: hwnd={:?}, 桌面 #{}", hwnd, index + 1);
+    if backend.get_window_desktop(hwnd) != index {
+        backend.move_window_to_desktop(hwnd, index);
+    }
+    if backend.get_current_desktop() != index {
+        backend.go_to_desktop(index);
+        waiter(config, clock).sleep(Duration::from_millis(config.timing.switch_delay_ms));
+    }
+    registry.add_member(space, SpaceMember::new(windows, hwnd))
+}
+
+/// 加入空间的窗口离开空间，回到空间的原桌面（原桌面已不存在时留在空间桌面上）
+fn leave_space(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    info: &SpaceInfo,
+    hwnd: HWND,
+) {
+    info!("窗口离开全屏空间: hwnd={:?}", hwnd);
+    if let Some(index) = backend.get_desktop_index(info.original_desktop) {
+        backend.move_window_to_desktop(hwnd, index);
+    }
+    registry.leave(windows, hwnd);
+}
+
+/// 找分屏的另一个窗口：Z 序中活动窗口之下、在当前桌面上且不在任何空间中的第一个窗口
+///
+/// 通常就是切换到活动窗口之前使用的窗口。找不到时返回 None
+pub fn split_partner(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &SpaceRegistry,
+    hwnd: HWND,
+) -> Option<HWND> {
+    let current = backend.get_current_desktop();
+    let mut candidate = hwnd;
+    // Z 序在查找期间可能变化，限制查找的窗口数量
+    for _ in 0..MAX_SPLIT_CANDIDATES {
+        candidate = windows.next_window(candidate)?;
+        if candidate != hwnd
+            && backend.get_window_desktop(candidate) == current
+            && !registry.contains(windows, candidate)
+        {
+            return Some(candidate);
+        }
+    }
+    None
+}
+
+/// 查找分屏的另一个窗口时最多检查的窗口数量
+const MAX_SPLIT_CANDIDATES: usize = 64;
+
+/// 按比例把分屏的两个窗口并排铺满左侧窗口所在显示器的工作区
+fn tile_windows(windows: &dyn WindowBackend, left: HWND, right: HWND, ratio: f64) -> Result<(), StepError> {
+    let area = windows.work_area(left).ok_or(StepError::WindowClosed)?;
+    let (left_rect, right_rect) = split::split_rects(&area, ratio);
+    for (hwnd, rect) in [(left, left_rect), (right, right_rect)] {
+        if !windows.set_rect(hwnd, &rect) {
+            return Err(if windows.is_valid(hwnd) {
+                StepError::Rejected("调整窗口位置")
+            } else {
+                StepError::WindowClosed
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 记录窗口的位置、大小和最大化状态，最大化的窗口先还原（并排时需要调整大小）
+fn prepare_tile<'a>(
+    tx: &mut Transaction<'a>,
+    windows: &'a dyn WindowBackend,
+    waiter: &'a Waiter<'_>,
+    hwnd: HWND,
+) -> Result<WindowPlacement, TransitionError> {
+    let placement = tx.step("记录窗口位置", || {
+        windows.get_placement(hwnd).ok_or(StepError::WindowClosed)
+    })?;
+    if placement.maximized {
+        tx.step("还原窗口", || restore_window(waiter, windows, hwnd))?;
+        tx.compensate("重新最大化窗口", move || maximize_window(waiter, windows, hwnd));
+    }
+    Ok(placement)
+}
+
+/// 进入分屏：活动窗口（左）和 `right` 左右并排放进同一个新建的全屏空间
+///
+/// 两个窗口都记录原来的位置，结束分屏时恢复；比例取配置的 `split_ratio`。
+/// 已经在全屏空间中的窗口不能分屏。某一步失败时与进入全屏空间一样撤销已完成的步骤。
+pub fn enter_split_view(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    clock: &dyn Clock,
+    right: HWND,
+) -> Result<(), TransitionError> {
+    let left = windows.get_active();
+    if !windows.is_valid(left) || !windows.is_valid(right) || left == right {
         warn!("无效的窗口");
+        return Ok(());
+    }
+    if registry.contains(windows, left) || registry.contains(windows, right) {
+        warn!("窗口已在全屏空间中，不能分屏: {:?}, {:?}", left, right);
+        return Ok(());
+    }
+    
+    info!("进入分屏: 左={:?}, 右={:?}", left, right);
+    let ratio = split::clamp_ratio(config.behavior.split_ratio);
+    let waiter = &waiter(config, clock);
+    let mut tx = Transaction::new(TransitionKind::Enter);
+    
+    // 1. 记录原始桌面和两个窗口原来的位置
+    let original_desktop = tx.step("记录原桌面", || {
+        backend
+            .get_desktop_id(backend.get_current_desktop())
+            .ok_or(StepError::Unavailable("当前桌面"))
+    })?;
+    let left_placement = prepare_tile(&mut tx, windows, waiter, left)?;
+    let right_placement = prepare_tile(&mut tx, windows, waiter, right)?;
+    
+    // 2. 新建桌面，把左侧窗口移过去并切换过去，再把右侧窗口也移过去
+    let (created_desktop, new_desktop) =
+        open_space_desktop(&mut tx, backend, windows, waiter, config, left, original_desktop)?;
+    tx.step("移动另一个窗口到新桌面", || {
+        move_window_to_desktop_id(backend, windows, waiter, right, created_desktop, "窗口移到新桌面")
+    })?;
+    tx.compensate("把另一个窗口移回原桌面", move || {
+        move_window_to_desktop_id(backend, windows, waiter, right, original_desktop, "窗口移回原桌面")
+    });
+    let window = windows.details(left);
+    let name = name_space(backend, config, new_desktop, &window);
+    waiter.sleep(Duration::from_millis(config.timing.switch_delay_ms));
+    
+    // 3. 左右并排
+    tx.compensate("恢复窗口位置", move || {
+        windows.set_placement(left, &left_placement);
+        windows.set_placement(right, &right_placement);
+        Ok(())
+    });
+    tx.step("并排窗口", || tile_windows(windows, left, right, ratio))?;
+    tx.commit();
+    
+    // 4. 注册到空间注册表，右侧窗口作为第一个加入的窗口
+    registry.register(SpaceInfo {
+        name,
+        placement: Some(left_placement),
+        identity: windows.identity(left).unwrap_or_default(),
+        members: vec![SpaceMember {
+            placement: Some(right_placement),
+            ..SpaceMember::new(windows, right)
+        }],
+        split: Some(ratio),
+        ..SpaceInfo::new(left, window, original_desktop, created_desktop)
+    });
+    
+    info!("进入分屏完成: 桌面 #{} ({})", new_desktop + 1, created_desktop);
+    Ok(())
+}
+
+/// 移动分屏的分隔线（`delta` 为比例的变化，正数向右），窗口不在分屏空间中或已到边界时返回 false
+pub fn adjust_split(windows: &dyn WindowBackend, registry: &mut SpaceRegistry, hwnd: HWND, delta: f64) -> bool {
+    let Some(info) = registry.get(windows, hwnd).filter(|s| s.split.is_some()) else {
+        return false;
+    };
+    let (Some(ratio), Some(right)) = (info.split, info.members.first()) else {
+        return false;
+    };
+    let new_ratio = split::clamp_ratio(ratio + delta);
+    if (new_ratio - ratio).abs() < f64::EPSILON {
+        debug!("分隔线已到边界: {:.2}", ratio);
+        return false;
+    }
+    
+    let (left, right, desktop) = (info.hwnd, right.hwnd, info.created_desktop);
+    if let Err(e) = tile_windows(windows, left, right, new_ratio) {
+        warn!("调整分屏比例失败: {}", e);
+        return false;
+    }
+    debug!("分屏比例: {:.2} -> {:.2}", ratio, new_ratio);
+    registry.set_split_ratio(desktop, new_ratio)
+}
+
+/// 结束分屏：两个窗口恢复原来的位置，删除空间桌面（窗口随之回到原桌面）
+///
+/// 任一窗口关闭、最小化、移到其他桌面或按 Win+F 时调用，另一个窗口不会单独留在空间中
+pub fn end_split_view(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    clock: &dyn Clock,
+    info: &SpaceInfo,
+) {
+    info!("结束分屏: 桌面 {}", info.created_desktop);
+    release_split_windows(windows, info);
+    discard_space_desktop(backend, config, &waiter(config, clock), info);
+    registry.remove_by_desktop(info.created_desktop);
+}
+
+/// 分屏空间中仍然存在的窗口恢复进入分屏前的位置（不是分屏空间时什么都不做）
+fn release_split_windows(windows: &dyn WindowBackend, info: &SpaceInfo) {
+    if info.split.is_none() {
         return;
     }
+    if let Some(placement) = &info.placement {
+        if windows.is_valid(info.hwnd) && info.is_same_window(windows) {
+            windows.set_placement(info.hwnd, placement);
+        }
+    }
+    for member in &info.members {
+        if let Some(placement) = &member.placement {
+            if windows.is_valid(member.hwnd) && member.is_same_window(windows) {
+                windows.set_placement(member.hwnd, placement);
+            }
+        }
+    }
+}
+
+/// 窗口所属应用的显示名称
+///
+/// 取可执行文件名（去掉 `.exe`，首字母大写），无法获取进程路径时依次退回到标题和类名
+pub fn app_name(window: &WindowDetails) -> String {
+    let mut chars = exe_stem(window.process_name()).chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None if !window.title.is_empty() => window.title.clone(),
+        None => window.class_name.clone(),
+    }
+}
+
+/// 按模板生成全屏空间的桌面名称（`{app}` 替换为应用名，模板为空时返回 None）
+pub fn space_name(template: &str, window: &WindowDetails) -> Option<String> {
+    let template = template.trim();
+    (!template.is_empty()).then(|| template.replace("{app}", &app_name(window)))
+}
+
+/// 桌面的显示文本：`#3` 或带名称的 `#3 Chrome — 全屏`
+pub fn desktop_label(index: i32, name: Option<&str>) -> String {
+    match name.map(str::trim) {
+        Some(name) if !name.is_empty() => format!("#{} {}", index + 1, name),
+        _ => format!("#{}", index + 1),
+    }
+}
+
+/// 切换全屏空间
+///
+/// 每一步操作后等待其生效再继续。某一步失败时撤销已完成的步骤，
+/// 恢复到切换前的状态并返回错误（见 transition.rs）。
+/// 主窗口退出时空间中的其他窗口随空间桌面的删除一起回到原桌面；
+/// 对加入空间的其他窗口只是把它移出空间。分屏空间的任一窗口都会结束分屏。
+pub fn toggle_fullscreen(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    rules: &RuleSet,
+    clock: &dyn Clock,
+) -> Result<(), TransitionError> {
+    let hwnd = windows.get_active();
+    toggle_window_fullscreen(backend, windows, registry, config, rules, clock, hwnd)
+}
+
+/// 切换指定窗口的全屏空间（命令行控制可以指定不是活动窗口的窗口），流程与 `toggle_fullscreen` 相同
+pub fn toggle_window_fullscreen(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    rules: &RuleSet,
+    clock: &dyn Clock,
+    hwnd: HWND,
+) -> Result<(), TransitionError> {
+    if !windows.is_valid(hwnd) {
+        warn!("无效的窗口");
+        return Ok(());
+    }
     
-    if registry.is_fullscreen_space(hwnd) {
-        exit_fullscreen_space(vda, registry, hwnd);
+    if let Some(info) = registry.get(windows, hwnd).filter(|s| s.split.is_some()).cloned() {
+        end_split_view(backend, windows, registry, config, clock, &info);
+        return Ok(());
+    }
+    
+    let waiter = waiter(config, clock);
+    if registry.is_fullscreen_space(windows, hwnd) {
+        exit_fullscreen_space(backend, windows, registry, config, rules, &waiter, hwnd)
+    } else if let Some(info) = registry.get(windows, hwnd).cloned() {
+        leave_space(backend, windows, registry, &info, hwnd);
+        Ok(())
     } else {
-        enter_fullscreen_space(vda, registry, hwnd);
+        enter_fullscreen_space(backend, windows, registry, config, rules, &waiter, hwnd)
     }
 }
 
-/// 进入全屏空间
-fn enter_fullscreen_space(vda: &VirtualDesktopAccessor, registry: &mut SpaceRegistry, hwnd: HWND) {
-    info!("进入全屏空间: hwnd={:?}", hwnd);
+/// 窗口是否处于全屏状态（见 fullscreen.rs）
+pub fn is_fullscreen(windows: &dyn WindowBackend, hwnd: HWND) -> bool {
+    windows
+        .snapshot(hwnd)
+        .is_some_and(|snapshot| fullscreen::is_fullscreen(&snapshot))
+}
+
+/// 跟随应用自己进入或退出全屏（如双击视频播放器）
+///
+/// 窗口进入全屏时按与 Win+F 相同的流程为它创建全屏空间（窗口本身保持原样）；
+/// 应用自己退出全屏时删除为它创建的空间，把窗口送回原桌面。
+pub fn follow_app_fullscreen(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    rules: &RuleSet,
+    clock: &dyn Clock,
+    hwnd: HWND,
+) -> Result<(), TransitionError> {
+    let fullscreen = is_fullscreen(windows, hwnd);
+    let waiter = waiter(config, clock);
+    match registry.get(windows, hwnd) {
+        None if fullscreen => {
+            info!("应用自己进入了全屏: hwnd={:?}", hwnd);
+            enter_fullscreen_space(backend, windows, registry, config, rules, &waiter, hwnd)
+        }
+        Some(info) if info.hwnd == hwnd && info.app_initiated && !fullscreen => {
+            info!("应用自己退出了全屏: hwnd={:?}", hwnd);
+            exit_fullscreen_space(backend, windows, registry, config, rules, &waiter, hwnd)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 按配置的超时和检查间隔等待
+fn waiter<'a>(config: &Config, clock: &'a dyn Clock) -> Waiter<'a> {
+    Waiter::new(
+        clock,
+        Duration::from_millis(config.timing.wait_timeout_ms),
+        Duration::from_millis(config.timing.wait_poll_ms),
+    )
+}
+
+/// 退出全屏空间时发送的全屏按键
+///
+/// 以无边框方式进入的空间没有按过全屏键，即使规则已经修改也不发送；
+/// 应用自己进入的全屏只在应用还没有自己退出时发送
+fn exit_chord(windows: &dyn WindowBackend, info: &SpaceInfo, behavior: &AppBehavior) -> Option<Chord> {
+    if info.frame.is_some() || (info.app_initiated && !is_fullscreen(windows, info.hwnd)) {
+        return None;
+    }
+    behavior.method.chord()
+}
+
+/// 等待窗口达到某个状态，窗口中途关闭时报告 `WindowClosed` 而不是超时
+fn wait_window(
+    waiter: &Waiter,
+    windows: &dyn WindowBackend,
+    hwnd: HWND,
+    condition: &'static str,
+    check: impl FnMut() -> bool,
+) -> Result<(), StepError> {
+    match waiter.until(condition, check) {
+        Err(_) if !windows.is_valid(hwnd) => Err(StepError::WindowClosed),
+        result => Ok(result?),
+    }
+}
+
+/// 切换到 GUID 对应的桌面并等待切换完成
+fn go_to_desktop_id(
+    backend: &dyn DesktopBackend,
+    waiter: &Waiter,
+    desktop: DesktopId,
+    condition: &'static str,
+) -> Result<(), StepError> {
+    let index = backend.get_desktop_index(desktop).ok_or(StepError::Unavailable("目标桌面"))?;
+    backend.go_to_desktop(index);
+    waiter.until(condition, || backend.get_current_desktop() == index)?;
+    Ok(())
+}
+
+/// 将窗口移动到 GUID 对应的桌面并等待移动完成
+fn move_window_to_desktop_id(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    waiter: &Waiter,
+    hwnd: HWND,
+    desktop: DesktopId,
+    condition: &'static str,
+) -> Result<(), StepError> {
+    let index = backend.get_desktop_index(desktop).ok_or(StepError::Unavailable("目标桌面"))?;
+    backend.move_window_to_desktop(hwnd, index);
+    wait_window(waiter, windows, hwnd, condition, || backend.get_window_desktop(hwnd) == index)
+}
+
+/// 最大化窗口并等待生效
+fn maximize_window(waiter: &Waiter, windows: &dyn WindowBackend, hwnd: HWND) -> Result<(), StepError> {
+    windows.maximize(hwnd);
+    wait_window(waiter, windows, hwnd, "窗口最大化", || windows.is_maximized(hwnd))
+}
+
+/// 还原窗口并等待生效
+fn restore_window(waiter: &Waiter, windows: &dyn WindowBackend, hwnd: HWND) -> Result<(), StepError> {
+    windows.restore(hwnd);
+    wait_window(waiter, windows, hwnd, "窗口还原", || !windows.is_maximized(hwnd))
+}
+
+/// 恢复窗口进入前的位置、大小和最大化状态并等待生效
+fn apply_placement(
+    waiter: &Waiter,
+    windows: &dyn WindowBackend,
+    hwnd: HWND,
+    placement: &WindowPlacement,
+) -> Result<(), StepError> {
+    if !windows.set_placement(hwnd, placement) {
+        return Err(if windows.is_valid(hwnd) {
+            StepError::Rejected("恢复位置")
+        } else {
+            StepError::WindowClosed
+        });
+    }
+    wait_window(waiter, windows, hwnd, "窗口恢复原来的位置", || {
+        windows.is_maximized(hwnd) == placement.maximized
+    })
+}
+
+/// 去掉窗口边框铺满显示器，返回原来的样式
+fn make_borderless(windows: &dyn WindowBackend, hwnd: HWND) -> Result<WindowFrame, StepError> {
+    match windows.make_borderless(hwnd) {
+        Some(frame) => Ok(frame),
+        None if !windows.is_valid(hwnd) => Err(StepError::WindowClosed),
+        None => Err(StepError::Rejected("去掉边框")),
+    }
+}
+
+/// 恢复去掉边框之前的样式
+fn restore_frame(windows: &dyn WindowBackend, hwnd: HWND, frame: &WindowFrame) -> Result<(), StepError> {
+    if windows.restore_frame(hwnd, frame) {
+        Ok(())
+    } else if !windows.is_valid(hwnd) {
+        Err(StepError::WindowClosed)
+    } else {
+        Err(StepError::Rejected("恢复边框"))
+    }
+}
+
+/// 为窗口新建空间桌面，把窗口移过去并切换过去，返回新桌面和它的当前索引
+///
+/// 进入全屏空间和重新展开收起的空间共用；每一步都在 `tx` 中登记补偿操作
+fn open_space_desktop<'a>(
+    tx: &mut Transaction<'a>,
+    backend: &'a dyn DesktopBackend,
+    windows: &'a dyn WindowBackend,
+    waiter: &'a Waiter<'_>,
+    config: &Config,
+    hwnd: HWND,
+    original_desktop: DesktopId,
+) -> Result<(DesktopId, i32), TransitionError> {
+    // 创建新桌面（追加在最后）
+    let created_desktop = tx.step("新建桌面", || {
+        let count_before = backend.get_desktop_count();
+        backend.create_desktop();
+        waiter.until("新桌面创建", || backend.get_desktop_count() > count_before)?;
+        backend
+            .get_desktop_id(count_before)
+            .ok_or(StepError::Unavailable("新建的桌面"))
+    })?;
+    tx.compensate("删除新建的桌面", move || {
+        remove_space_desktop(backend, created_desktop, Some(original_desktop));
+        Ok(())
+    });
     
-    // 1. 记录原始桌面
-    let original_desktop = vda.get_current_desktop();
+    // 按配置把新桌面移到原桌面右侧；空间以 GUID 记录，其他桌面的后移不影响注册表
+    let new_desktop = tx.step("调整新桌面位置", || {
+        let end = backend
+            .get_desktop_index(created_desktop)
+            .ok_or(StepError::Unavailable("新建的桌面"))?;
+        if config.behavior.space_placement != SpacePlacement::AfterOriginal {
+            return Ok(end);
+        }
+        let target = backend
+            .get_desktop_index(original_desktop)
+            .ok_or(StepError::Unavailable("原桌面"))?
+            + 1;
+        if target >= end {
+            return Ok(end);
+        }
+        if !backend.move_desktop(end, target) {
+            debug!("不支持调整桌面顺序，全屏空间保留在最后");
+            return Ok(end);
+        }
+        waiter.until("新桌面移到原桌面右侧", || {
+            backend.get_desktop_index(created_desktop) == Some(target)
+        })?;
+        Ok(target)
+    })?;
     
-    // 2. 如果窗口是最大化的，先还原
-    let was_maximized = WindowHelper::is_maximized(hwnd);
-    if was_maximized {
-        WindowHelper::restore(hwnd);
-        thread::sleep(Duration::from_millis(50));
+    // 移动窗口到新桌面
+    tx.step("移动窗口到新桌面", || {
+        move_window_to_desktop_id(backend, windows, waiter, hwnd, created_desktop, "窗口移到新桌面")
+    })?;
+    tx.compensate("把窗口移回原桌面", move || {
+        move_window_to_desktop_id(backend, windows, waiter, hwnd, original_desktop, "窗口移回原桌面")
+    });
+    
+    // 切换到新桌面
+    tx.step("切换到新桌面", || {
+        go_to_desktop_id(backend, waiter, created_desktop, "切换到新桌面")
+    })?;
+    tx.compensate("切换回原桌面", move || {
+        go_to_desktop_id(backend, waiter, original_desktop, "切换回原桌面")
+    });
+    Ok((created_desktop, new_desktop))
+}
+
+/// 按应用命名空间桌面，返回名称（DLL 不支持名称时跳过，不影响进入全屏；桌面删除时名称随之消失）
+fn name_space(backend: &dyn DesktopBackend, config: &Config, index: i32, window: &WindowDetails) -> String {
+    space_name(&config.behavior.space_name, window)
+        .filter(|name| {
+            let named = backend.set_desktop_name(index, name);
+            if !named {
+                debug!("无法设置桌面名称，全屏空间保持未命名");
+            }
+            named
+        })
+        .unwrap_or_default()
+}
+
+/// 进入全屏空间
+fn enter_fullscreen_space(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    rules: &RuleSet,
+    waiter: &Waiter,
+    hwnd: HWND,
+) -> Result<(), TransitionError> {
+    let window = windows.details(hwnd);
+    let behavior = rules.resolve(&window, &config.timing);
+    if behavior.excluded {
+        info!("应用规则排除了该窗口，不创建全屏空间: {}", app_name(&window));
+        return Ok(());
     }
     
-    // 3. 创建新桌面
-    let count_before = vda.get_desktop_count();
-    vda.create_desktop();
-    thread::sleep(Duration::from_millis(50));
+    info!("进入全屏空间: hwnd={:?}, 方式={:?}", hwnd, behavior.method);
+    let mut tx = Transaction::new(TransitionKind::Enter);
     
-    let new_desktop = count_before; // 新桌面在最后
+    // 1. 记录原始桌面
+    let original_desktop = tx.step("记录原桌面", || {
+        backend
+            .get_desktop_id(backend.get_current_desktop())
+            .ok_or(StepError::Unavailable("当前桌面"))
+    })?;
     
-    // 4. 移动窗口到新桌面
-    vda.move_window_to_desktop(hwnd, new_desktop);
-    thread::sleep(Duration::from_millis(50));
+    // 2. 应用已经自己进入了全屏（视频播放器、游戏等）时保持窗口原样，由应用自己退出；
+    //    否则记录窗口的位置、大小和是否最大化，退出时原样恢复，最大化的窗口先还原
+    let app_initiated = is_fullscreen(windows, hwnd);
+    let placement = if app_initiated {
+        None
+    } else {
+        let placement = tx.step("记录窗口位置", || {
+            windows.get_placement(hwnd).ok_or(StepError::WindowClosed)
+        })?;
+        if placement.maximized {
+            tx.step("还原窗口", || restore_window(waiter, windows, hwnd))?;
+            tx.compensate("重新最大化窗口", move || maximize_window(waiter, windows, hwnd));
+        }
+        Some(placement)
+    };
     
-    // 5. 切换到新桌面
-    vda.go_to_desktop(new_desktop);
-    thread::sleep(Duration::from_millis(SWITCH_DELAY_MS));
+    // 3~5. 新建桌面，把窗口移过去并切换过去，再等系统切换动画结束
+    let (created_desktop, new_desktop) =
+        open_space_desktop(&mut tx, backend, windows, waiter, config, hwnd, original_desktop)?;
+    let name = name_space(backend, config, new_desktop, &window);
+    waiter.sleep(behavior.switch_delay);
     
-    // 6. 最大化窗口
-    WindowHelper::maximize(hwnd);
+    // 6. 最大化窗口；无边框全屏则去掉边框铺满显示器，并记下原来的样式
+    let frame = if app_initiated {
+        None
+    } else if behavior.method == FullscreenMethod::Borderless {
+        Some(tx.step("去掉窗口边框", || make_borderless(windows, hwnd))?)
+    } else {
+        tx.step("最大化窗口", || maximize_window(waiter, windows, hwnd))?;
+        None
+    };
     
-    // 7. 发送 F11 进入应用全屏模式
-    thread::sleep(Duration::from_millis(100));
-    send_f11();
+    // 7. 按规则发送全屏按键进入应用全屏模式（只最大化的应用不发送）
+    if let Some(chord) = behavior.method.chord().filter(|_| !app_initiated) {
+        windows.send_chord(chord);
+    }
+    tx.commit();
     
     // 8. 注册到空间注册表
-    registry.register(hwnd, original_desktop, new_desktop);
+    registry.register(SpaceInfo {
+        name,
+        app_initiated,
+        frame,
+        placement,
+        identity: windows.identity(hwnd).unwrap_or_default(),
+        ..SpaceInfo::new(hwnd, window, original_desktop, created_desktop)
+    });
     
-    info!("进入全屏空间完成: 桌面 #{}", new_desktop + 1);
+    info!("进入全屏空间完成: 桌面 #{} ({})", new_desktop + 1, created_desktop);
+    Ok(())
 }
 
 /// 退出全屏空间
-fn exit_fullscreen_space(vda: &VirtualDesktopAccessor, registry: &mut SpaceRegistry, hwnd: HWND) {
-    let info = match registry.get(hwnd) {
+fn exit_fullscreen_space(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    rules: &RuleSet,
+    waiter: &Waiter,
+    hwnd: HWND,
+) -> Result<(), TransitionError> {
+    let info = match registry.get(windows, hwnd) {
         Some(info) => info.clone(),
         None => {
             warn!("窗口不在注册表中");
-            return;
+            return Ok(());
         }
     };
     
-    info!("退出全屏空间: hwnd={:?}", hwnd);
+    // 按进入时记录的窗口信息匹配规则，标题在全屏期间变化不影响退出方式
+    let behavior = rules.resolve(&info.window, &config.timing);
     
-    let original_desktop = info.original_desktop;
+    info!("退出全屏空间: hwnd={:?}", hwnd);
+    let mut tx = Transaction::new(TransitionKind::Exit);
     let created_desktop = info.created_desktop;
     
-    // 1. 发送 F11 退出应用全屏模式
-    send_f11();
-    thread::sleep(Duration::from_millis(100));
+    // 原桌面可能已被用户删除，此时回到空间桌面旁边的桌面
+    let return_to = backend.get_desktop_index(info.original_desktop).or_else(|| {
+        backend
+            .get_desktop_index(created_desktop)
+            .map(|index| fallback_for(index, None))
+    });
+    let return_to = return_to.and_then(|index| backend.get_desktop_id(index));
     
-    // 2. 还原窗口
-    WindowHelper::restore(hwnd);
-    thread::sleep(Duration::from_millis(50));
+    // 1. 发送全屏按键退出应用全屏模式（应用是否已退出无法观察，留一点处理时间）
+    if let Some(chord) = exit_chord(windows, &info, &behavior) {
+        windows.send_chord(chord);
+        waiter.sleep(behavior.settle);
+        tx.compensate("重新进入应用全屏", move || {
+            windows.send_chord(chord);
+            Ok(())
+        });
+    }
     
-    // 3. 移动窗口回原桌面
-    vda.move_window_to_desktop(hwnd, original_desktop);
-    thread::sleep(Duration::from_millis(50));
+    // 2. 无边框全屏先恢复进入时记录的样式（与当前规则无关）
+    if let Some(frame) = info.frame {
+        tx.step("恢复窗口边框", || restore_frame(windows, hwnd, &frame))?;
+        tx.compensate("重新去掉窗口边框", move || make_borderless(windows, hwnd).map(|_| ()));
+    }
     
-    // 4. 切换到原桌面
-    vda.go_to_desktop(original_desktop);
-    thread::sleep(Duration::from_millis(SWITCH_DELAY_MS));
+    // 3. 恢复进入前的位置、大小和最大化状态（旧版本的状态文件没有记录时只还原最大化）；
+    //    应用自己进入的全屏由应用负责还原窗口
+    match info.placement {
+        Some(placement) => {
+            tx.step("还原窗口", || apply_placement(waiter, windows, hwnd, &placement))?;
+        }
+        None if !info.app_initiated => {
+            tx.step("还原窗口", || restore_window(waiter, windows, hwnd))?;
+        }
+        None => {}
+    }
+    if info.frame.is_none() && !info.app_initiated {
+        tx.compensate("重新最大化窗口", move || maximize_window(waiter, windows, hwnd));
+    }
     
-    // 5. 删除空桌面
-    if vda.get_desktop_count() > 1 {
-        let fallback = if created_desktop > 0 { created_desktop - 1 } else { 0 };
-        vda.remove_desktop(created_desktop, fallback);
+    if let Some(target) = return_to {
+        // 4. 移动窗口回原桌面
+        tx.step("移动窗口回原桌面", || {
+            move_window_to_desktop_id(backend, windows, waiter, hwnd, target, "窗口移回原桌面")
+        })?;
+        tx.compensate("把窗口移回空间桌面", move || {
+            move_window_to_desktop_id(backend, windows, waiter, hwnd, created_desktop, "窗口移回空间桌面")
+        });
         
-        // 更新其他空间的桌面索引
-        registry.update_indices_after_delete(created_desktop);
+        // 5. 切换到原桌面
+        tx.step("切换到原桌面", || {
+            go_to_desktop_id(backend, waiter, target, "切换到原桌面")
+        })?;
+        tx.compensate("切换回空间桌面", move || {
+            go_to_desktop_id(backend, waiter, created_desktop, "切换回空间桌面")
+        });
+        waiter.sleep(behavior.switch_delay);
     }
+    tx.commit();
     
-    // 6. 从注册表移除
-    registry.remove(hwnd);
+    // 6. 删除空桌面
+    remove_space_desktop(backend, created_desktop, Some(info.original_desktop));
     
-    info!("退出全屏空间完成: 返回桌面 #{}", original_desktop + 1);
+    // 7. 从注册表移除
+    registry.remove_by_desktop(created_desktop);
+    
+    info!("退出全屏空间完成: 返回桌面 #{}", backend.get_current_desktop() + 1);
+    Ok(())
 }
 
 /// 处理窗口关闭事件
-pub fn handle_window_closed(vda: &VirtualDesktopAccessor, registry: &mut SpaceRegistry, hwnd: HWND) {
-    // 检查窗口是否在注册表中
-    if !registry.contains(hwnd) {
+pub fn handle_window_closed(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    clock: &dyn Clock,
+    hwnd: HWND,
+) {
+    // 收起的空间没有桌面，丢弃记录即可
+    if registry.take_collapsed(windows, hwnd).is_some() {
+        info!("收起空间的窗口已关闭: hwnd={:?}", hwnd);
+        return;
+    }
+    
+    // 分屏空间随任一窗口关闭而结束
+    if let Some(info) = registry.get(windows, hwnd).filter(|s| s.split.is_some()).cloned() {
+        info!("分屏空间的窗口关闭: hwnd={:?}", hwnd);
+        end_split_view(backend, windows, registry, config, clock, &info);
+        return;
+    }
+    
+    // 空间里还有其他窗口时只离开空间
+    if registry.leave(windows, hwnd) {
+        info!("全屏空间的窗口关闭，空间中还有其他窗口: hwnd={:?}", hwnd);
         return;
     }
     
-    let info = match registry.get(hwnd) {
+    // 检查窗口是否在注册表中（句柄已被新窗口复用时不是这个空间的窗口）
+    let info = match registry.get(windows, hwnd) {
         Some(info) => info.clone(),
         None => return,
     };
     
     info!("检测到全屏空间窗口关闭: hwnd={:?}", hwnd);
+    discard_space_desktop(backend, config, &waiter(config, clock), &info);
     
-    let created_desktop = info.created_desktop;
-    let current_desktop = vda.get_current_desktop();
+    // 从注册表移除
+    registry.remove(windows, hwnd);
+    
+    info!("全屏空间窗口关闭处理完成");
+}
+
+/// 全屏空间的窗口被最小化或隐藏：按配置保留、收起或删除空间
+///
+/// 收起和删除都会删除空间桌面，窗口随之回到原桌面，窗口本身的状态保持不变。
+/// 只看主窗口；空间里还有其他窗口时空间仍在使用，保留空间。
+/// 分屏空间不能收起，除非配置为保留，任一窗口隐藏都会结束分屏。
+pub fn handle_window_hidden(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    clock: &dyn Clock,
+    hwnd: HWND,
+) {
+    let Some(info) = registry.get(windows, hwnd).cloned() else {
+        return;
+    };
+    if info.split.is_some() {
+        if config.behavior.hidden_window != HiddenWindowPolicy::Keep {
+            info!("分屏空间的窗口已隐藏: hwnd={:?}", hwnd);
+            end_split_view(backend, windows, registry, config, clock, &info);
+        }
+        return;
+    }
+    if info.hwnds() != [hwnd] {
+        debug!("空间中还有其他窗口，保留空间: hwnd={:?}", hwnd);
+        return;
+    }
+    
+    match config.behavior.hidden_window {
+        HiddenWindowPolicy::Keep => {
+            debug!("全屏空间的窗口已隐藏，保留空间: hwnd={:?}", hwnd);
+        }
+        HiddenWindowPolicy::Collapse => {
+            info!("全屏空间的窗口已隐藏，收起空间: hwnd={:?}", hwnd);
+            discard_space_desktop(backend, config, &waiter(config, clock), &info);
+            registry.collapse(windows, hwnd);
+        }
+        HiddenWindowPolicy::Remove => {
+            info!("全屏空间的窗口已隐藏，删除空间: hwnd={:?}", hwnd);
+            discard_space_desktop(backend, config, &waiter(config, clock), &info);
+            registry.remove_by_desktop(info.created_desktop);
+        }
+    }
+}
+
+/// 收起空间的窗口恢复显示：重新为它创建空间桌面并切换过去
+///
+/// 窗口仍保持进入全屏空间时的状态，只重新创建桌面；原来记录的窗口位置和样式保留，
+/// 退出时照常恢复。原桌面已被删除时以当前桌面作为原桌面。
+pub fn handle_window_shown(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    rules: &RuleSet,
+    clock: &dyn Clock,
+    hwnd: HWND,
+) -> Result<(), TransitionError> {
+    if !windows.is_valid(hwnd) {
+        return Ok(());
+    }
+    let Some(info) = registry.take_collapsed(windows, hwnd) else {
+        return Ok(());
+    };
+    
+    info!("收起空间的窗口恢复显示，重新创建空间: hwnd={:?}", hwnd);
+    let behavior = rules.resolve(&info.window, &config.timing);
+    let waiter = waiter(config, clock);
+    let mut tx = Transaction::new(TransitionKind::Enter);
     
+    let original_desktop = match backend.get_desktop_index(info.original_desktop) {
+        Some(_) => info.original_desktop,
+        None => tx.step("记录原桌面", || {
+            backend
+                .get_desktop_id(backend.get_current_desktop())
+                .ok_or(StepError::Unavailable("当前桌面"))
+        })?,
+    };
+    let result = open_space_desktop(&mut tx, backend, windows, &waiter, config, hwnd, original_desktop);
+    let (created_desktop, new_desktop) = match result {
+        Ok(created) => created,
+        Err(e) => {
+            // 重新展开失败时继续记住空间，下次显示时再试
+            registry.adopt_collapsed(info);
+            return Err(e);
+        }
+    };
+    let name = name_space(backend, config, new_desktop, &info.window);
+    waiter.sleep(behavior.switch_delay);
+    tx.commit();
+    
+    registry.adopt(SpaceInfo {
+        original_desktop,
+        created_desktop,
+        name,
+        ..info
+    });
+    
+    info!("空间已重新展开: 桌面 #{} ({})", new_desktop + 1, created_desktop);
+    Ok(())
+}
+
+/// 清理窗口已不存在或句柄已被其他窗口复用的空间，删除它们的桌面
+///
+/// 正常情况下窗口关闭时就会收到销毁事件；事件丢失（如钩子线程繁忙）时由定时清理兜底。
+/// 返回清理的空间数量。
+pub fn sweep_spaces(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    config: &Config,
+    clock: &dyn Clock,
+) -> usize {
+    let waiter = waiter(config, clock);
+    let stale = registry.sweep(windows);
+    for info in &stale {
+        warn!("全屏空间的窗口已不存在，删除空间: hwnd={:?}, 桌面 {}", info.hwnd, info.created_desktop);
+        release_split_windows(windows, info);
+        discard_space_desktop(backend, config, &waiter, info);
+    }
+    stale.len()
+}
+
+/// 删除空间桌面；当前正在这个桌面上时先切换走
+fn discard_space_desktop(backend: &dyn DesktopBackend, config: &Config, waiter: &Waiter, info: &SpaceInfo) {
     // 如果当前在即将删除的桌面上，先切换走
-    if current_desktop == created_desktop {
-        let target = if created_desktop > 0 { created_desktop - 1 } else { 0 };
-        vda.go_to_desktop(target);
-        thread::sleep(Duration::from_millis(SWITCH_DELAY_MS));
+    if let Some(created_index) = backend.get_desktop_index(info.created_desktop) {
+        if backend.get_current_desktop() == created_index {
+            let preferred = backend.get_desktop_index(info.original_desktop);
+            backend.go_to_desktop(fallback_for(created_index, preferred));
+            waiter.sleep(Duration::from_millis(config.timing.switch_delay_ms));
+        }
     }
     
     // 删除空桌面
-    if vda.get_desktop_count() > 1 {
-        let fallback = if created_desktop > 0 { created_desktop - 1 } else { 0 };
-        vda.remove_desktop(created_desktop, fallback);
+    remove_space_desktop(backend, info.created_desktop, Some(info.original_desktop));
+}
+
+/// 恢复上次运行时保存的全屏空间
+///
+/// 窗口仍然存在（身份一致）、属于同一个应用且还在它的空间桌面上时重新接管；收起的空间只要窗口还在就继续记住。
+/// 分屏空间要求两个窗口都还在，否则结束分屏。
+/// 否则认为空间已失效，删除它创建的桌面（桌面已不存在时只丢弃记录）。
+/// 返回 (接管数量, 删除的桌面数量)
+pub fn restore_spaces(
+    backend: &dyn DesktopBackend,
+    windows: &dyn WindowBackend,
+    registry: &mut SpaceRegistry,
+    saved: &SavedState,
+) -> (usize, usize) {
+    let mut adopted = 0;
+    let mut stale = Vec::new();
+    
+    for space in &saved.spaces {
+        let mut info = SpaceInfo::from_saved(space);
+        let hwnd = info.hwnd;
+        let created_index = backend.get_desktop_index(info.created_desktop);
+        // 加入空间的窗口同样要求还在空间桌面上，分屏空间的两个窗口缺一不可
+        let member_alive = |m: &SpaceMember| {
+            windows.is_valid(m.hwnd)
+                && m.is_same_window(windows)
+                && Some(backend.get_window_desktop(m.hwnd)) == created_index
+        };
+        let alive = created_index.is_some()
+            && windows.is_valid(hwnd)
+            && info.is_same_window(windows)
+            && windows.details(hwnd).same_app(&info.window)
+            && Some(backend.get_window_desktop(hwnd)) == created_index
+            && (info.split.is_none() || info.members.iter().all(member_alive));
         
-        // 更新其他空间的桌面索引
-        registry.update_indices_after_delete(created_desktop);
+        if alive {
+            info!("接管全屏空间: hwnd={:?}, 桌面 {}", hwnd, info.created_desktop);
+            info.members.retain(member_alive);
+            remember_identity(windows, &mut info);
+            registry.adopt(info);
+            adopted += 1;
+        } else {
+            stale.push(info);
+        }
     }
     
-    // 从注册表移除
-    registry.remove(hwnd);
+    // 收起的空间没有桌面，窗口还在就继续记住
+    for space in &saved.collapsed {
+        let mut info = SpaceInfo::from_saved(space);
+        if windows.is_valid(info.hwnd)
+            && info.is_same_window(windows)
+            && windows.details(info.hwnd).same_app(&info.window)
+        {
+            remember_identity(windows, &mut info);
+            registry.adopt_collapsed(info);
+        } else {
+            debug!("丢弃失效的收起空间: hwnd={:?}", info.hwnd);
+        }
+    }
     
-    info!("全屏空间窗口关闭处理完成");
+    let mut removed = 0;
+    for info in &stale {
+        if backend.get_desktop_index(info.created_desktop).is_none()
+            || registry.get_by_desktop(info.created_desktop).is_some()
+        {
+            warn!("丢弃失效的空间记录: hwnd={:?}, 桌面 {}", info.hwnd, info.created_desktop);
+            continue;
+        }
+        
+        info!("删除失效空间的桌面 {}: hwnd={:?}", info.created_desktop, info.hwnd);
+        release_split_windows(windows, info);
+        remove_space_desktop(backend, info.created_desktop, Some(info.original_desktop));
+        removed += 1;
+    }
+    
+    registry.persist();
+    (adopted, removed)
+}
+
+/// 旧版本的状态文件没有记录窗口身份，接管时补上
+fn remember_identity(windows: &dyn WindowBackend, info: &mut SpaceInfo) {
+    if info.identity.pid == 0 {
+        info.identity = windows.identity(info.hwnd).unwrap_or_default();
+    }
 }
 
-/// 发送 F11 按键
-fn send_f11() {
-    unsafe {
-        let mut inputs = [
-            INPUT {
-                r#type: INPUT_KEYBOARD,
-                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                    ki: KEYBDINPUT {
-                        wVk: VK_F11,
-                        wScan: 0,
-                        dwFlags: windows::Win32::UI::Input::KeyboardAndMouse::KEYBD_EVENT_FLAGS(0),
-                        time: 0,
-                        dwExtraInfo: 0,
-                    },
-                },
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{hwnd, FakeBackend, MONITOR, WINDOW_RECT, WORK_AREA};
+    use crate::backend::WindowRect;
+    use crate::wait::fake::FakeClock;
+    use crate::config::{FullscreenMethodConfig, RuleConfig};
+    use crate::keymap::Chord;
+    use crate::rules::DEFAULT_SETTLE;
+    use crate::wait::WaitError;
+
+    /// 测试不需要等待系统动画；模拟后端支持调整桌面顺序，全屏空间紧跟在原桌面右侧
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.timing.switch_delay_ms = 0;
+        config.behavior.space_placement = SpacePlacement::AfterOriginal;
+        config
+    }
+
+    /// 全屏空间追加在最后
+    fn config_at_end() -> Config {
+        let mut config = config();
+        config.behavior.space_placement = SpacePlacement::End;
+        config
+    }
+
+    #[test]
+    fn test_switch_stops_at_edges() {
+        let backend = FakeBackend::new(2);
+        
+        assert_eq!(switch_target_by(0, 2, -1, false), None);
+        let target = switch_target_by(0, 2, 1, false).unwrap();
+        assert!(switch_to(&backend, target));
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(switch_target_by(1, 2, 1, false), None);
+    }
+
+    #[test]
+    fn test_switch_target_wraps_when_cyclic() {
+        // 中间的桌面不受循环模式影响
+        assert_eq!(switch_target(1, 3, Direction::Left, true), Some(0));
+        assert_eq!(switch_target(1, 3, Direction::Right, true), Some(2));
+        
+        // 首尾相接
+        assert_eq!(switch_target(2, 3, Direction::Right, true), Some(0));
+        assert_eq!(switch_target(0, 3, Direction::Left, true), Some(2));
+        assert_eq!(switch_target(2, 3, Direction::Right, false), None);
+        assert_eq!(switch_target(0, 3, Direction::Left, false), None);
+        
+        // 两个桌面时左右都到另一个
+        assert_eq!(switch_target(0, 2, Direction::Left, true), Some(1));
+        assert_eq!(switch_target(1, 2, Direction::Right, true), Some(0));
+        
+        // 只有一个桌面时不切换
+        assert_eq!(switch_target(0, 1, Direction::Left, true), None);
+        assert_eq!(switch_target(0, 1, Direction::Right, true), None);
+    }
+
+    #[test]
+    fn test_switch_target_by_multiple_steps() {
+        assert_eq!(switch_target_by(0, 5, 3, false), Some(3));
+        assert_eq!(switch_target_by(3, 5, -2, false), Some(1));
+        // 非循环时在边缘停下
+        assert_eq!(switch_target_by(3, 5, 4, false), Some(4));
+        assert_eq!(switch_target_by(4, 5, 1, false), None);
+        // 循环时绕回，转满一圈等于没动
+        assert_eq!(switch_target_by(3, 5, 4, true), Some(2));
+        assert_eq!(switch_target_by(1, 3, 3, true), None);
+        assert_eq!(switch_target_by(1, 3, 0, true), None);
+    }
+
+    #[test]
+    fn test_cyclic_switch_wraps_around() {
+        let backend = FakeBackend::new(3);
+        backend.go_to_desktop(2);
+        
+        let target = switch_target_by(2, 3, 1, true).unwrap();
+        assert!(switch_to(&backend, target));
+        assert_eq!(backend.get_current_desktop(), 0);
+        
+        let target = switch_target_by(0, 3, -1, true).unwrap();
+        assert!(switch_to(&backend, target));
+        assert_eq!(backend.get_current_desktop(), 2);
+    }
+
+    #[test]
+    fn test_switch_to_clamps_and_picks_direction() {
+        let backend = FakeBackend::new(3);
+        backend.go_to_desktop(1);
+        
+        assert_eq!(direction_to(&backend, 1), None);
+        assert!(!switch_to(&backend, 1));
+        
+        assert_eq!(direction_to(&backend, 0), Some(Direction::Left));
+        assert_eq!(direction_to(&backend, 8), Some(Direction::Right));
+        assert!(switch_to(&backend, 8));
+        assert_eq!(backend.get_current_desktop(), 2);
+        
+        // 超出范围的目标被限制到最后一个桌面，此时已经在那里
+        assert_eq!(direction_to(&backend, 5), None);
+        assert!(!switch_to(&backend, 5));
+        
+        assert!(switch_to(&backend, 0));
+        assert_eq!(backend.get_current_desktop(), 0);
+    }
+
+    #[test]
+    fn test_move_window_with_and_without_follow() {
+        let backend = FakeBackend::new(3);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 1);
+        backend.go_to_desktop(1);
+        backend.set_active(app);
+        
+        let clock = FakeClock::new();
+        let mut config = Config::default();
+        config.behavior.follow_moved_window = false;
+        assert!(move_active_window(&backend, &backend, &mut registry, &config, &RuleSet::default(), &clock, Direction::Right));
+        assert_eq!(backend.get_window_desktop(app), 2);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(clock.sleeps(), 0);
+        
+        // 跟随时等待切换动画，经由时钟而不是真正睡眠
+        config.behavior.follow_moved_window = true;
+        assert!(move_active_window(&backend, &backend, &mut registry, &config, &RuleSet::default(), &clock, Direction::Left));
+        assert_eq!(backend.get_window_desktop(app), 1);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(clock.elapsed(), Duration::from_millis(config.timing.switch_delay_ms));
+    }
+
+    #[test]
+    fn test_move_window_stops_at_edges() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        
+        assert!(!can_move_active_window(&backend, &backend, Direction::Left));
+        assert!(!move_active_window(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new(), Direction::Left));
+        assert_eq!(backend.get_window_desktop(app), 0);
+        assert_eq!(backend.get_current_desktop(), 0);
+    }
+
+    #[test]
+    fn test_move_window_out_of_fullscreen_space_removes_it() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        let other = hwnd(0x200);
+        backend.add_window(app, 0);
+        backend.add_window(other, 1);
+        
+        // 两个全屏空间：app -> #3，other -> #4
+        backend.set_active(app);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config_at_end(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        backend.go_to_desktop(1);
+        backend.set_active(other);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config_at_end(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        assert_eq!(backend.get_desktop_count(), 4);
+        
+        // 把 app 从它的空间 (#3) 移到左边的 #2，不跟随
+        backend.go_to_desktop(2);
+        backend.set_active(app);
+        let mut config = config();
+        config.behavior.follow_moved_window = false;
+        assert!(move_active_window(&backend, &backend, &mut registry, &config, &RuleSet::default(), &FakeClock::new(), Direction::Left));
+        
+        assert_eq!(backend.get_desktop_count(), 3);
+        assert_eq!(backend.get_window_desktop(app), 1);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert!(!backend.is_maximized(app));
+        assert!(!registry.contains(&backend, app));
+        assert_eq!(backend.get_desktop_index(registry.get(&backend, other).unwrap().created_desktop), Some(2));
+        assert_eq!(backend.get_window_desktop(other), 2);
+    }
+
+    #[test]
+    fn test_enter_fullscreen_creates_space() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config_at_end(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_desktop_count(), 3);
+        assert_eq!(backend.get_current_desktop(), 2);
+        assert_eq!(backend.get_window_desktop(app), 2);
+        assert!(backend.is_maximized(app));
+        assert_eq!(backend.f11_presses(), 1);
+        
+        let info = registry.get(&backend, app).unwrap();
+        assert_eq!(backend.get_desktop_index(info.original_desktop), Some(0));
+        assert_eq!(backend.get_desktop_index(info.created_desktop), Some(2));
+    }
+
+    #[test]
+    fn test_exit_restores_placement_and_maximized_state() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        let normal = WindowRect {
+            left: 300,
+            top: 200,
+            right: 1100,
+            bottom: 800,
+        };
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        backend.set_placement(
+            app,
+            &WindowPlacement {
+                maximized: true,
+                normal_position: normal,
+                ..backend.get_placement(app).unwrap()
+            },
+        );
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        let placement = registry.get(&backend, app).unwrap().placement.unwrap();
+        assert!(placement.maximized);
+        assert_eq!(placement.normal_position, normal);
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        // 回到原桌面后仍是最大化的，还原时回到原来的位置和大小
+        let window = backend.window(app).unwrap();
+        assert!(window.maximized);
+        assert_eq!(window.rect, normal);
+        assert_eq!(backend.get_window_desktop(app), 0);
+    }
+
+    #[test]
+    fn test_enter_names_space_after_app() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_details(
+            app,
+            WindowDetails {
+                process_path: r"C:\Program Files\Google\Chrome\Application\chrome.exe".to_string(),
+                class_name: "Chrome_WidgetWin_1".to_string(),
+                title: "新标签页".to_string(),
+            },
+        );
+        backend.set_active(app);
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_desktop_name(1).as_deref(), Some("Chrome — 全屏"));
+        assert_eq!(registry.get(&backend, app).unwrap().name, "Chrome — 全屏");
+    }
+
+    #[test]
+    fn test_enter_without_desktop_names_leaves_space_unnamed() {
+        let backend = FakeBackend::new(2);
+        backend.disable_desktop_names();
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_window_desktop(app), 1);
+        assert_eq!(registry.get(&backend, app).unwrap().name, "");
+    }
+
+    #[test]
+    fn test_space_and_desktop_names() {
+        let window = |process_path: &str, title: &str| WindowDetails {
+            process_path: process_path.to_string(),
+            class_name: "Notepad".to_string(),
+            title: title.to_string(),
+        };
+        
+        assert_eq!(app_name(&window(r"C:\Windows\notepad.EXE", "")), "Notepad");
+        assert_eq!(app_name(&window(r"C:\Apps\微信", "")), "微信");
+        assert_eq!(app_name(&window("", "无标题 - 记事本")), "无标题 - 记事本");
+        assert_eq!(app_name(&window("", "")), "Notepad");
+        
+        assert_eq!(
+            space_name("{app} — 全屏", &window(r"C:\Apps\code.exe", "")).as_deref(),
+            Some("Code — 全屏")
+        );
+        assert_eq!(space_name("  ", &window(r"C:\Apps\code.exe", "")), None);
+        
+        assert_eq!(desktop_label(2, Some("Code — 全屏")), "#3 Code — 全屏");
+        assert_eq!(desktop_label(0, Some("")), "#1");
+        assert_eq!(desktop_label(0, None), "#1");
+    }
+
+    /// 按进程名匹配 `app` 窗口（模拟后端的进程路径为 `C:\Apps\app{hwnd:x}.exe`）
+    fn rules_for(process: &str, rule: RuleConfig) -> RuleSet {
+        RuleSet::from_config(&[RuleConfig {
+            process: Some(process.to_string()),
+            ..rule
+        }])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_maximize_rule_sends_no_keys() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        let rules = rules_for(
+            "app100.exe",
+            RuleConfig {
+                method: FullscreenMethodConfig::Maximize,
+                ..Default::default()
+            },
+        );
+        let clock = FakeClock::new();
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &rules, &clock).unwrap();
+        assert!(backend.is_maximized(app));
+        assert_eq!(backend.get_window_desktop(app), 1);
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &rules, &clock).unwrap();
+        assert!(!backend.is_maximized(app));
+        assert_eq!(backend.get_window_desktop(app), 0);
+        assert!(backend.sent_chords().is_empty());
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_custom_chord_and_delays_from_rule() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        let rules = rules_for(
+            "APP100",
+            RuleConfig {
+                method: FullscreenMethodConfig::Keys,
+                keys: Some("Alt+Enter".to_string()),
+                switch_delay_ms: Some(40),
+                settle_ms: Some(300),
+                ..Default::default()
+            },
+        );
+        let clock = FakeClock::new();
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &rules, &clock).unwrap();
+        assert_eq!(clock.elapsed(), Duration::from_millis(40));
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &rules, &clock).unwrap();
+        assert_eq!(clock.elapsed(), Duration::from_millis(40 + 300 + 40));
+        
+        let alt_enter: Chord = "Alt+Enter".parse().unwrap();
+        assert_eq!(backend.sent_chords(), vec![alt_enter, alt_enter]);
+        assert_eq!(backend.f11_presses(), 0);
+    }
+
+    #[test]
+    fn test_excluded_app_gets_no_space() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        let rules = rules_for(
+            "app100.exe",
+            RuleConfig {
+                exclude: true,
+                ..Default::default()
             },
-            INPUT {
-                r#type: INPUT_KEYBOARD,
-                Anonymous: windows::Win32::UI::Input::KeyboardAndMouse::INPUT_0 {
-                    ki: KEYBDINPUT {
-                        wVk: VK_F11,
-                        wScan: 0,
-                        dwFlags: KEYEVENTF_KEYUP,
-                        time: 0,
-                        dwExtraInfo: 0,
-                    },
-                },
+        );
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &rules, &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert!(!registry.contains(&backend, app));
+        assert!(!backend.is_maximized(app));
+        assert!(backend.sent_chords().is_empty());
+    }
+
+    fn borderless_rules() -> RuleSet {
+        rules_for(
+            "app100.exe",
+            RuleConfig {
+                method: FullscreenMethodConfig::Borderless,
+                ..Default::default()
             },
-        ];
+        )
+    }
+
+    #[test]
+    fn test_borderless_frame_is_restored_after_restart() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        let rules = borderless_rules();
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &rules, &FakeClock::new()).unwrap();
+        let window = backend.window(app).unwrap();
+        assert!(window.borderless);
+        assert_eq!(window.rect, MONITOR);
+        assert!(!window.maximized);
+        assert!(backend.sent_chords().is_empty());
+        assert_eq!(registry.get(&backend, app).unwrap().placement.unwrap().normal_position, WINDOW_RECT);
+        
+        // 原来的样式和位置随状态文件保存，重启后退出仍能恢复
+        let (mut registry, _) = restart(&backend, &registry);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        let window = backend.window(app).unwrap();
+        assert!(!window.borderless);
+        assert_eq!(window.rect, WINDOW_RECT);
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert!(backend.sent_chords().is_empty());
+    }
+
+    #[test]
+    fn test_rejected_borderless_rolls_back() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        backend.set_unresponsive(app);
+        
+        let err = toggle_fullscreen(&backend, &backend, &mut registry, &config(), &borderless_rules(), &FakeClock::new())
+            .unwrap_err();
+        
+        assert_eq!(err.step, "去掉窗口边框");
+        assert_eq!(err.cause, StepError::Rejected("去掉边框"));
+        assert!(err.rolled_back());
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert_eq!(backend.get_window_desktop(app), 0);
+        assert_eq!(backend.window(app).unwrap().rect, WINDOW_RECT);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_window_already_fullscreen_is_moved_as_is() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        backend.set_app_fullscreen(app, true);
+        
+        // 已经全屏的窗口（如游戏）只移到新桌面，不最大化也不发送 F11
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        assert_eq!(backend.get_window_desktop(app), 1);
+        assert!(!backend.is_maximized(app));
+        assert_eq!(backend.f11_presses(), 0);
+        assert!(registry.get(&backend, app).unwrap().app_initiated);
+        
+        // 应用还在全屏时按 Win+F 退出：按规则发送 F11 让应用退出全屏，窗口位置交给应用自己还原
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        assert_eq!(backend.f11_presses(), 1);
+        assert_eq!(backend.get_window_desktop(app), 0);
+        assert_eq!(backend.window(app).unwrap().rect, MONITOR);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_transitions_wait_only_as_long_as_needed() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        backend.maximize(app);
+        let clock = FakeClock::new();
+        
+        // 模拟后端的每一步都立即生效，进入时无需睡眠
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &clock).unwrap();
+        assert_eq!(clock.sleeps(), 0);
+        
+        // 退出时只为 F11 留出处理时间
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &clock).unwrap();
+        assert_eq!(clock.elapsed(), DEFAULT_SETTLE);
+    }
+
+    #[test]
+    fn test_enter_reports_unresponsive_window() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        backend.set_unresponsive(app);
+        let clock = FakeClock::new();
+        
+        let err = toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &clock).unwrap_err();
+        
+        assert_eq!(err.kind, TransitionKind::Enter);
+        assert_eq!(err.step, "最大化窗口");
+        assert_eq!(
+            err.cause,
+            StepError::Wait(WaitError::Timeout {
+                condition: "窗口最大化",
+                timeout_ms: config().timing.wait_timeout_ms
+            })
+        );
+        assert_eq!(clock.elapsed(), Duration::from_millis(config().timing.wait_timeout_ms));
+        assert_eq!(backend.f11_presses(), 0);
+        assert!(registry.is_empty());
+        
+        // 已回滚：窗口回到原桌面，新建的桌面已删除
+        assert!(err.rolled_back());
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert_eq!(backend.get_current_desktop(), 0);
+        assert_eq!(backend.get_window_desktop(app), 0);
+    }
+
+    #[test]
+    fn test_enter_rolls_back_when_window_cannot_be_moved() {
+        let backend = FakeBackend::new(3);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 1);
+        backend.go_to_desktop(1);
+        backend.set_active(app);
+        backend.maximize(app);
+        backend.set_elevated(app);
+        let desktops: Vec<_> = (0..3).map(|i| backend.get_desktop_id(i)).collect();
+        
+        let err = toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new())
+            .unwrap_err();
+        
+        assert_eq!(err.step, "移动窗口到新桌面");
+        assert!(err.rolled_back());
+        assert!(registry.is_empty());
+        assert_eq!((0..3).map(|i| backend.get_desktop_id(i)).collect::<Vec<_>>(), desktops);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(backend.get_window_desktop(app), 1);
+        assert!(backend.is_maximized(app));
+        assert_eq!(backend.f11_presses(), 0);
+        assert_eq!(
+            err.to_string(),
+            "进入全屏空间失败（移动窗口到新桌面）: 等待窗口移到新桌面超时（2000 毫秒）"
+        );
+    }
+
+    #[test]
+    fn test_exit_rolls_back_and_keeps_space() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        let created = registry.get(&backend, app).unwrap().created_desktop;
+        
+        // 窗口无响应，无法还原
+        backend.set_unresponsive(app);
+        let err = toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new())
+            .unwrap_err();
+        
+        assert_eq!(err.kind, TransitionKind::Exit);
+        assert_eq!(err.step, "还原窗口");
+        assert!(err.rolled_back());
+        
+        // 空间保持原样，并重新发送 F11 回到应用全屏
+        assert_eq!(backend.f11_presses(), 3);
+        assert!(registry.contains(&backend, app));
+        assert_eq!(backend.get_desktop_index(created), Some(1));
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(backend.get_window_desktop(app), 1);
+    }
+
+    #[test]
+    fn test_enter_fullscreen_inserts_after_original() {
+        let backend = FakeBackend::new(3);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        let second = backend.get_desktop_id(1).unwrap();
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        // 空间紧跟在原桌面右侧，原来的 #2 后移
+        let info = registry.get(&backend, app).unwrap().clone();
+        assert_eq!(backend.get_desktop_index(info.created_desktop), Some(1));
+        assert_eq!(backend.get_desktop_index(second), Some(2));
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(backend.get_window_desktop(app), 1);
+        
+        // 退出后回到原桌面，其他桌面恢复原来的位置
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        assert_eq!(backend.get_desktop_count(), 3);
+        assert_eq!(backend.get_current_desktop(), 0);
+        assert_eq!(backend.get_window_desktop(app), 0);
+        assert_eq!(backend.get_desktop_index(second), Some(1));
+    }
+
+    #[test]
+    fn test_insertion_keeps_other_spaces_consistent() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let first = hwnd(0x100);
+        let second = hwnd(0x200);
+        backend.add_window(first, 1);
+        backend.add_window(second, 0);
+        
+        // 先在 #2 右侧建立一个空间（成为 #3），再从 #1 建立第二个空间
+        backend.go_to_desktop(1);
+        backend.set_active(first);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        backend.go_to_desktop(0);
+        backend.move_window_to_desktop(second, 0);
+        backend.set_active(second);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        // 第二个空间插入到 #2，第一个空间后移到 #4，窗口随桌面一起移动
+        let first_space = registry.get(&backend, first).unwrap().created_desktop;
+        assert_eq!(backend.get_desktop_index(registry.get(&backend, second).unwrap().created_desktop), Some(1));
+        assert_eq!(backend.get_desktop_index(first_space), Some(3));
+        assert_eq!(backend.get_window_desktop(first), 3);
+        
+        // 退出第一个空间仍能回到它的原桌面（现在是 #3）
+        backend.go_to_desktop(3);
+        backend.set_active(first);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        assert_eq!(backend.get_current_desktop(), 2);
+        assert_eq!(backend.get_window_desktop(first), 2);
+        assert_eq!(backend.get_desktop_count(), 3);
+    }
+
+    #[test]
+    fn test_insertion_falls_back_to_end_without_reorder_support() {
+        let backend = FakeBackend::new(3);
+        backend.disable_move_desktop();
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_desktop_index(registry.get(&backend, app).unwrap().created_desktop), Some(3));
+        assert_eq!(backend.get_current_desktop(), 3);
+    }
+
+    #[test]
+    fn test_toggle_twice_restores_desktops() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 1);
+        backend.go_to_desktop(1);
+        backend.set_active(app);
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(backend.get_window_desktop(app), 1);
+        assert!(!backend.is_maximized(app));
+        assert_eq!(backend.f11_presses(), 2);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_exit_follows_desktops_reordered_by_user() {
+        let backend = FakeBackend::new(3);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 1);
+        backend.go_to_desktop(1);
+        backend.set_active(app);
+        let original = backend.get_desktop_id(1).unwrap();
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config_at_end(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        let created = registry.get(&backend, app).unwrap().created_desktop;
+        
+        // 用户在系统中把空间桌面拖到最前面，原桌面变成 #3
+        backend.reorder_desktop(3, 0);
+        assert_eq!(backend.get_desktop_index(original), Some(2));
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_desktop_count(), 3);
+        assert_eq!(backend.get_desktop_index(created), None);
+        assert_eq!(backend.get_desktop_index(original), Some(1));
+        assert_eq!(backend.get_window_desktop(app), 1);
+        assert_eq!(backend.get_current_desktop(), 1);
+    }
+
+    #[test]
+    fn test_close_after_user_removed_space_desktop() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config_at_end(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        // 用户在系统中删除了空间桌面，之后窗口关闭：不应再删除其他桌面
+        backend.remove_desktop(2, 1);
+        backend.close_window(app);
+        handle_window_closed(&backend, &backend, &mut registry, &config(), &FakeClock::new(), app);
+        
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_toggle_ignores_invalid_window() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_window_closed_removes_space_and_shifts_indices() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let first = hwnd(0x100);
+        let second = hwnd(0x200);
+        backend.add_window(first, 0);
+        backend.add_window(second, 0);
+        
+        backend.set_active(first);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        backend.set_active(second);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        assert_eq!(backend.get_desktop_count(), 3);
+        
+        // 关闭第一个全屏窗口，第二个空间的索引前移
+        backend.close_window(first);
+        handle_window_closed(&backend, &backend, &mut registry, &config(), &FakeClock::new(), first);
+        
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert!(!registry.contains(&backend, first));
+        assert_eq!(backend.get_desktop_index(registry.get(&backend, second).unwrap().created_desktop), Some(1));
+        assert_eq!(backend.get_window_desktop(second), 1);
+    }
+
+    #[test]
+    fn test_window_closed_switches_away_from_current_space() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        assert_eq!(backend.get_current_desktop(), 1);
+        
+        backend.close_window(app);
+        handle_window_closed(&backend, &backend, &mut registry, &config(), &FakeClock::new(), app);
+        
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert_eq!(backend.get_current_desktop(), 0);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_window_closed_ignores_unregistered_window() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        
+        handle_window_closed(&backend, &backend, &mut registry, &config(), &FakeClock::new(), hwnd(0x100));
+        
+        assert_eq!(backend.get_desktop_count(), 2);
+    }
+
+    #[test]
+    fn test_hidden_window_collapses_and_expands_space() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        // 最小化：删除空间桌面，窗口回到原桌面，但记住空间
+        handle_window_hidden(&backend, &backend, &mut registry, &config(), &FakeClock::new(), app);
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert_eq!(backend.get_current_desktop(), 0);
+        assert_eq!(backend.get_window_desktop(app), 0);
+        assert!(!registry.contains(&backend, app));
+        assert!(registry.is_collapsed(&backend, app));
+        
+        // 重启后仍然记得
+        let (mut registry, _) = restart(&backend, &registry);
+        assert!(registry.is_collapsed(&backend, app));
+        
+        // 恢复显示：重新创建空间并切换过去，窗口保持全屏
+        handle_window_shown(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new(), app).unwrap();
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert_eq!(backend.get_window_desktop(app), 1);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert!(backend.is_maximized(app));
+        assert!(registry.contains(&backend, app));
+        assert!(!registry.is_collapsed(&backend, app));
+        
+        // 之后照常退出
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert!(!backend.is_maximized(app));
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_hidden_window_policies() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        // 保留：什么都不做，再次显示也不会重复创建
+        let mut keep = config();
+        keep.behavior.hidden_window = HiddenWindowPolicy::Keep;
+        handle_window_hidden(&backend, &backend, &mut registry, &keep, &FakeClock::new(), app);
+        handle_window_shown(&backend, &backend, &mut registry, &keep, &RuleSet::default(), &FakeClock::new(), app).unwrap();
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert!(registry.contains(&backend, app));
+        
+        // 删除：忘掉空间，再次显示时不会重新创建
+        let mut remove = config();
+        remove.behavior.hidden_window = HiddenWindowPolicy::Remove;
+        handle_window_hidden(&backend, &backend, &mut registry, &remove, &FakeClock::new(), app);
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert!(registry.is_empty());
+        assert!(!registry.is_collapsed(&backend, app));
+        handle_window_shown(&backend, &backend, &mut registry, &remove, &RuleSet::default(), &FakeClock::new(), app).unwrap();
+        assert_eq!(backend.get_desktop_count(), 1);
+    }
+
+    #[test]
+    fn test_closed_window_forgets_collapsed_space() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        handle_window_hidden(&backend, &backend, &mut registry, &config(), &FakeClock::new(), app);
+        
+        backend.close_window(app);
+        handle_window_closed(&backend, &backend, &mut registry, &config(), &FakeClock::new(), app);
+        
+        assert!(!registry.is_collapsed(&backend, app));
+        assert_eq!(backend.get_desktop_count(), 1);
+    }
+
+    /// 模拟重启：用保存的状态和新的注册表恢复
+    fn restart(backend: &FakeBackend, registry: &SpaceRegistry) -> (SpaceRegistry, (usize, usize)) {
+        let saved = registry.snapshot();
+        let mut restored = SpaceRegistry::new();
+        let result = restore_spaces(backend, backend, &mut restored, &saved);
+        (restored, result)
+    }
+
+    #[test]
+    fn test_restore_adopts_live_windows() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        let (restored, result) = restart(&backend, &registry);
+        
+        assert_eq!(result, (1, 0));
+        assert_eq!(backend.get_desktop_count(), 2);
+        let info = restored.get(&backend, app).unwrap();
+        assert_eq!(backend.get_desktop_index(info.original_desktop), Some(0));
+        assert_eq!(backend.get_desktop_index(info.created_desktop), Some(1));
+        assert_eq!(info.created_at, registry.get(&backend, app).unwrap().created_at);
+    }
+
+    #[test]
+    fn test_restore_removes_orphaned_desktops() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let closed = hwnd(0x100);
+        let reused = hwnd(0x200);
+        let live = hwnd(0x300);
+        for app in [closed, reused, live] {
+            backend.add_window(app, 0);
+            backend.set_active(app);
+            toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        }
+        assert_eq!(backend.get_desktop_count(), 4);
+        
+        // 程序退出期间：一个窗口关闭，另一个句柄被其他应用复用
+        backend.close_window(closed);
+        backend.set_details(reused, WindowDetails {
+            process_path: r"C:\Other\other.exe".to_string(),
+            class_name: "Other".to_string(),
+            title: String::new(),
+        });
+        
+        let (restored, result) = restart(&backend, &registry);
+        
+        assert_eq!(result, (1, 2));
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert!(!restored.contains(&backend, closed));
+        assert!(!restored.contains(&backend, reused));
+        assert_eq!(backend.get_desktop_index(restored.get(&backend, live).unwrap().created_desktop), Some(1));
+        assert_eq!(backend.get_window_desktop(live), 1);
+        assert_eq!(backend.get_window_desktop(reused), 0);
+    }
+
+    #[test]
+    fn test_restore_drops_implausible_records() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        // 程序退出期间用户手动删除了空间桌面，窗口回到 #1
+        backend.remove_desktop(1, 0);
+        
+        let (restored, result) = restart(&backend, &registry);
+        
+        assert_eq!(result, (0, 0));
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert!(restored.is_empty());
+    }
+
+    #[test]
+    fn test_reused_handle_is_not_mistaken_for_space() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        // 错过了销毁事件，同一个应用的新窗口在空间桌面上复用了句柄
+        backend.close_window(app);
+        backend.add_window(app, 1);
+        assert!(!registry.contains(&backend, app));
+        
+        // 重启时不会把新窗口当成原来的空间
+        let (restored, result) = restart(&backend, &registry);
+        assert_eq!(result, (0, 1));
+        assert!(restored.is_empty());
+    }
+
+    #[test]
+    fn test_sweep_removes_spaces_of_vanished_windows() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (first, second) = (hwnd(0x100), hwnd(0x200));
+        for app in [first, second] {
+            backend.go_to_desktop(0);
+            backend.add_window(app, 0);
+            backend.set_active(app);
+            toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        }
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(sweep_spaces(&backend, &backend, &mut registry, &config(), &FakeClock::new()), 0);
+        
+        // 错过了第二个窗口的销毁事件
+        backend.close_window(second);
+        assert_eq!(sweep_spaces(&backend, &backend, &mut registry, &config(), &FakeClock::new()), 1);
+        
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert_eq!(backend.get_current_desktop(), 0);
+        assert!(registry.contains(&backend, first));
+        assert!(!registry.contains(&backend, second));
+    }
+
+    /// 为 `app` 创建全屏空间，再把 `other` 加入该空间
+    fn space_with_member(backend: &FakeBackend, registry: &mut SpaceRegistry, app: HWND, other: HWND) {
+        backend.add_window(app, 0);
+        backend.add_window(other, 0);
+        backend.set_active(app);
+        toggle_fullscreen(backend, backend, registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        let space = registry.get(backend, app).unwrap().created_desktop;
+        
+        backend.go_to_desktop(0);
+        backend.set_active(other);
+        assert!(send_active_window_to_space(backend, backend, registry, &config(), &FakeClock::new(), space));
+    }
+
+    #[test]
+    fn test_space_is_removed_when_last_window_closes() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (app, other) = (hwnd(0x100), hwnd(0x200));
+        space_with_member(&backend, &mut registry, app, other);
+        
+        assert_eq!(backend.get_window_desktop(other), 1);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(registry.get(&backend, other).unwrap().hwnds(), vec![app, other]);
+        // 已经在空间中的窗口不会重复加入
+        let space = registry.get(&backend, app).unwrap().created_desktop;
+        assert!(!send_active_window_to_space(&backend, &backend, &mut registry, &config(), &FakeClock::new(), space));
+        
+        // 主窗口关闭：空间保留，由加入的窗口接替
+        backend.close_window(app);
+        handle_window_closed(&backend, &backend, &mut registry, &config(), &FakeClock::new(), app);
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert_eq!(registry.get(&backend, other).unwrap().hwnds(), vec![other]);
+        
+        // 最后一个窗口关闭：删除空间
+        backend.close_window(other);
+        handle_window_closed(&backend, &backend, &mut registry, &config(), &FakeClock::new(), other);
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_member_leaves_space_without_removing_it() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (app, other) = (hwnd(0x100), hwnd(0x200));
+        space_with_member(&backend, &mut registry, app, other);
+        
+        // 对加入的窗口按 Win+F：只把它送回原桌面
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        assert_eq!(backend.get_window_desktop(other), 0);
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert!(!registry.contains(&backend, other));
+        assert!(backend.is_maximized(app));
+    }
+
+    #[test]
+    fn test_exit_takes_members_back_to_original_desktop() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (app, other) = (hwnd(0x100), hwnd(0x200));
+        space_with_member(&backend, &mut registry, app, other);
+        
+        backend.set_active(app);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert_eq!(backend.get_window_desktop(app), 0);
+        assert_eq!(backend.get_window_desktop(other), 0);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_members_survive_restart() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (app, other) = (hwnd(0x100), hwnd(0x200));
+        space_with_member(&backend, &mut registry, app, other);
+        
+        let (restored, result) = restart(&backend, &registry);
+        assert_eq!(result, (1, 0));
+        assert_eq!(restored.get(&backend, app).unwrap().hwnds(), vec![app, other]);
+    }
+
+    /// `left` 和 `right` 在桌面 0 上进入分屏（`left` 为活动窗口，`right` 在它下面；还没有的窗口先添加）
+    fn split_space(backend: &FakeBackend, registry: &mut SpaceRegistry, left: HWND, right: HWND) {
+        for hwnd in [right, left] {
+            if backend.window(hwnd).is_none() {
+                backend.add_window(hwnd, 0);
+            }
+        }
+        backend.set_active(left);
+        let partner = split_partner(backend, backend, registry, left).unwrap();
+        enter_split_view(backend, backend, registry, &config(), &FakeClock::new(), partner).unwrap();
+    }
+
+    #[test]
+    fn test_split_view_tiles_both_windows() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (left, right) = (hwnd(0x100), hwnd(0x200));
+        backend.add_window(right, 0);
+        backend.maximize(right);
+        split_space(&backend, &mut registry, left, right);
+        
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert_eq!(backend.get_window_desktop(left), 1);
+        assert_eq!(backend.get_window_desktop(right), 1);
+        
+        // 两个窗口都是还原状态，左右并排铺满工作区
+        let (left_rect, right_rect) = split::split_rects(&WORK_AREA, split::DEFAULT_RATIO);
+        assert_eq!(backend.window(left).unwrap().rect, left_rect);
+        assert_eq!(backend.window(right).unwrap().rect, right_rect);
+        assert!(!backend.is_maximized(right));
+        
+        let info = registry.get(&backend, right).unwrap();
+        assert_eq!(info.hwnds(), vec![left, right]);
+        assert_eq!(info.split, Some(split::DEFAULT_RATIO));
+        
+        // 分屏空间不能再加入窗口
+        let (third, space) = (hwnd(0x300), info.created_desktop);
+        backend.add_window(third, 1);
+        backend.set_active(third);
+        assert!(!send_active_window_to_space(&backend, &backend, &mut registry, &config(), &FakeClock::new(), space));
+    }
+
+    #[test]
+    fn test_split_partner_is_next_window_on_current_desktop() {
+        let backend = FakeBackend::new(2);
+        let registry = SpaceRegistry::new();
+        let (below, elsewhere, active) = (hwnd(0x100), hwnd(0x200), hwnd(0x300));
+        
+        backend.add_window(active, 0);
+        assert_eq!(split_partner(&backend, &backend, &registry, active), None);
+        
+        // Z 序：active, elsewhere（另一个桌面）, below
+        backend.add_window(below, 0);
+        backend.add_window(elsewhere, 1);
+        backend.set_active(active);
+        assert_eq!(split_partner(&backend, &backend, &registry, active), Some(below));
+    }
+
+    #[test]
+    fn test_adjust_split_moves_divider() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (left, right) = (hwnd(0x100), hwnd(0x200));
+        split_space(&backend, &mut registry, left, right);
+        
+        assert!(adjust_split(&backend, &mut registry, right, 0.1));
+        assert_eq!(backend.window(left).unwrap().rect.right, 1152);
+        assert_eq!(backend.window(right).unwrap().rect.left, 1152);
+        assert_eq!(registry.get(&backend, left).unwrap().split, Some(0.6));
+        
+        // 分隔线到边界后不再移动
+        assert!(adjust_split(&backend, &mut registry, left, -1.0));
+        assert_eq!(registry.get(&backend, left).unwrap().split, Some(split::MIN_RATIO));
+        assert!(!adjust_split(&backend, &mut registry, left, -0.05));
+        
+        // 不在分屏空间中的窗口
+        let other = hwnd(0x300);
+        backend.add_window(other, 0);
+        assert!(!adjust_split(&backend, &mut registry, other, 0.1));
+    }
+
+    #[test]
+    fn test_closing_either_window_ends_split_view() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (left, right) = (hwnd(0x100), hwnd(0x200));
+        split_space(&backend, &mut registry, left, right);
+        
+        backend.close_window(right);
+        handle_window_closed(&backend, &backend, &mut registry, &config(), &FakeClock::new(), right);
+        
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert_eq!(backend.get_window_desktop(left), 0);
+        assert_eq!(backend.window(left).unwrap().rect, WINDOW_RECT);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_toggle_ends_split_view_and_restores_windows() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (left, right) = (hwnd(0x100), hwnd(0x200));
+        backend.add_window(right, 0);
+        backend.maximize(right);
+        split_space(&backend, &mut registry, left, right);
+        
+        backend.set_active(right);
+        toggle_fullscreen(&backend, &backend, &mut registry, &config(), &RuleSet::default(), &FakeClock::new()).unwrap();
+        
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert_eq!(backend.get_current_desktop(), 0);
+        for hwnd in [left, right] {
+            assert_eq!(backend.get_window_desktop(hwnd), 0);
+            assert_eq!(backend.window(hwnd).unwrap().rect, WINDOW_RECT);
+        }
+        assert!(backend.is_maximized(right));
+        assert!(!backend.is_maximized(left));
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_split_view_needs_both_windows_after_restart() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (left, right) = (hwnd(0x100), hwnd(0x200));
+        split_space(&backend, &mut registry, left, right);
+        
+        let (restored, result) = restart(&backend, &registry);
+        assert_eq!(result, (1, 0));
+        assert_eq!(restored.get(&backend, right).unwrap().split, Some(split::DEFAULT_RATIO));
+        
+        // 右侧窗口在程序未运行时关闭：结束分屏，左侧窗口恢复原来的位置
+        backend.close_window(right);
+        let (restored, result) = restart(&backend, &registry);
+        assert_eq!(result, (0, 1));
+        assert!(restored.is_empty());
+        assert_eq!(backend.get_window_desktop(left), 0);
+        assert_eq!(backend.window(left).unwrap().rect, WINDOW_RECT);
+    }
+
+    #[test]
+    fn test_sweep_ends_split_view_of_vanished_window() {
+        let backend = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        let (left, right) = (hwnd(0x100), hwnd(0x200));
+        split_space(&backend, &mut registry, left, right);
+        
+        // 错过了销毁事件，句柄已被新窗口复用
+        backend.close_window(right);
+        backend.add_window(right, 1);
+        assert_eq!(sweep_spaces(&backend, &backend, &mut registry, &config(), &FakeClock::new()), 1);
         
-        SendInput(&mut inputs, std::mem::size_of::<INPUT>() as i32);
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert_eq!(backend.window(left).unwrap().rect, WINDOW_RECT);
+        assert!(registry.is_empty());
     }
 }
@@ -0,0 +1,263 @@
+//! 桌面事件
+//!
+//! 检测 MacSpaces 之外发生的桌面变化（Win+Ctrl+D 新建、在任务视图中关闭或重命名桌面等）。
+//! DLL 的 RegisterPostMessageHook 只在当前桌面切换时通知，新建、删除和重命名
+//! 不一定伴随切换，因此统一采用"快照 + 比较"的方式：收到通知或定时轮询时
+//! 拍一次快照，与上一次比较得出事件，再由 `reconcile` 同步到空间注册表。
+
+use crate::backend::{DesktopBackend, DesktopId};
+use crate::registry::SpaceRegistry;
+use tracing::{debug, info};
+
+/// 桌面事件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DesktopEvent {
+    /// 新建了桌面
+    Created { id: DesktopId, index: i32 },
+    /// 删除了桌面
+    Removed { id: DesktopId },
+    /// 当前桌面改变
+    Switched { from: Option<DesktopId>, to: DesktopId },
+    /// 桌面改名
+    Renamed { id: DesktopId, name: String },
+}
+
+/// 某一时刻的桌面列表
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DesktopSnapshot {
+    /// 按顺序排列的桌面及其名称（DLL 不支持名称时为 None）
+    pub desktops: Vec<(DesktopId, Option<String>)>,
+    /// 当前桌面
+    pub current: Option<DesktopId>,
+}
+
+impl DesktopSnapshot {
+    /// 从后端读取当前的桌面列表
+    pub fn capture(backend: &dyn DesktopBackend) -> Self {
+        let desktops = (0..backend.get_desktop_count())
+            .filter_map(|index| {
+                backend
+                    .get_desktop_id(index)
+                    .map(|id| (id, backend.get_desktop_name(index)))
+            })
+            .collect();
+
+        Self {
+            desktops,
+            current: backend.get_desktop_id(backend.get_current_desktop()),
+        }
+    }
+
+    fn find(&self, id: DesktopId) -> Option<&Option<String>> {
+        self.desktops.iter().find(|(d, _)| *d == id).map(|(_, name)| name)
+    }
+}
+
+/// 比较两次快照，得出期间发生的事件
+///
+/// 顺序为：删除、新建、重命名、切换（切换到新桌面时，新建事件在前）
+pub fn diff(old: &DesktopSnapshot, new: &DesktopSnapshot) -> Vec<DesktopEvent> {
+    let mut events = Vec::new();
+
+    for (id, _) in &old.desktops {
+        if new.find(*id).is_none() {
+            events.push(DesktopEvent::Removed { id: *id });
+        }
+    }
+
+    for (index, (id, name)) in new.desktops.iter().enumerate() {
+        match old.find(*id) {
+            None => events.push(DesktopEvent::Created {
+                id: *id,
+                index: index as i32,
+            }),
+            Some(old_name) => {
+                if let (Some(old_name), Some(name)) = (old_name, name) {
+                    if old_name != name {
+                        events.push(DesktopEvent::Renamed {
+                            id: *id,
+                            name: name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(to) = new.current {
+        if old.current != Some(to) {
+            events.push(DesktopEvent::Switched {
+                from: old.current,
+                to,
+            });
+        }
+    }
+
+    events
+}
+
+/// 桌面变化监视器，记录上一次快照
+pub struct DesktopWatcher {
+    last: DesktopSnapshot,
+}
+
+impl DesktopWatcher {
+    /// 以当前状态为起点创建监视器
+    pub fn new(backend: &dyn DesktopBackend) -> Self {
+        Self {
+            last: DesktopSnapshot::capture(backend),
+        }
+    }
+
+    /// 重新拍快照并返回自上次以来的事件
+    pub fn poll(&mut self, backend: &dyn DesktopBackend) -> Vec<DesktopEvent> {
+        let snapshot = DesktopSnapshot::capture(backend);
+        if snapshot == self.last {
+            return Vec::new();
+        }
+
+        let events = diff(&self.last, &snapshot);
+        self.last = snapshot;
+        events
+    }
+}
+
+/// 将桌面事件同步到空间注册表
+///
+/// 用户在系统中删除了全屏空间所在的桌面时，该空间已不复存在（窗口已被系统移到相邻桌面），
+/// 从注册表中移除；重命名了全屏空间的桌面时记录新名称。返回被移除的空间数量。
+pub fn reconcile(registry: &mut SpaceRegistry, events: &[DesktopEvent]) -> usize {
+    let mut removed = 0;
+
+    for event in events {
+        debug!("桌面事件: {:?}", event);
+        match event {
+            DesktopEvent::Removed { id } => {
+                if let Some(space) = registry.remove_by_desktop(*id) {
+                    info!("全屏空间的桌面 {} 已在系统中删除，移除空间: hwnd={:?}", id, space.hwnd);
+                    removed += 1;
+                }
+            }
+            DesktopEvent::Renamed { id, name } => {
+                if registry.rename(*id, name) {
+                    debug!("全屏空间的桌面 {} 已重命名为「{}」", id, name);
+                }
+            }
+            DesktopEvent::Created { .. } | DesktopEvent::Switched { .. } => {}
+        }
+    }
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{hwnd, FakeBackend};
+    use crate::backend::WindowDetails;
+    use crate::registry::SpaceInfo;
+
+    #[test]
+    fn test_no_change_no_events() {
+        let backend = FakeBackend::new(3);
+        let mut watcher = DesktopWatcher::new(&backend);
+        assert!(watcher.poll(&backend).is_empty());
+    }
+
+    #[test]
+    fn test_created_and_switched() {
+        let backend = FakeBackend::new(2);
+        let first = backend.get_desktop_id(0);
+        let mut watcher = DesktopWatcher::new(&backend);
+
+        // Win+Ctrl+D：新建桌面并切换过去
+        backend.create_desktop();
+        backend.go_to_desktop(2);
+        let created = backend.get_desktop_id(2).unwrap();
+
+        assert_eq!(
+            watcher.poll(&backend),
+            vec![
+                DesktopEvent::Created { id: created, index: 2 },
+                DesktopEvent::Switched { from: first, to: created },
+            ]
+        );
+        assert!(watcher.poll(&backend).is_empty());
+    }
+
+    #[test]
+    fn test_removed_and_renamed() {
+        let backend = FakeBackend::new(3);
+        let removed = backend.get_desktop_id(1).unwrap();
+        let renamed = backend.get_desktop_id(2).unwrap();
+        let mut watcher = DesktopWatcher::new(&backend);
+
+        backend.remove_desktop(1, 0);
+        backend.rename_desktop(1, "工作");
+
+        assert_eq!(
+            watcher.poll(&backend),
+            vec![
+                DesktopEvent::Removed { id: removed },
+                DesktopEvent::Renamed {
+                    id: renamed,
+                    name: "工作".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reorder_is_not_an_event() {
+        let backend = FakeBackend::new(3);
+        let mut watcher = DesktopWatcher::new(&backend);
+
+        backend.reorder_desktop(0, 2);
+
+        // 顺序变化使快照不同，但没有增删、改名或切换
+        assert!(watcher.poll(&backend).is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_drops_spaces_whose_desktop_was_removed() {
+        let backend = FakeBackend::new(3);
+        let mut registry = SpaceRegistry::new();
+        let space = backend.get_desktop_id(2).unwrap();
+        let other = backend.get_desktop_id(1).unwrap();
+        registry.register(SpaceInfo::new(
+            hwnd(0x100),
+            WindowDetails::default(),
+            backend.get_desktop_id(0).unwrap(),
+            space,
+        ));
+        let mut watcher = DesktopWatcher::new(&backend);
+
+        // 用户在任务视图中关闭了空间桌面和另一个普通桌面
+        backend.remove_desktop(2, 1);
+        backend.remove_desktop(1, 0);
+        let events = watcher.poll(&backend);
+
+        assert!(events.contains(&DesktopEvent::Removed { id: space }));
+        assert!(events.contains(&DesktopEvent::Removed { id: other }));
+        assert_eq!(reconcile(&mut registry, &events), 1);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_records_renamed_space() {
+        let backend = FakeBackend::new(2);
+        let mut registry = SpaceRegistry::new();
+        let space = backend.get_desktop_id(1).unwrap();
+        registry.register(SpaceInfo {
+            name: "App — 全屏".to_string(),
+            ..SpaceInfo::new(hwnd(0x100), WindowDetails::default(), backend.get_desktop_id(0).unwrap(), space)
+        });
+        let mut watcher = DesktopWatcher::new(&backend);
+
+        backend.rename_desktop(1, "看电影");
+        let events = watcher.poll(&backend);
+
+        assert_eq!(reconcile(&mut registry, &events), 0);
+        assert_eq!(registry.get_by_desktop(space).unwrap().name, "看电影");
+    }
+}
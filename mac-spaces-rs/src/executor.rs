@@ -0,0 +1,1034 @@
+//! 动作执行器
+//!
+//! 消息循环只负责接收快捷键、窗口事件和托盘菜单，把要做的事作为 `Action` 交给
+//! 后台线程，不再在消息循环中等待桌面切换或全屏空间的各个步骤。
+//! 后台线程独占桌面后端和空间注册表，需要 UI 线程处理的结果（播放动画、显示指示器、
+//! 报告错误）以 `Report` 发回，并通过回调通知 UI 线程来取。
+//!
+//! 执行上一个动作期间积压的切换请求会合并：连按三次 Win+→ 只切换一次，向右跳三个桌面。
+//! 命令行控制（见 ipc.rs）需要等待结果，通过 `Requester::query` 提交，执行后结果直接发回请求方。
+
+use crate::backend::{DesktopBackend, DesktopId, WindowBackend, HWND};
+use crate::config::Config;
+use crate::desktop::{self, Direction};
+use crate::desktop_events::{self, DesktopEvent, DesktopWatcher};
+use crate::fullscreen::FullscreenTracker;
+use crate::indicator_layout::IndicatorSnapshot;
+use crate::registry::SpaceRegistry;
+use crate::rules::RuleSet;
+use crate::transition::TransitionError;
+use crate::wait::Clock;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// 交给后台线程执行的动作
+///
+/// 窗口句柄不能跨线程传递，以整数形式保存
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))]
+pub enum Action {
+    /// 按方向切换桌面（正数向右，负数向左，绝对值为次数）
+    Switch { steps: i32 },
+    /// 切换到指定桌面（0-based）
+    GoTo(i32),
+    /// 切换活动窗口的全屏空间
+    ToggleFullscreen,
+    /// 切换指定窗口的全屏空间（命令行控制）
+    ToggleWindowFullscreen(isize),
+    /// 将活动窗口移动到相邻桌面
+    MoveWindow(Direction),
+    /// 将活动窗口加入全屏空间（当前桌面的空间，或最近访问的空间）
+    SendToSpace,
+    /// 活动窗口和它下面的窗口进入分屏，已在分屏中时结束分屏
+    SplitView,
+    /// 按方向移动分屏的分隔线
+    MoveSplit(Direction),
+    /// 窗口已销毁
+    WindowClosed(isize),
+    /// 窗口的位置或样式变化已经稳定（检查应用是否自己进入或退出了全屏）
+    WindowChanged(isize),
+    /// 窗口被最小化或隐藏
+    WindowHidden(isize),
+    /// 窗口从最小化恢复或重新显示
+    WindowShown(isize),
+    /// 检查在系统中发生的桌面变化
+    SyncDesktops,
+    /// 清理窗口已不存在或句柄已被复用的空间（定时执行）
+    SweepSpaces,
+    /// 汇总桌面信息（托盘菜单）
+    DescribeDesktops,
+    /// 汇总空间注册表（托盘菜单）
+    DescribeRegistry,
+    /// 列出全屏空间（命令行控制）
+    ListSpaces,
+}
+
+/// 后台线程发回 UI 线程的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Report {
+    /// 即将切换桌面，播放切换动画
+    Animate(Direction),
+    /// 当前桌面或桌面列表变化，显示空间指示器
+    ShowIndicator(IndicatorSnapshot),
+    /// 当前桌面或其名称变化（如 `#3 Chrome — 全屏`），更新托盘菜单
+    CurrentDesktop(String),
+    /// 操作失败
+    Failed(String),
+    /// 显示信息（托盘菜单请求的内容）
+    Info { title: &'static str, text: String },
+}
+
+/// 待执行的动作队列，加入时合并可以合并的动作
+#[derive(Debug, Default)]
+pub struct ActionQueue {
+    pending: VecDeque<Action>,
+}
+
+impl ActionQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入动作
+    ///
+    /// - 相邻的同方向切换累加步数；方向相反时不合并（非循环模式在首尾桌面停下，先后顺序影响结果）
+    /// - 切换到指定桌面时，之前紧挨着的切换请求没有意义，直接替换
+    /// - 已经在排队的桌面检查、同一窗口的变化检查不重复加入
+    /// - 同一窗口排在最后的显示或隐藏处理与新加入的相同时不重复加入（交替的显示和隐藏保持顺序）
+    pub fn push(&mut self, action: Action) {
+        match (self.pending.back().copied(), action) {
+            (Some(Action::Switch { steps: queued }), Action::Switch { steps })
+                if queued.signum() == steps.signum() =>
+            {
+                self.pending.pop_back();
+                self.pending.push_back(Action::Switch {
+                    steps: queued + steps,
+                });
+            }
+            (Some(Action::Switch { .. } | Action::GoTo(_)), Action::GoTo(_)) => {
+                self.pending.pop_back();
+                self.pending.push_back(action);
+            }
+            (_, Action::SyncDesktops | Action::SweepSpaces | Action::WindowChanged(_))
+                if self.pending.contains(&action) => {}
+            (_, Action::WindowHidden(hwnd) | Action::WindowShown(hwnd))
+                if self.last_visibility(hwnd) == Some(action) => {}
+            _ => self.pending.push_back(action),
+        }
+    }
+
+    /// 同一窗口排在最后的显示或隐藏处理
+    fn last_visibility(&self, hwnd: isize) -> Option<Action> {
+        self.pending
+            .iter()
+            .rev()
+            .find(|a| matches!(a, Action::WindowHidden(h) | Action::WindowShown(h) if *h == hwnd))
+            .copied()
+    }
+
+    /// 取出下一个动作
+    pub fn pop(&mut self) -> Option<Action> {
+        self.pending.pop_front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// 在后台线程中执行动作
+pub trait ActionHandler {
+    /// 执行一个动作，通过 `report` 发回需要 UI 线程处理的结果
+    fn handle(&mut self, action: Action, report: &mut dyn FnMut(Report));
+}
+
+impl<F: FnMut(Action, &mut dyn FnMut(Report))> ActionHandler for F {
+    fn handle(&mut self, action: Action, report: &mut dyn FnMut(Report)) {
+        self(action, report)
+    }
+}
+
+/// 交给后台线程的任务
+enum Job {
+    /// 不等待结果的动作
+    Action(Action),
+    /// 等待结果的动作：之前提交的动作先执行完，结果发回请求方
+    Query(Action, Sender<Vec<Report>>),
+    /// 执行完已提交的动作后退出
+    Stop,
+}
+
+/// 动作执行器（UI 线程持有）
+///
+/// 丢弃时等待后台线程执行完已提交的动作后退出
+pub struct Executor {
+    jobs: Sender<Job>,
+    reports: Receiver<Report>,
+    worker: Option<JoinHandle<()>>,
+}
+
+/// 从其他线程向后台线程提交动作并等待结果（命令行控制的服务线程持有）
+#[derive(Clone)]
+pub struct Requester {
+    jobs: Sender<Job>,
+}
+
+impl Requester {
+    /// 执行动作并返回它产生的结果；后台线程已退出时返回 None
+    ///
+    /// `Report::Info` 只发回请求方，不在 UI 线程弹出；其他结果（动画、指示器、错误提示）照常发给 UI 线程
+    pub fn query(&self, action: Action) -> Option<Vec<Report>> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.jobs.send(Job::Query(action, reply_tx)).ok()?;
+        reply_rx.recv().ok()
+    }
+}
+
+impl Executor {
+    /// 启动后台线程
+    ///
+    /// `make_handler` 在后台线程中调用，创建的处理器（以及其中的窗口句柄）不需要跨线程传递。
+    /// 每发回一个结果调用一次 `notify`，用于唤醒 UI 线程的消息循环
+    pub fn spawn<H, F, N>(make_handler: F, notify: N) -> Self
+    where
+        H: ActionHandler,
+        F: FnOnce() -> H + Send + 'static,
+        N: Fn() + Send + 'static,
+    {
+        let (job_tx, job_rx) = mpsc::channel();
+        let (report_tx, report_rx) = mpsc::channel();
+
+        let worker = thread::Builder::new()
+            .name("mac-spaces-worker".to_string())
+            .spawn(move || {
+                let mut handler = make_handler();
+                run_worker(&job_rx, &mut handler, &mut |report| {
+                    if report_tx.send(report).is_ok() {
+                        notify();
+                    }
+                });
+            })
+            .expect("无法创建后台线程");
+
+        Self {
+            jobs: job_tx,
+            reports: report_rx,
+            worker: Some(worker),
+        }
+    }
+
+    /// 提交动作（不等待执行）
+    pub fn submit(&self, action: Action) {
+        if self.jobs.send(Job::Action(action)).is_err() {
+            warn!("后台线程已退出，忽略动作 {:?}", action);
+        }
+    }
+
+    /// 供其他线程提交动作并等待结果
+    pub fn requester(&self) -> Requester {
+        Requester {
+            jobs: self.jobs.clone(),
+        }
+    }
+
+    /// 取出一个已发回的结果
+    pub fn try_recv(&self) -> Option<Report> {
+        self.reports.try_recv().ok()
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        // `Requester` 可能还持有发送端，通道不会关闭，需要明确通知后台线程退出
+        let _ = self.jobs.send(Job::Stop);
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                warn!("后台线程异常退出");
+            }
+        }
+    }
+}
+
+/// 后台线程主循环：取出所有积压的动作合并后逐个执行，收到 `Stop` 或通道关闭且队列为空时返回
+fn run_worker(jobs: &Receiver<Job>, handler: &mut dyn ActionHandler, report: &mut dyn FnMut(Report)) {
+    let mut queue = ActionQueue::new();
+    loop {
+        let first = if queue.is_empty() {
+            match jobs.recv() {
+                Ok(job) => Some(job),
+                Err(_) => return,
+            }
+        } else {
+            None
+        };
+        for job in first.into_iter().chain(std::iter::from_fn(|| jobs.try_recv().ok())) {
+            match job {
+                Job::Action(action) => queue.push(action),
+                Job::Query(action, reply) => {
+                    drain(&mut queue, handler, report);
+                    let _ = reply.send(answer(action, handler, report));
+                }
+                Job::Stop => {
+                    drain(&mut queue, handler, report);
+                    return;
+                }
+            }
+        }
+        if let Some(action) = queue.pop() {
+            debug!("执行动作: {:?}", action);
+            handler.handle(action, report);
+        }
+    }
+}
+
+/// 执行队列中剩下的所有动作
+fn drain(queue: &mut ActionQueue, handler: &mut dyn ActionHandler, report: &mut dyn FnMut(Report)) {
+    while let Some(action) = queue.pop() {
+        debug!("执行动作: {:?}", action);
+        handler.handle(action, report);
+    }
+}
+
+/// 执行等待结果的动作，收集它产生的结果（`Info` 只发回请求方）
+fn answer(action: Action, handler: &mut dyn ActionHandler, report: &mut dyn FnMut(Report)) -> Vec<Report> {
+    debug!("执行请求: {:?}", action);
+    let mut reports = Vec::new();
+    handler.handle(action, &mut |r| {
+        if !matches!(r, Report::Info { .. }) {
+            report(r.clone());
+        }
+        reports.push(r);
+    });
+    reports
+}
+
+/// 全屏空间的动作处理：持有注册表和桌面变化监视器，后端在调用时传入
+pub struct SpaceWorker {
+    registry: SpaceRegistry,
+    config: Config,
+    rules: RuleSet,
+    clock: Box<dyn Clock>,
+    watcher: DesktopWatcher,
+    /// 各窗口上次检查时是否全屏
+    fullscreen: FullscreenTracker,
+    /// 上次报告的当前桌面（启动后第一次同步时总会报告）
+    current_label: Option<String>,
+    /// 最近访问的全屏空间
+    last_space: Option<DesktopId>,
+}
+
+impl SpaceWorker {
+    pub fn new(
+        backend: &dyn DesktopBackend,
+        registry: SpaceRegistry,
+        config: Config,
+        clock: Box<dyn Clock>,
+    ) -> Self {
+        // 配置加载时已校验过规则，这里不会失败
+        let rules = RuleSet::from_config(&config.rules).unwrap_or_else(|e| {
+            warn!("应用规则无效，全部忽略: {}", e);
+            RuleSet::default()
+        });
+        Self {
+            registry,
+            config,
+            rules,
+            clock,
+            watcher: DesktopWatcher::new(backend),
+            fullscreen: FullscreenTracker::new(),
+            current_label: None,
+            last_space: None,
+        }
+    }
+
+    /// 执行一个动作，之后同步桌面变化
+    pub fn handle(
+        &mut self,
+        backend: &dyn DesktopBackend,
+        windows: &dyn WindowBackend,
+        action: Action,
+        report: &mut dyn FnMut(Report),
+    ) {
+        let cyclic = self.config.behavior.cyclic_switch;
+
+        match action {
+            Action::Switch { steps } => {
+                let current = backend.get_current_desktop();
+                let count = backend.get_desktop_count();
+                if let Some(target) = desktop::switch_target_by(current, count, steps, cyclic) {
+                    let direction = if steps > 0 { Direction::Right } else { Direction::Left };
+                    self.animate(direction, report);
+                    desktop::switch_to(backend, target);
+                }
+            }
+            Action::GoTo(index) => {
+                if let Some(direction) = desktop::direction_to(backend, index) {
+                    self.animate(direction, report);
+                    desktop::switch_to(backend, index);
+                }
+            }
+            Action::ToggleFullscreen => {
+                if let Err(e) = desktop::toggle_fullscreen(
+                    backend,
+                    windows,
+                    &mut self.registry,
+                    &self.config,
+                    &self.rules,
+                    self.clock.as_ref(),
+                ) {
+                    transition_failed(&e, report);
+                }
+            }
+            Action::ToggleWindowFullscreen(raw) => {
+                let hwnd = HWND(raw as *mut std::ffi::c_void);
+                if !windows.is_valid(hwnd) {
+                    report(Report::Failed(format!("窗口不存在: {:#x}", raw)));
+                } else if let Err(e) = desktop::toggle_window_fullscreen(
+                    backend,
+                    windows,
+                    &mut self.registry,
+                    &self.config,
+                    &self.rules,
+                    self.clock.as_ref(),
+                    hwnd,
+                ) {
+                    transition_failed(&e, report);
+                }
+            }
+            Action::MoveWindow(direction) => {
+                if desktop::can_move_active_window(backend, windows, direction) {
+                    if self.config.behavior.follow_moved_window {
+                        self.animate(direction, report);
+                    }
+                    desktop::move_active_window(
+                        backend,
+                        windows,
+                        &mut self.registry,
+                        &self.config,
+                        &self.rules,
+                        self.clock.as_ref(),
+                        direction,
+                    );
+                }
+            }
+            Action::SendToSpace => {
+                let current = backend.get_desktop_id(backend.get_current_desktop());
+                let space = current
+                    .filter(|id| self.registry.get_by_desktop(*id).is_some())
+                    .or(self.last_space)
+                    .filter(|id| self.registry.get_by_desktop(*id).is_some());
+                match space {
+                    Some(space) => {
+                        desktop::send_active_window_to_space(
+                            backend,
+                            windows,
+                            &mut self.registry,
+                            &self.config,
+                            self.clock.as_ref(),
+                            space,
+                        );
+                    }
+                    None => report(Report::Failed("没有可以加入的全屏空间".to_string())),
+                }
+            }
+            Action::SplitView => {
+                let active = windows.get_active();
+                let split = self
+                    .registry
+                    .get(windows, active)
+                    .filter(|s| s.split.is_some())
+                    .cloned();
+                if let Some(info) = split {
+                    desktop::end_split_view(
+                        backend,
+                        windows,
+                        &mut self.registry,
+                        &self.config,
+                        self.clock.as_ref(),
+                        &info,
+                    );
+                } else if self.registry.contains(windows, active) {
+                    report(Report::Failed("全屏空间中的窗口不能分屏".to_string()));
+                } else {
+                    match desktop::split_partner(backend, windows, &self.registry, active) {
+                        Some(other) => {
+                            if let Err(e) = desktop::enter_split_view(
+                                backend,
+                                windows,
+                                &mut self.registry,
+                                &self.config,
+                                self.clock.as_ref(),
+                                other,
+                            ) {
+                                transition_failed(&e, report);
+                            }
+                        }
+                        None => report(Report::Failed("当前桌面上没有可以并排的另一个窗口".to_string())),
+                    }
+                }
+            }
+            Action::MoveSplit(direction) => {
+                let step = self.config.behavior.split_step;
+                let delta = if direction == Direction::Right { step } else { -step };
+                desktop::adjust_split(windows, &mut self.registry, windows.get_active(), delta);
+            }
+            Action::WindowClosed(raw) => {
+                let hwnd = HWND(raw as *mut std::ffi::c_void);
+                self.fullscreen.forget(raw);
+                desktop::handle_window_closed(
+                    backend,
+                    windows,
+                    &mut self.registry,
+                    &self.config,
+                    self.clock.as_ref(),
+                    hwnd,
+                );
+            }
+            Action::WindowChanged(raw) => {
+                let hwnd = HWND(raw as *mut std::ffi::c_void);
+                // 位置变化非常频繁，全屏状态没有改变时直接返回，也不同步桌面
+                let fullscreen = desktop::is_fullscreen(windows, hwnd);
+                let auto = self.config.behavior.auto_fullscreen_space;
+                if !self.fullscreen.observe(raw, fullscreen) || !auto {
+                    return;
+                }
+                if let Err(e) = desktop::follow_app_fullscreen(
+                    backend,
+                    windows,
+                    &mut self.registry,
+                    &self.config,
+                    &self.rules,
+                    self.clock.as_ref(),
+                    hwnd,
+                ) {
+                    transition_failed(&e, report);
+                }
+            }
+            Action::WindowHidden(raw) => {
+                let hwnd = HWND(raw as *mut std::ffi::c_void);
+                if !self.registry.contains(windows, hwnd) {
+                    return;
+                }
+                desktop::handle_window_hidden(
+                    backend,
+                    windows,
+                    &mut self.registry,
+                    &self.config,
+                    self.clock.as_ref(),
+                    hwnd,
+                );
+            }
+            Action::WindowShown(raw) => {
+                let hwnd = HWND(raw as *mut std::ffi::c_void);
+                if !self.registry.is_collapsed(windows, hwnd) {
+                    return;
+                }
+                if let Err(e) = desktop::handle_window_shown(
+                    backend,
+                    windows,
+                    &mut self.registry,
+                    &self.config,
+                    &self.rules,
+                    self.clock.as_ref(),
+                    hwnd,
+                ) {
+                    transition_failed(&e, report);
+                }
+            }
+            Action::SweepSpaces => {
+                self.fullscreen
+                    .retain(|raw| windows.is_valid(HWND(raw as *mut std::ffi::c_void)));
+                desktop::sweep_spaces(backend, windows, &mut self.registry, &self.config, self.clock.as_ref());
+            }
+            Action::SyncDesktops => {}
+            Action::DescribeDesktops => report(Report::Info {
+                title: "MacSpaces 桌面信息",
+                text: describe_desktops(backend),
+            }),
+            Action::DescribeRegistry => report(Report::Info {
+                title: "MacSpaces 空间注册表",
+                text: self.registry.debug_info(),
+            }),
+            Action::ListSpaces => report(Report::Info {
+                title: "MacSpaces 全屏空间",
+                text: describe_spaces(backend, &self.registry),
+            }),
+        }
+
+        self.sync(backend, report);
+    }
+
+    /// 通知 UI 线程播放切换动画，等遮罩淡入后再切换
+    fn animate(&self, direction: Direction, report: &mut dyn FnMut(Report)) {
+        let animation = &self.config.animation;
+        if animation.enabled {
+            report(Report::Animate(direction));
+            self.clock
+                .sleep(Duration::from_millis(animation.duration_ms * 35 / 100));
+        }
+    }
+
+    /// 同步桌面变化，当前桌面或桌面列表变化时显示空间指示器
+    fn sync(&mut self, backend: &dyn DesktopBackend, report: &mut dyn FnMut(Report)) {
+        let events = self.watcher.poll(backend);
+        if !events.is_empty() {
+            desktop_events::reconcile(&mut self.registry, &events);
+            if events.iter().any(|e| !matches!(e, DesktopEvent::Renamed { .. })) {
+                report(Report::ShowIndicator(IndicatorSnapshot::capture(
+                    backend,
+                    &self.registry,
+                )));
+            }
+        }
+
+        let current = backend.get_current_desktop();
+        if let Some(id) = backend.get_desktop_id(current) {
+            if self.registry.get_by_desktop(id).is_some() {
+                self.last_space = Some(id);
+            }
+        }
+        let label = desktop::desktop_label(current, backend.get_desktop_name(current).as_deref());
+        if self.current_label.as_ref() != Some(&label) {
+            self.current_label = Some(label.clone());
+            report(Report::CurrentDesktop(label));
+        }
+    }
+}
+
+/// 记录并报告进入/退出全屏空间失败
+fn transition_failed(e: &TransitionError, report: &mut dyn FnMut(Report)) {
+    warn!("切换全屏空间失败: {}", e);
+    if !e.rolled_back() {
+        warn!("未能完全恢复，以下回滚步骤失败: {:?}", e.rollback_failures);
+    }
+    report(Report::Failed(e.to_string()));
+}
+
+/// 桌面信息：总数和每个桌面的名称，标出当前桌面
+fn describe_desktops(backend: &dyn DesktopBackend) -> String {
+    let count = backend.get_desktop_count();
+    let current = backend.get_current_desktop();
+
+    let mut text = format!("桌面总数: {}\n当前桌面: #{}\n", count, current + 1);
+    for index in 0..count {
+        let label = desktop::desktop_label(index, backend.get_desktop_name(index).as_deref());
+        let marker = if index == current { "  ← 当前" } else { "" };
+        text.push_str(&format!("\n{}{}", label, marker));
+    }
+    text
+}
+
+/// 全屏空间列表：每行一个空间，按桌面顺序列出桌面、主窗口句柄和应用名
+fn describe_spaces(backend: &dyn DesktopBackend, registry: &SpaceRegistry) -> String {
+    let mut lines = Vec::new();
+    for index in 0..backend.get_desktop_count() {
+        let Some(info) = backend.get_desktop_id(index).and_then(|id| registry.get_by_desktop(id)) else {
+            continue;
+        };
+        let label = desktop::desktop_label(index, Some(&info.name));
+        let mut line = format!("{}\thwnd={:#x}\t{}", label, info.hwnd.0 as isize, desktop::app_name(&info.window));
+        if info.split.is_some() {
+            line.push_str("\t分屏");
+        } else if !info.members.is_empty() {
+            line.push_str(&format!("\t另有 {} 个窗口", info.members.len()));
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        "没有全屏空间".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{hwnd, FakeBackend};
+    use crate::config::SpacePlacement;
+    use crate::wait::fake::FakeClock;
+    use std::sync::{Arc, Mutex};
+
+    fn queued(actions: &[Action]) -> Vec<Action> {
+        let mut queue = ActionQueue::new();
+        for action in actions {
+            queue.push(*action);
+        }
+        std::iter::from_fn(|| queue.pop()).collect()
+    }
+
+    #[test]
+    fn test_switches_are_coalesced() {
+        let right = Action::Switch { steps: 1 };
+        let left = Action::Switch { steps: -1 };
+
+        assert_eq!(queued(&[right, right, right]), vec![Action::Switch { steps: 3 }]);
+        assert_eq!(queued(&[right, right, left]), vec![Action::Switch { steps: 2 }, left]);
+        assert_eq!(queued(&[right, left, left]), vec![right, Action::Switch { steps: -2 }]);
+
+        // 其他动作隔开的切换不合并
+        assert_eq!(
+            queued(&[right, Action::ToggleFullscreen, right]),
+            vec![right, Action::ToggleFullscreen, right]
+        );
+    }
+
+    #[test]
+    fn test_go_to_replaces_pending_switches() {
+        assert_eq!(
+            queued(&[Action::Switch { steps: 2 }, Action::GoTo(4), Action::GoTo(1)]),
+            vec![Action::GoTo(1)]
+        );
+        assert_eq!(
+            queued(&[Action::GoTo(1), Action::Switch { steps: 1 }]),
+            vec![Action::GoTo(1), Action::Switch { steps: 1 }]
+        );
+    }
+
+    #[test]
+    fn test_sync_is_not_queued_twice() {
+        assert_eq!(
+            queued(&[Action::SyncDesktops, Action::Switch { steps: 1 }, Action::SyncDesktops]),
+            vec![Action::SyncDesktops, Action::Switch { steps: 1 }]
+        );
+        assert_eq!(
+            queued(&[Action::WindowChanged(1), Action::WindowChanged(2), Action::WindowChanged(1)]),
+            vec![Action::WindowChanged(1), Action::WindowChanged(2)]
+        );
+        assert_eq!(
+            queued(&[Action::WindowHidden(1), Action::WindowHidden(2), Action::WindowHidden(1)]),
+            vec![Action::WindowHidden(1), Action::WindowHidden(2)]
+        );
+        assert_eq!(
+            queued(&[Action::WindowHidden(1), Action::WindowShown(1), Action::WindowShown(1)]),
+            vec![Action::WindowHidden(1), Action::WindowShown(1)]
+        );
+    }
+
+    #[test]
+    fn test_worker_drains_and_coalesces_backlog() {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..3 {
+            tx.send(Job::Action(Action::Switch { steps: 1 })).unwrap();
+        }
+        tx.send(Job::Action(Action::ToggleFullscreen)).unwrap();
+        drop(tx);
+
+        let mut handled = Vec::new();
+        let mut handler = |action: Action, _: &mut dyn FnMut(Report)| handled.push(action);
+        run_worker(&rx, &mut handler, &mut |_| {});
+
+        assert_eq!(
+            handled,
+            vec![Action::Switch { steps: 3 }, Action::ToggleFullscreen]
+        );
+    }
+
+    #[test]
+    fn test_executor_reports_back() {
+        let notified = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&notified);
+        let executor = Executor::spawn(
+            || {
+                |action: Action, report: &mut dyn FnMut(Report)| {
+                    report(Report::Failed(format!("{:?}", action)));
+                }
+            },
+            move || *counter.lock().unwrap() += 1,
+        );
+
+        executor.submit(Action::DescribeDesktops);
+        let reports = executor.reports.recv().unwrap();
+        drop(executor);
+
+        assert_eq!(reports, Report::Failed("DescribeDesktops".to_string()));
+        assert_eq!(*notified.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_requester_waits_for_result() {
+        let executor = Executor::spawn(
+            || {
+                |action: Action, report: &mut dyn FnMut(Report)| {
+                    report(Report::CurrentDesktop(format!("{:?}", action)));
+                    report(Report::Info {
+                        title: "结果",
+                        text: format!("{:?}", action),
+                    });
+                }
+            },
+            || {},
+        );
+        let requester = executor.requester();
+
+        // 之前提交的动作先执行，Info 只发回请求方
+        executor.submit(Action::SyncDesktops);
+        let reports = requester.query(Action::ListSpaces).unwrap();
+        assert_eq!(
+            reports,
+            vec![
+                Report::CurrentDesktop("ListSpaces".to_string()),
+                Report::Info {
+                    title: "结果",
+                    text: "ListSpaces".to_string(),
+                },
+            ]
+        );
+        let ui: Vec<Report> = std::iter::from_fn(|| executor.try_recv()).collect();
+        assert_eq!(
+            ui,
+            vec![
+                Report::CurrentDesktop("SyncDesktops".to_string()),
+                Report::Info {
+                    title: "结果",
+                    text: "SyncDesktops".to_string(),
+                },
+                Report::CurrentDesktop("ListSpaces".to_string()),
+            ]
+        );
+
+        // 仍持有 Requester 时执行器也能正常退出
+        drop(executor);
+        assert_eq!(requester.query(Action::ListSpaces), None);
+    }
+
+    /// 创建处理器并完成启动时的第一次同步（丢弃其中报告的当前桌面）
+    fn worker(backend: &FakeBackend) -> SpaceWorker {
+        let mut config = Config::default();
+        config.timing.switch_delay_ms = 0;
+        let mut worker =
+            SpaceWorker::new(backend, SpaceRegistry::new(), config, Box::new(FakeClock::new()));
+        worker.handle(backend, backend, Action::SyncDesktops, &mut |_| {});
+        worker
+    }
+
+    #[test]
+    fn test_space_worker_jumps_once_for_coalesced_switch() {
+        let backend = FakeBackend::new(5);
+        let mut worker = worker(&backend);
+        let mut reports = Vec::new();
+
+        worker.handle(&backend, &backend, Action::Switch { steps: 3 }, &mut |r| reports.push(r));
+
+        assert_eq!(backend.get_current_desktop(), 3);
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0], Report::Animate(Direction::Right));
+        assert!(matches!(&reports[1], Report::ShowIndicator(s) if s.current == 3));
+        assert_eq!(reports[2], Report::CurrentDesktop("#4".to_string()));
+    }
+
+    #[test]
+    fn test_opposite_switches_at_the_edge_keep_their_order() {
+        let backend = FakeBackend::new(5);
+        let mut worker = worker(&backend);
+        let mut run = |actions: &[Action]| {
+            for action in queued(actions) {
+                worker.handle(&backend, &backend, action, &mut |_| {});
+            }
+        };
+
+        // 在最后一个桌面：向右不动，再向左移一个
+        backend.go_to_desktop(4);
+        run(&[Action::Switch { steps: 1 }, Action::Switch { steps: -1 }]);
+        assert_eq!(backend.get_current_desktop(), 3);
+
+        // 在倒数第二个桌面：向右两次只能移一个，再向左回到倒数第二个
+        run(&[Action::Switch { steps: 2 }, Action::Switch { steps: -1 }]);
+        assert_eq!(backend.get_current_desktop(), 3);
+    }
+
+    #[test]
+    fn test_space_worker_reports_failed_transition() {
+        let backend = FakeBackend::new(2);
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        backend.set_active(app);
+        backend.set_elevated(app);
+        let mut worker = worker(&backend);
+        let mut reports = Vec::new();
+
+        worker.handle(&backend, &backend, Action::ToggleFullscreen, &mut |r| reports.push(r));
+
+        assert!(matches!(&reports[..], [Report::Failed(message)] if message.contains("移动窗口到新桌面")));
+        assert_eq!(backend.get_desktop_count(), 2);
+    }
+
+    #[test]
+    fn test_space_worker_follows_app_fullscreen() {
+        let backend = FakeBackend::new(2);
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        let mut config = Config::default();
+        config.behavior.auto_fullscreen_space = true;
+        config.behavior.space_placement = SpacePlacement::AfterOriginal;
+        let mut worker =
+            SpaceWorker::new(&backend, SpaceRegistry::new(), config, Box::new(FakeClock::new()));
+        let changed = Action::WindowChanged(0x100);
+
+        // 普通的位置变化不做任何事
+        worker.handle(&backend, &backend, changed, &mut |_| {});
+        assert_eq!(backend.get_desktop_count(), 2);
+
+        // 应用自己进入全屏：创建空间，窗口保持原样
+        backend.set_app_fullscreen(app, true);
+        worker.handle(&backend, &backend, changed, &mut |_| {});
+        assert_eq!(backend.get_desktop_count(), 3);
+        assert_eq!(backend.get_window_desktop(app), 1);
+        assert_eq!(backend.get_current_desktop(), 1);
+        assert!(!backend.is_maximized(app));
+        assert!(backend.sent_chords().is_empty());
+
+        worker.handle(&backend, &backend, changed, &mut |_| {});
+        assert_eq!(backend.get_desktop_count(), 3);
+
+        // 应用自己退出全屏：删除空间，回到原桌面
+        backend.set_app_fullscreen(app, false);
+        worker.handle(&backend, &backend, changed, &mut |_| {});
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert_eq!(backend.get_window_desktop(app), 0);
+        assert_eq!(backend.get_current_desktop(), 0);
+        assert!(backend.sent_chords().is_empty());
+    }
+
+    #[test]
+    fn test_space_worker_ignores_app_fullscreen_by_default() {
+        let backend = FakeBackend::new(2);
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        let mut worker = worker(&backend);
+
+        backend.set_app_fullscreen(app, true);
+        worker.handle(&backend, &backend, Action::WindowChanged(0x100), &mut |_| {});
+
+        assert_eq!(backend.get_desktop_count(), 2);
+        assert_eq!(backend.get_window_desktop(app), 0);
+    }
+
+    #[test]
+    fn test_space_worker_sync_reports_external_changes() {
+        let backend = FakeBackend::new(2);
+        let mut worker = worker(&backend);
+        let mut reports = Vec::new();
+
+        worker.handle(&backend, &backend, Action::SyncDesktops, &mut |r| reports.push(r));
+        assert!(reports.is_empty());
+
+        backend.create_desktop();
+        worker.handle(&backend, &backend, Action::SyncDesktops, &mut |r| reports.push(r));
+        assert!(matches!(&reports[..], [Report::ShowIndicator(s)] if s.spaces.len() == 3));
+    }
+
+    #[test]
+    fn test_space_worker_sends_window_to_last_visited_space() {
+        let backend = FakeBackend::new(1);
+        let mut worker = worker(&backend);
+        let (app, other) = (hwnd(0x100), hwnd(0x200));
+        backend.add_window(app, 0);
+        backend.add_window(other, 0);
+        let mut reports = Vec::new();
+
+        // 还没有全屏空间
+        backend.set_active(other);
+        worker.handle(&backend, &backend, Action::SendToSpace, &mut |r| reports.push(r));
+        assert!(matches!(&reports[..], [Report::Failed(_)]));
+
+        backend.set_active(app);
+        worker.handle(&backend, &backend, Action::ToggleFullscreen, &mut |_| {});
+        worker.handle(&backend, &backend, Action::GoTo(0), &mut |_| {});
+
+        // 回到普通桌面后仍加入刚才的空间
+        backend.set_active(other);
+        worker.handle(&backend, &backend, Action::SendToSpace, &mut |_| {});
+        assert_eq!(backend.get_window_desktop(other), 1);
+        assert_eq!(backend.get_current_desktop(), 1);
+    }
+
+    #[test]
+    fn test_space_worker_toggles_split_view() {
+        let backend = FakeBackend::new(1);
+        let mut worker = worker(&backend);
+        let (below, active) = (hwnd(0x100), hwnd(0x200));
+        backend.add_window(active, 0);
+        let mut reports = Vec::new();
+
+        // 当前桌面上没有另一个窗口
+        backend.set_active(active);
+        worker.handle(&backend, &backend, Action::SplitView, &mut |r| reports.push(r));
+        assert!(matches!(&reports[..], [Report::Failed(_)]));
+
+        backend.add_window(below, 0);
+        backend.set_active(active);
+        worker.handle(&backend, &backend, Action::SplitView, &mut |_| {});
+        assert_eq!(backend.get_window_desktop(below), 1);
+        assert_eq!(backend.get_current_desktop(), 1);
+
+        worker.handle(&backend, &backend, Action::MoveSplit(Direction::Left), &mut |_| {});
+        assert_eq!(backend.window(active).unwrap().rect.right, 864);
+
+        // 再按一次结束分屏
+        worker.handle(&backend, &backend, Action::SplitView, &mut |_| {});
+        assert_eq!(backend.get_desktop_count(), 1);
+        assert_eq!(backend.get_window_desktop(below), 0);
+    }
+
+    #[test]
+    fn test_space_worker_lists_spaces() {
+        let backend = FakeBackend::new(1);
+        let mut worker = worker(&backend);
+        let app = hwnd(0x100);
+        backend.add_window(app, 0);
+        let mut reports = Vec::new();
+
+        worker.handle(&backend, &backend, Action::ListSpaces, &mut |r| reports.push(r));
+        assert!(matches!(&reports[..], [Report::Info { text, .. }] if text == "没有全屏空间"));
+
+        worker.handle(&backend, &backend, Action::ToggleWindowFullscreen(0x100), &mut |_| {});
+        assert_eq!(backend.get_window_desktop(app), 1);
+
+        reports.clear();
+        worker.handle(&backend, &backend, Action::ListSpaces, &mut |r| reports.push(r));
+        assert!(matches!(
+            &reports[..],
+            [Report::Info { text, .. }] if text == "#2 App100 — 全屏\thwnd=0x100\tApp100"
+        ));
+
+        // 指定的窗口不存在
+        reports.clear();
+        worker.handle(&backend, &backend, Action::ToggleWindowFullscreen(0x999), &mut |r| reports.push(r));
+        assert!(matches!(&reports[..], [Report::Failed(message)] if message.contains("0x999")));
+    }
+
+    #[test]
+    fn test_space_worker_reports_current_desktop_name() {
+        let backend = FakeBackend::new(2);
+        let mut worker =
+            SpaceWorker::new(&backend, SpaceRegistry::new(), Config::default(), Box::new(FakeClock::new()));
+        let mut reports = Vec::new();
+
+        // 启动后第一次同步报告当前桌面，之后只在变化时报告
+        worker.handle(&backend, &backend, Action::SyncDesktops, &mut |r| reports.push(r));
+        worker.handle(&backend, &backend, Action::SyncDesktops, &mut |r| reports.push(r));
+        assert_eq!(reports, vec![Report::CurrentDesktop("#1".to_string())]);
+
+        // 用户重命名当前桌面：不显示指示器，只更新名称
+        reports.clear();
+        backend.rename_desktop(0, "工作");
+        worker.handle(&backend, &backend, Action::SyncDesktops, &mut |r| reports.push(r));
+        assert_eq!(reports, vec![Report::CurrentDesktop("#1 工作".to_string())]);
+    }
+
+    #[test]
+    fn test_describe_desktops_lists_names() {
+        let backend = FakeBackend::new(3);
+        backend.go_to_desktop(1);
+        backend.rename_desktop(2, "Chrome — 全屏");
+
+        assert_eq!(
+            describe_desktops(&backend),
+            "桌面总数: 3\n当前桌面: #2\n\n#1\n#2  ← 当前\n#3 Chrome — 全屏"
+        );
+    }
+}
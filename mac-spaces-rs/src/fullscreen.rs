@@ -0,0 +1,286 @@
+//! 应用自身全屏的检测
+//!
+//! 视频播放器双击、游戏等会自己进入全屏：去掉标题栏和边框，铺满所在的显示器。
+//! 判断只依赖窗口快照（样式、矩形和所在显示器），与平台无关，便于测试；
+//! 快照由 `WindowBackend::snapshot` 获取。
+
+use crate::backend::WindowRect;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+const WS_CHILD: u32 = 0x4000_0000;
+const WS_CAPTION: u32 = 0x00C0_0000;
+const WS_THICKFRAME: u32 = 0x0004_0000;
+const WS_EX_TOOLWINDOW: u32 = 0x0000_0080;
+
+/// 同样铺满显示器、但不是应用窗口的 shell 窗口（桌面和任务栏），也不能作为分屏的另一个窗口
+pub const SHELL_CLASSES: &[&str] = &["Progman", "WorkerW", "Shell_TrayWnd", "Shell_SecondaryTrayWnd"];
+
+/// 判断是否全屏所需的窗口信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowSnapshot {
+    /// 窗口类名
+    pub class_name: String,
+    /// 窗口样式（`GWL_STYLE`）
+    pub style: u32,
+    /// 扩展样式（`GWL_EXSTYLE`）
+    pub ex_style: u32,
+    /// 是否可见
+    pub visible: bool,
+    /// 是否最小化
+    pub minimized: bool,
+    /// 窗口矩形
+    pub rect: WindowRect,
+    /// 所在显示器的矩形
+    pub monitor: WindowRect,
+}
+
+/// 窗口是否处于全屏状态：可见的顶层应用窗口，没有标题栏和可调边框，覆盖整个显示器
+pub fn is_fullscreen(window: &WindowSnapshot) -> bool {
+    let covers_monitor = window.rect.left <= window.monitor.left
+        && window.rect.top <= window.monitor.top
+        && window.rect.right >= window.monitor.right
+        && window.rect.bottom >= window.monitor.bottom;
+
+    window.visible
+        && !window.minimized
+        && window.style & WS_CHILD == 0
+        && window.ex_style & WS_EX_TOOLWINDOW == 0
+        && window.style & WS_CAPTION != WS_CAPTION
+        && window.style & WS_THICKFRAME == 0
+        && covers_monitor
+        && !SHELL_CLASSES.contains(&window.class_name.as_str())
+}
+
+/// 记录哪些窗口处于全屏状态，窗口位置变化时只关心全屏状态是否改变
+///
+/// 只记录全屏的窗口，窗口退出全屏时即移除
+#[derive(Debug, Default)]
+pub struct FullscreenTracker {
+    fullscreen: HashSet<isize>,
+}
+
+impl FullscreenTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录窗口当前是否全屏，与上次不同时返回 true（第一次见到的窗口视为之前不是全屏）
+    pub fn observe(&mut self, hwnd: isize, fullscreen: bool) -> bool {
+        if fullscreen {
+            self.fullscreen.insert(hwnd)
+        } else {
+            self.fullscreen.remove(&hwnd)
+        }
+    }
+
+    /// 窗口已销毁，不再记录（句柄可能被新窗口复用）
+    pub fn forget(&mut self, hwnd: isize) {
+        self.fullscreen.remove(&hwnd);
+    }
+
+    /// 只保留 `keep` 返回 true 的窗口（销毁事件丢失时由定时清理兜底）
+    pub fn retain(&mut self, mut keep: impl FnMut(isize) -> bool) {
+        self.fullscreen.retain(|&hwnd| keep(hwnd));
+    }
+}
+
+/// 合并同一窗口连续的位置和状态变化
+///
+/// 进入或退出全屏时窗口会连续改变样式、位置和大小，中间状态没有意义；
+/// 窗口最后一次变化后安静了 `delay` 才检查一次全屏状态。
+#[derive(Debug)]
+pub struct ChangeDebouncer {
+    delay: Duration,
+    /// 有变化、还没有检查的窗口及其最后一次变化的时间
+    pending: HashMap<isize, Instant>,
+}
+
+impl ChangeDebouncer {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// 记录窗口在 `now` 发生了变化
+    pub fn note(&mut self, hwnd: isize, now: Instant) {
+        self.pending.insert(hwnd, now);
+    }
+
+    /// 窗口已销毁，不再检查
+    pub fn forget(&mut self, hwnd: isize) {
+        self.pending.remove(&hwnd);
+    }
+
+    /// 取出到 `now` 为止已经安静了 `delay` 的窗口
+    pub fn take_due(&mut self, now: Instant) -> Vec<isize> {
+        let mut due: Vec<isize> = self
+            .pending
+            .iter()
+            .filter(|(_, &changed)| now.saturating_duration_since(changed) >= self.delay)
+            .map(|(&hwnd, _)| hwnd)
+            .collect();
+        due.sort_unstable();
+        for hwnd in &due {
+            self.pending.remove(hwnd);
+        }
+        due
+    }
+
+    /// 是否还有等待检查的窗口
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: WindowRect = WindowRect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1080,
+    };
+
+    /// 铺满显示器的无边框窗口（WS_POPUP | WS_VISIBLE）
+    fn fullscreen_window() -> WindowSnapshot {
+        WindowSnapshot {
+            class_name: "MediaPlayerClassicW".to_string(),
+            style: 0x9000_0000,
+            ex_style: 0,
+            visible: true,
+            minimized: false,
+            rect: MONITOR,
+            monitor: MONITOR,
+        }
+    }
+
+    #[test]
+    fn test_borderless_window_covering_monitor_is_fullscreen() {
+        assert!(is_fullscreen(&fullscreen_window()));
+
+        // 比显示器大一些也算（部分游戏会多出几个像素）
+        let window = WindowSnapshot {
+            rect: WindowRect {
+                left: -2,
+                top: -2,
+                right: 1922,
+                bottom: 1082,
+            },
+            ..fullscreen_window()
+        };
+        assert!(is_fullscreen(&window));
+
+        // 副屏上的全屏窗口
+        let second = WindowRect {
+            left: 1920,
+            top: 0,
+            right: 3200,
+            bottom: 1024,
+        };
+        let window = WindowSnapshot {
+            rect: second,
+            monitor: second,
+            ..fullscreen_window()
+        };
+        assert!(is_fullscreen(&window));
+    }
+
+    #[test]
+    fn test_ordinary_windows_are_not_fullscreen() {
+        // 最大化的普通窗口有标题栏和可调边框（WS_OVERLAPPEDWINDOW | WS_MAXIMIZE）
+        let maximized = WindowSnapshot {
+            style: 0x11CF_0000,
+            rect: WindowRect {
+                left: -8,
+                top: -8,
+                right: 1928,
+                bottom: 1048,
+            },
+            ..fullscreen_window()
+        };
+        assert!(!is_fullscreen(&maximized));
+
+        let small = WindowSnapshot {
+            rect: WindowRect {
+                left: 0,
+                top: 0,
+                right: 1920,
+                bottom: 1040,
+            },
+            ..fullscreen_window()
+        };
+        assert!(!is_fullscreen(&small));
+
+        let hidden = WindowSnapshot {
+            visible: false,
+            ..fullscreen_window()
+        };
+        assert!(!is_fullscreen(&hidden));
+
+        let minimized = WindowSnapshot {
+            minimized: true,
+            ..fullscreen_window()
+        };
+        assert!(!is_fullscreen(&minimized));
+
+        let tool = WindowSnapshot {
+            ex_style: WS_EX_TOOLWINDOW,
+            ..fullscreen_window()
+        };
+        assert!(!is_fullscreen(&tool));
+
+        let desktop = WindowSnapshot {
+            class_name: "Progman".to_string(),
+            ..fullscreen_window()
+        };
+        assert!(!is_fullscreen(&desktop));
+    }
+
+    #[test]
+    fn test_tracker_reports_only_changes() {
+        let mut tracker = FullscreenTracker::new();
+
+        assert!(!tracker.observe(0x100, false));
+        assert!(tracker.observe(0x100, true));
+        assert!(!tracker.observe(0x100, true));
+        assert!(tracker.observe(0x100, false));
+
+        // 销毁后复用句柄的新窗口从头开始记录
+        tracker.observe(0x200, true);
+        tracker.forget(0x200);
+        assert!(tracker.observe(0x200, true));
+
+        // 已不存在的窗口被清理
+        tracker.observe(0x300, true);
+        tracker.retain(|hwnd| hwnd != 0x200);
+        assert!(tracker.observe(0x200, true));
+        assert!(!tracker.observe(0x300, true));
+    }
+
+    #[test]
+    fn test_debouncer_waits_for_changes_to_settle() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut debouncer = ChangeDebouncer::new(Duration::from_millis(100));
+
+        // 0x100 连续变化，每次变化都重新计时
+        debouncer.note(0x100, at(0));
+        debouncer.note(0x200, at(10));
+        debouncer.note(0x100, at(60));
+        assert!(debouncer.take_due(at(90)).is_empty());
+        assert_eq!(debouncer.take_due(at(110)), vec![0x200]);
+        assert_eq!(debouncer.take_due(at(160)), vec![0x100]);
+        assert!(debouncer.is_empty());
+
+        // 已销毁的窗口不再检查
+        debouncer.note(0x300, at(200));
+        debouncer.forget(0x300);
+        assert!(debouncer.take_due(at(400)).is_empty());
+        assert!(debouncer.is_empty());
+    }
+}
@@ -1,6 +1,14 @@
 //! 窗口事件监听
 //! 
 //! 使用 SetWinEventHook 监听窗口事件，替代轮询方式
+//!
+//! 开启自动全屏空间时还监听窗口位置和状态变化：应用自己进入或退出全屏时
+//! 会改变样式并调整窗口大小，改变样式后的 `SWP_FRAMECHANGED` 同样产生位置变化事件。
+//! 这类事件非常频繁，回调中只转发可见的顶层应用窗口的事件，由 UI 线程合并后再检查。
+//! 显示、隐藏和最小化事件同样只转发顶层应用窗口的。
+//!
+//! 最小化和隐藏事件用于收起全屏空间。切换虚拟桌面时系统只是隐藏（cloak）窗口，
+//! 不会产生 `EVENT_OBJECT_HIDE`，所以不会误判。
 
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
@@ -9,7 +17,10 @@ use windows::Win32::UI::Accessibility::{
     SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
-    EVENT_OBJECT_DESTROY, OBJID_WINDOW, WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS,
+    GetAncestor, GetWindowLongW, IsWindowVisible, CHILDID_SELF, EVENT_OBJECT_DESTROY,
+    EVENT_OBJECT_HIDE, EVENT_OBJECT_LOCATIONCHANGE, EVENT_OBJECT_SHOW, EVENT_OBJECT_STATECHANGE,
+    EVENT_SYSTEM_MINIMIZEEND, EVENT_SYSTEM_MINIMIZESTART, GA_ROOT, GWL_EXSTYLE, GWL_STYLE, OBJID_WINDOW,
+    WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS, WS_CHILD, WS_EX_TOOLWINDOW,
 };
 use tracing::{debug, trace};
 
@@ -18,6 +29,16 @@ use tracing::{debug, trace};
 pub enum WindowEvent {
     /// 窗口被销毁
     Destroyed(HWND),
+    /// 窗口的位置、大小或状态变化
+    Changed(HWND),
+    /// 窗口被最小化
+    Minimized(HWND),
+    /// 窗口从最小化恢复
+    Restored(HWND),
+    /// 窗口被隐藏
+    Hidden(HWND),
+    /// 窗口重新显示
+    Shown(HWND),
 }
 
 // 全局状态（用于钩子回调）
@@ -26,23 +47,38 @@ static HOOK_ACTIVE: AtomicBool = AtomicBool::new(false);
 
 /// 窗口事件监听器
 pub struct WindowEventHook {
-    hook: HWINEVENTHOOK,
+    hooks: Vec<HWINEVENTHOOK>,
 }
 
 impl WindowEventHook {
-    /// 创建窗口事件监听器
-    pub fn new(tx: Sender<WindowEvent>) -> windows::core::Result<Self> {
+    /// 创建窗口事件监听器（`watch_changes` 为 true 时同时监听位置和状态变化）
+    pub fn new(tx: Sender<WindowEvent>, watch_changes: bool) -> windows::core::Result<Self> {
         // 保存发送器到全局状态
         unsafe {
             WINDOW_TX = Some(tx);
         }
         HOOK_ACTIVE.store(true, Ordering::SeqCst);
         
-        // 设置 WinEvent 钩子监听窗口销毁事件
+        // 设置 WinEvent 钩子监听窗口销毁、显示和隐藏事件（0x8001~0x8003 相邻）
+        let mut this = Self { hooks: Vec::new() };
+        this.install(EVENT_OBJECT_DESTROY, EVENT_OBJECT_HIDE)?;
+        this.install(EVENT_SYSTEM_MINIMIZESTART, EVENT_SYSTEM_MINIMIZEEND)?;
+        if watch_changes {
+            // STATECHANGE (0x800A) 和 LOCATIONCHANGE (0x800B) 相邻，用一个钩子监听
+            this.install(EVENT_OBJECT_STATECHANGE, EVENT_OBJECT_LOCATIONCHANGE)?;
+        }
+        
+        debug!("窗口事件钩子已安装");
+        
+        Ok(this)
+    }
+    
+    /// 安装监听 `min..=max` 范围事件的钩子
+    fn install(&mut self, min: u32, max: u32) -> windows::core::Result<()> {
         let hook = unsafe {
             SetWinEventHook(
-                EVENT_OBJECT_DESTROY,
-                EVENT_OBJECT_DESTROY,
+                min,
+                max,
                 None,
                 Some(win_event_proc),
                 0,  // 所有进程
@@ -54,10 +90,8 @@ impl WindowEventHook {
         if hook.is_invalid() {
             return Err(windows::core::Error::from_win32());
         }
-        
-        debug!("窗口事件钩子已安装");
-        
-        Ok(Self { hook })
+        self.hooks.push(hook);
+        Ok(())
     }
 }
 
@@ -65,7 +99,9 @@ impl Drop for WindowEventHook {
     fn drop(&mut self) {
         HOOK_ACTIVE.store(false, Ordering::SeqCst);
         unsafe {
-            let _ = UnhookWinEvent(self.hook);
+            for hook in self.hooks.drain(..) {
+                let _ = UnhookWinEvent(hook);
+            }
             WINDOW_TX = None;
         }
         debug!("窗口事件钩子已卸载");
@@ -78,7 +114,7 @@ unsafe extern "system" fn win_event_proc(
     event: u32,
     hwnd: HWND,
     id_object: i32,
-    _id_child: i32,
+    id_child: i32,
     _event_thread: u32,
     _event_time: u32,
 ) {
@@ -86,12 +122,36 @@ unsafe extern "system" fn win_event_proc(
         return;
     }
     
-    // 只处理窗口本身的销毁事件（不是子对象）
-    if event == EVENT_OBJECT_DESTROY && id_object == OBJID_WINDOW.0 {
-        trace!("窗口销毁事件: hwnd={:?}", hwnd);
-        
-        if let Some(ref tx) = WINDOW_TX {
-            let _ = tx.send(WindowEvent::Destroyed(hwnd));
+    // 只处理窗口本身的事件（不是子对象，如光标和滚动条）
+    if id_object != OBJID_WINDOW.0 || id_child != CHILDID_SELF as i32 {
+        return;
+    }
+    
+    let window_event = match event {
+        EVENT_OBJECT_DESTROY => {
+            trace!("窗口销毁事件: hwnd={:?}", hwnd);
+            WindowEvent::Destroyed(hwnd)
         }
+        // 工具提示、菜单、下拉列表和子窗口的显示隐藏非常频繁，只转发顶层窗口的
+        _ if !is_top_level(hwnd) => return,
+        EVENT_OBJECT_SHOW => WindowEvent::Shown(hwnd),
+        EVENT_OBJECT_HIDE => WindowEvent::Hidden(hwnd),
+        EVENT_SYSTEM_MINIMIZESTART => WindowEvent::Minimized(hwnd),
+        EVENT_SYSTEM_MINIMIZEEND => WindowEvent::Restored(hwnd),
+        EVENT_OBJECT_STATECHANGE | EVENT_OBJECT_LOCATIONCHANGE if IsWindowVisible(hwnd).as_bool() => {
+            WindowEvent::Changed(hwnd)
+        }
+        _ => return,
+    };
+    
+    if let Some(ref tx) = WINDOW_TX {
+        let _ = tx.send(window_event);
     }
 }
+
+/// 是否是顶层的应用窗口（不是子窗口和工具窗口），不要求可见：隐藏事件到达时窗口已经不可见
+unsafe fn is_top_level(hwnd: HWND) -> bool {
+    GetAncestor(hwnd, GA_ROOT) == hwnd
+        && GetWindowLongW(hwnd, GWL_STYLE) as u32 & WS_CHILD.0 == 0
+        && GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 & WS_EX_TOOLWINDOW.0 == 0
+}
@@ -3,50 +3,33 @@
 //! 使用独立线程 + 低级键盘钩子 (WH_KEYBOARD_LL) 实现全局快捷键监听
 //! 通过 PostMessage 与主线程通信，避免卡顿
 
+use crate::keymap::{Keymap, Modifiers};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
 use std::thread::{self, JoinHandle};
 use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
 use windows::Win32::System::Threading::GetCurrentThreadId;
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    GetAsyncKeyState, VK_LEFT, VK_RIGHT, VK_LWIN, VK_RWIN,
+    GetAsyncKeyState, VIRTUAL_KEY, VK_CONTROL, VK_LWIN, VK_MENU, VK_RWIN, VK_SHIFT,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
     CallNextHookEx, DispatchMessageW, GetMessageW, PostMessageW, PostThreadMessageW,
     SetWindowsHookExW, TranslateMessage, UnhookWindowsHookEx,
-    KBDLLHOOKSTRUCT, MSG, WH_KEYBOARD_LL, WM_KEYDOWN, WM_QUIT, WM_USER,
+    KBDLLHOOKSTRUCT, LLKHF_INJECTED, MSG, WH_KEYBOARD_LL, WM_KEYDOWN, WM_QUIT, WM_SYSKEYDOWN,
+    WM_USER,
 };
 use tracing::{debug, trace};
 
 /// 自定义消息 ID
 pub const WM_HOTKEY_EVENT: u32 = WM_USER + 100;
 
-/// 快捷键事件（作为 WPARAM 传递）
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(usize)]
-pub enum HotkeyEvent {
-    /// Win+Left: 切换到左边的桌面
-    SwitchLeft = 1,
-    /// Win+Right: 切换到右边的桌面
-    SwitchRight = 2,
-    /// Win+F: 切换全屏空间
-    ToggleFullscreen = 3,
-}
-
-impl HotkeyEvent {
-    pub fn from_wparam(wparam: usize) -> Option<Self> {
-        match wparam {
-            1 => Some(HotkeyEvent::SwitchLeft),
-            2 => Some(HotkeyEvent::SwitchRight),
-            3 => Some(HotkeyEvent::ToggleFullscreen),
-            _ => None,
-        }
-    }
-}
+pub use crate::keymap::HotkeyEvent;
 
 // 全局状态（用于钩子回调）
 static mut MAIN_HWND: HWND = HWND(std::ptr::null_mut());
 static mut HOOK_THREAD_ID: u32 = 0;
 static HOOK_ACTIVE: AtomicBool = AtomicBool::new(false);
+static KEYMAP: RwLock<Keymap> = RwLock::new(Keymap::new());
 
 /// 快捷键管理器
 pub struct HotkeyManager {
@@ -55,11 +38,12 @@ pub struct HotkeyManager {
 
 impl HotkeyManager {
     /// 创建快捷键管理器并在独立线程中安装钩子
-    pub fn new(main_hwnd: HWND) -> Self {
-        // 保存主窗口句柄到全局状态
+    pub fn new(main_hwnd: HWND, keymap: Keymap) -> Self {
+        // 保存主窗口句柄和绑定表到全局状态
         unsafe {
             MAIN_HWND = main_hwnd;
         }
+        *KEYMAP.write().unwrap() = keymap;
         HOOK_ACTIVE.store(true, Ordering::SeqCst);
         
         // 在独立线程中运行钩子
@@ -132,6 +116,21 @@ fn run_hook_thread() {
     }
 }
 
+/// 检测按键当前是否按下
+unsafe fn is_pressed(vk: VIRTUAL_KEY) -> bool {
+    GetAsyncKeyState(vk.0 as i32) < 0
+}
+
+/// 读取当前按下的修饰键
+unsafe fn current_modifiers() -> Modifiers {
+    Modifiers {
+        win: is_pressed(VK_LWIN) || is_pressed(VK_RWIN),
+        ctrl: is_pressed(VK_CONTROL),
+        shift: is_pressed(VK_SHIFT),
+        alt: is_pressed(VK_MENU),
+    }
+}
+
 /// 键盘钩子回调函数
 unsafe extern "system" fn keyboard_proc(
     code: i32,
@@ -141,35 +140,33 @@ unsafe extern "system" fn keyboard_proc(
     if code >= 0 && HOOK_ACTIVE.load(Ordering::SeqCst) {
         let kbd = *(lparam.0 as *const KBDLLHOOKSTRUCT);
         
-        // 只处理按键按下事件
-        if wparam.0 == WM_KEYDOWN as usize {
-            // 检测 Win 键是否按下
-            let win_pressed = GetAsyncKeyState(VK_LWIN.0 as i32) < 0
-                || GetAsyncKeyState(VK_RWIN.0 as i32) < 0;
+        // 跳过程序模拟的按键（包括本程序通过 SendInput 发送的），避免无修饰键的绑定被自己再次触发
+        if kbd.flags.contains(LLKHF_INJECTED) {
+            return CallNextHookEx(None, code, wparam, lparam);
+        }
+        
+        // 只处理按键按下事件（按住 Alt 时系统发送的是 WM_SYSKEYDOWN）
+        if wparam.0 == WM_KEYDOWN as usize || wparam.0 == WM_SYSKEYDOWN as usize {
+            let modifiers = current_modifiers();
+            let event = KEYMAP
+                .read()
+                .ok()
+                .and_then(|keymap| keymap.lookup(modifiers, kbd.vkCode as u16));
             
-            if win_pressed {
-                let event = match kbd.vkCode as u16 {
-                    x if x == VK_LEFT.0 => Some(HotkeyEvent::SwitchLeft),
-                    x if x == VK_RIGHT.0 => Some(HotkeyEvent::SwitchRight),
-                    0x46 => Some(HotkeyEvent::ToggleFullscreen), // 'F' key
-                    _ => None,
-                };
+            if let Some(event) = event {
+                trace!("检测到快捷键: {:?}", event);
+                
+                // 通过 PostMessage 发送到主线程（非阻塞）
+                let _ = PostMessageW(
+                    MAIN_HWND,
+                    WM_HOTKEY_EVENT,
+                    WPARAM(event.to_wparam()),
+                    LPARAM(0),
+                );
                 
-                if let Some(event) = event {
-                    trace!("检测到快捷键: {:?}", event);
-                    
-                    // 通过 PostMessage 发送到主线程（非阻塞）
-                    let _ = PostMessageW(
-                        MAIN_HWND,
-                        WM_HOTKEY_EVENT,
-                        WPARAM(event as usize),
-                        LPARAM(0),
-                    );
-                    
-                    // 阻止事件传递给系统（避免触发 Windows Snap）
-                    if matches!(event, HotkeyEvent::SwitchLeft | HotkeyEvent::SwitchRight) {
-                        return LRESULT(1);
-                    }
+                // 阻止事件传递给系统（避免触发 Windows Snap）
+                if event.suppresses_default() {
+                    return LRESULT(1);
                 }
             }
         }
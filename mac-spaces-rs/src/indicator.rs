@@ -0,0 +1,389 @@
+//! 空间指示器
+//!
+//! 切换桌面后在屏幕上短暂显示一排小圆点（类似 Mac 的空间指示），当前桌面高亮，
+//! 全屏空间显示对应应用的图标，当前桌面有名称时在圆点下方显示名称。窗口置顶、不抢焦点、鼠标点击穿透，
+//! 显示一段时间后淡出。布局计算见 indicator_layout.rs。
+
+use crate::backend::HWND;
+use crate::config::{Color, IndicatorConfig};
+use crate::indicator_layout::{self, IndicatorSnapshot, Rect, SlotKind};
+use std::cell::RefCell;
+use std::time::Instant;
+use tracing::{debug, warn};
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{COLORREF, LPARAM, LRESULT, RECT, SIZE, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreateSolidBrush, DeleteObject, DrawTextW, Ellipse, EndPaint, FillRect, GetDC,
+    GetStockObject, GetTextExtentPoint32W, InvalidateRect, ReleaseDC, RoundRect, SelectObject,
+    SetBkMode, SetTextColor, DEFAULT_GUI_FONT, DT_CENTER, DT_END_ELLIPSIS, DT_NOPREFIX,
+    DT_SINGLELINE, DT_VCENTER, HDC, NULL_PEN, PAINTSTRUCT, TRANSPARENT,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DrawIconEx, GetClassLongPtrW, KillTimer,
+    RegisterClassW, SendMessageTimeoutW, SetLayeredWindowAttributes, SetTimer, SetWindowPos,
+    ShowWindow, SystemParametersInfoW, DI_NORMAL, GCLP_HICON, GCLP_HICONSM, HICON, HWND_TOPMOST,
+    ICON_SMALL2, LWA_ALPHA, LWA_COLORKEY, SMTO_ABORTIFHUNG, SPI_GETWORKAREA,
+    SWP_NOACTIVATE, SWP_SHOWWINDOW, SW_HIDE, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, WM_DESTROY,
+    WM_GETICON, WM_PAINT, WM_TIMER, WNDCLASSW, WS_EX_LAYERED, WS_EX_NOACTIVATE,
+    WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_POPUP,
+};
+
+/// 淡出计时器
+const FADE_TIMER_ID: usize = 1;
+const FADE_TIMER_INTERVAL_MS: u32 = 16;
+
+/// 透明色键：背景圆角外的区域用这个颜色填充后完全透明
+const COLOR_KEY: COLORREF = COLORREF(0x00FF00FF);
+
+/// 当前显示内容（只在 UI 线程访问）
+struct PaintState {
+    /// 背景大小
+    panel: Rect,
+    /// 每个位置相对于背景的矩形和要画的内容
+    slots: Vec<(Rect, SlotPaint)>,
+    /// 当前桌面名称相对于背景的位置和文字（宽字符，不含结尾的 0）
+    label: Option<(Rect, Vec<u16>)>,
+    settings: IndicatorConfig,
+    shown_at: Instant,
+}
+
+/// 一个位置要画的内容
+enum SlotPaint {
+    Dot { current: bool },
+    Icon { icon: HICON, current: bool },
+}
+
+thread_local! {
+    static PAINT_STATE: RefCell<Option<PaintState>> = const { RefCell::new(None) };
+}
+
+/// 空间指示器窗口
+pub struct SpaceIndicator {
+    hwnd: HWND,
+    settings: IndicatorConfig,
+}
+
+impl SpaceIndicator {
+    /// 创建指示器窗口（初始隐藏）
+    pub fn new(settings: IndicatorConfig) -> windows::core::Result<Self> {
+        unsafe {
+            let instance = GetModuleHandleW(None)?;
+            let class_name = wide_string("MacSpacesIndicator");
+
+            let wc = WNDCLASSW {
+                lpfnWndProc: Some(indicator_window_proc),
+                hInstance: instance.into(),
+                lpszClassName: PCWSTR(class_name.as_ptr()),
+                ..Default::default()
+            };
+            RegisterClassW(&wc);
+
+            let hwnd = CreateWindowExW(
+                WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOPMOST | WS_EX_TOOLWINDOW | WS_EX_NOACTIVATE,
+                PCWSTR(class_name.as_ptr()),
+                PCWSTR::null(),
+                WS_POPUP,
+                0,
+                0,
+                0,
+                0,
+                None,
+                None,
+                instance,
+                None,
+            )?;
+
+            Ok(Self { hwnd, settings })
+        }
+    }
+
+    /// 显示桌面状态，之后自动淡出
+    pub fn show(&self, snapshot: &IndicatorSnapshot) {
+        if !self.settings.enabled {
+            return;
+        }
+
+        let count = snapshot.spaces.len();
+        let current = snapshot.current;
+
+        let mut kinds = Vec::with_capacity(count);
+        let mut paints = Vec::with_capacity(count);
+        for (index, space) in snapshot.spaces.iter().enumerate() {
+            let is_current = index as i32 == current;
+            let icon = space.and_then(|raw| window_icon(HWND(raw as *mut std::ffi::c_void)));
+
+            match icon {
+                Some(icon) => {
+                    kinds.push(SlotKind::Space);
+                    paints.push(SlotPaint::Icon {
+                        icon,
+                        current: is_current,
+                    });
+                }
+                None => {
+                    kinds.push(SlotKind::Desktop);
+                    paints.push(SlotPaint::Dot {
+                        current: is_current,
+                    });
+                }
+            }
+        }
+
+        let label_text: Option<Vec<u16>> = snapshot
+            .current_name()
+            .map(|name| name.encode_utf16().collect());
+        let label_size = label_text.as_deref().map(|text| self.text_size(text));
+
+        let layout = indicator_layout::layout(work_area(), &kinds, label_size, &self.settings);
+        let panel = layout.panel;
+        debug!("显示空间指示器: {} 个桌面，当前 #{}", count, current + 1);
+
+        PAINT_STATE.with(|state| {
+            *state.borrow_mut() = Some(PaintState {
+                panel,
+                slots: layout
+                    .slots
+                    .iter()
+                    .map(|rect| rect.relative_to(&panel))
+                    .zip(paints)
+                    .collect(),
+                label: layout
+                    .label
+                    .map(|rect| rect.relative_to(&panel))
+                    .zip(label_text),
+                settings: self.settings.clone(),
+                shown_at: Instant::now(),
+            });
+        });
+
+        unsafe {
+            let _ = SetLayeredWindowAttributes(
+                self.hwnd,
+                COLOR_KEY,
+                self.settings.opacity,
+                LWA_ALPHA | LWA_COLORKEY,
+            );
+            if let Err(e) = SetWindowPos(
+                self.hwnd,
+                HWND_TOPMOST,
+                panel.left,
+                panel.top,
+                panel.width(),
+                panel.height(),
+                SWP_NOACTIVATE | SWP_SHOWWINDOW,
+            ) {
+                warn!("显示空间指示器失败: {:?}", e);
+                return;
+            }
+            let _ = InvalidateRect(self.hwnd, None, true);
+            SetTimer(self.hwnd, FADE_TIMER_ID, FADE_TIMER_INTERVAL_MS, None);
+        }
+    }
+}
+
+impl SpaceIndicator {
+    /// 用指示器的字体测量文字大小（宽、高）
+    fn text_size(&self, text: &[u16]) -> (i32, i32) {
+        let mut size = SIZE::default();
+        unsafe {
+            let hdc = GetDC(self.hwnd);
+            let old_font = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+            let _ = GetTextExtentPoint32W(hdc, text, &mut size);
+            SelectObject(hdc, old_font);
+            ReleaseDC(self.hwnd, hdc);
+        }
+        (size.cx, size.cy)
+    }
+}
+
+impl Drop for SpaceIndicator {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = KillTimer(self.hwnd, FADE_TIMER_ID);
+            let _ = DestroyWindow(self.hwnd);
+        }
+        PAINT_STATE.with(|state| state.borrow_mut().take());
+    }
+}
+
+/// 主显示器的工作区（不含任务栏）
+fn work_area() -> Rect {
+    let mut rect = RECT::default();
+    unsafe {
+        let _ = SystemParametersInfoW(
+            SPI_GETWORKAREA,
+            0,
+            Some(&mut rect as *mut RECT as *mut std::ffi::c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        );
+    }
+    Rect::new(rect.left, rect.top, rect.right, rect.bottom)
+}
+
+/// 获取窗口图标（窗口无响应时不等待）
+fn window_icon(hwnd: HWND) -> Option<HICON> {
+    unsafe {
+        let mut result = 0usize;
+        SendMessageTimeoutW(
+            hwnd,
+            WM_GETICON,
+            WPARAM(ICON_SMALL2 as usize),
+            LPARAM(0),
+            SMTO_ABORTIFHUNG,
+            50,
+            Some(&mut result),
+        );
+        if result == 0 {
+            result = GetClassLongPtrW(hwnd, GCLP_HICONSM);
+        }
+        if result == 0 {
+            result = GetClassLongPtrW(hwnd, GCLP_HICON);
+        }
+        (result != 0).then_some(HICON(result as *mut std::ffi::c_void))
+    }
+}
+
+fn colorref(color: Color) -> COLORREF {
+    COLORREF(color.r as u32 | (color.g as u32) << 8 | (color.b as u32) << 16)
+}
+
+/// 绘制指示器
+unsafe fn paint(hdc: HDC, state: &PaintState) {
+    let settings = &state.settings;
+    let panel = Rect::new(0, 0, state.panel.width(), state.panel.height());
+
+    // 色键填充整个窗口，再画圆角背景
+    let key_brush = CreateSolidBrush(COLOR_KEY);
+    let full = RECT {
+        left: 0,
+        top: 0,
+        right: panel.right,
+        bottom: panel.bottom,
+    };
+    FillRect(hdc, &full, key_brush);
+    let _ = DeleteObject(key_brush);
+
+    let old_pen = SelectObject(hdc, GetStockObject(NULL_PEN));
+    let background = CreateSolidBrush(colorref(settings.background_color));
+    let active = CreateSolidBrush(colorref(settings.active_color));
+    let inactive = CreateSolidBrush(colorref(settings.inactive_color));
+
+    let old_brush = SelectObject(hdc, background);
+    let radius = panel.height();
+    let _ = RoundRect(hdc, 0, 0, panel.right + 1, panel.bottom + 1, radius, radius);
+
+    for (rect, slot) in &state.slots {
+        match slot {
+            SlotPaint::Dot { current } => {
+                SelectObject(hdc, if *current { active } else { inactive });
+                let _ = Ellipse(hdc, rect.left, rect.top, rect.right + 1, rect.bottom + 1);
+            }
+            SlotPaint::Icon { icon, current } => {
+                if *current {
+                    // 当前空间：图标背后画一个高亮的圆角底
+                    SelectObject(hdc, active);
+                    let _ = RoundRect(
+                        hdc,
+                        rect.left - 2,
+                        rect.top - 2,
+                        rect.right + 3,
+                        rect.bottom + 3,
+                        6,
+                        6,
+                    );
+                }
+                let _ = DrawIconEx(
+                    hdc,
+                    rect.left,
+                    rect.top,
+                    *icon,
+                    rect.width(),
+                    rect.height(),
+                    0,
+                    None,
+                    DI_NORMAL,
+                );
+            }
+        }
+    }
+
+    if let Some((rect, text)) = &state.label {
+        let mut text = text.clone();
+        let mut rect = RECT {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        };
+        let old_font = SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+        SetBkMode(hdc, TRANSPARENT);
+        SetTextColor(hdc, colorref(settings.active_color));
+        DrawTextW(
+            hdc,
+            &mut text,
+            &mut rect,
+            DT_CENTER | DT_VCENTER | DT_SINGLELINE | DT_END_ELLIPSIS | DT_NOPREFIX,
+        );
+        SelectObject(hdc, old_font);
+    }
+
+    SelectObject(hdc, old_brush);
+    SelectObject(hdc, old_pen);
+    let _ = DeleteObject(background);
+    let _ = DeleteObject(active);
+    let _ = DeleteObject(inactive);
+}
+
+/// 指示器窗口过程
+unsafe extern "system" fn indicator_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_PAINT => {
+            let mut ps = PAINTSTRUCT::default();
+            let hdc = BeginPaint(hwnd, &mut ps);
+            PAINT_STATE.with(|state| {
+                if let Some(state) = state.borrow().as_ref() {
+                    paint(hdc, state);
+                }
+            });
+            let _ = EndPaint(hwnd, &ps);
+            LRESULT(0)
+        }
+        WM_TIMER if wparam.0 == FADE_TIMER_ID => {
+            let alpha = PAINT_STATE.with(|state| {
+                state.borrow().as_ref().and_then(|state| {
+                    let elapsed = state.shown_at.elapsed().as_millis() as u64;
+                    indicator_layout::fade_alpha(
+                        elapsed,
+                        state.settings.display_ms,
+                        state.settings.fade_ms,
+                        state.settings.opacity,
+                    )
+                })
+            });
+
+            match alpha {
+                Some(alpha) => {
+                    let _ = SetLayeredWindowAttributes(hwnd, COLOR_KEY, alpha, LWA_ALPHA | LWA_COLORKEY);
+                }
+                None => {
+                    let _ = KillTimer(hwnd, FADE_TIMER_ID);
+                    let _ = ShowWindow(hwnd, SW_HIDE);
+                }
+            }
+            LRESULT(0)
+        }
+        WM_DESTROY => LRESULT(0),
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// 转换为宽字符串
+fn wide_string(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
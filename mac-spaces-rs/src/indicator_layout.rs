@@ -0,0 +1,369 @@
+//! 空间指示器布局
+//!
+//! 计算指示器背景和每个圆点在屏幕上的位置，以及淡出过程中的不透明度。
+//! 纯计算，不依赖 Windows API，绘制见 indicator.rs。
+
+use crate::backend::DesktopBackend;
+use crate::config::{IndicatorConfig, IndicatorPosition};
+use crate::registry::SpaceRegistry;
+
+/// 屏幕矩形（像素，right/bottom 不包含）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl Rect {
+    pub const fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub const fn width(&self) -> i32 {
+        self.right - self.left
+    }
+
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub const fn height(&self) -> i32 {
+        self.bottom - self.top
+    }
+
+    /// 相对于 `origin` 左上角的坐标
+    pub const fn relative_to(&self, origin: &Rect) -> Rect {
+        Rect::new(
+            self.left - origin.left,
+            self.top - origin.top,
+            self.right - origin.left,
+            self.bottom - origin.top,
+        )
+    }
+}
+
+/// 指示器要显示的桌面状态
+///
+/// 在持有后端和注册表的后台线程采集，交给 UI 线程绘制（只包含可以跨线程传递的数据）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndicatorSnapshot {
+    /// 当前桌面索引
+    pub current: i32,
+    /// 每个桌面对应的全屏空间窗口句柄（普通桌面为 None）
+    pub spaces: Vec<Option<isize>>,
+    /// 每个桌面的名称（未命名或 DLL 不支持名称时为空）
+    pub names: Vec<String>,
+}
+
+impl IndicatorSnapshot {
+    /// 读取当前桌面列表和各桌面上的全屏空间
+    pub fn capture(backend: &dyn DesktopBackend, registry: &SpaceRegistry) -> Self {
+        let count = backend.get_desktop_count();
+        let spaces = (0..count)
+            .map(|index| {
+                backend
+                    .get_desktop_id(index)
+                    .and_then(|id| registry.get_by_desktop(id))
+                    .map(|space| space.hwnd.0 as isize)
+            })
+            .collect();
+        let names = (0..count)
+            .map(|index| backend.get_desktop_name(index).unwrap_or_default())
+            .collect();
+
+        Self {
+            current: backend.get_current_desktop(),
+            spaces,
+            names,
+        }
+    }
+
+    /// 当前桌面的名称（未命名时返回 None）
+    pub fn current_name(&self) -> Option<&str> {
+        usize::try_from(self.current)
+            .ok()
+            .and_then(|index| self.names.get(index))
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+    }
+}
+
+/// 指示器中一个位置的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotKind {
+    /// 普通桌面，画成圆点
+    Desktop,
+    /// 全屏空间，画成应用图标
+    Space,
+}
+
+/// 指示器布局结果（屏幕坐标）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndicatorLayout {
+    /// 背景
+    pub panel: Rect,
+    /// 每个桌面对应的圆点或图标，顺序与桌面索引一致
+    pub slots: Vec<Rect>,
+    /// 当前桌面名称的位置（没有名称时为 None）
+    pub label: Option<Rect>,
+}
+
+/// 计算指示器布局
+///
+/// 背景在 `screen` 内水平居中，按配置贴近顶部或底部。圆点放不下时
+/// 按比例缩小圆点、图标和间距（最小 1 像素），保证指示器不超出屏幕。
+/// `label` 为当前桌面名称的文字大小（宽、高），放在圆点下方居中，过长时截断到屏幕宽度。
+pub fn layout(
+    screen: Rect,
+    slots: &[SlotKind],
+    label: Option<(i32, i32)>,
+    config: &IndicatorConfig,
+) -> IndicatorLayout {
+    let count = slots.len() as i32;
+    let padding = config.padding;
+
+    let natural_width = |dot: i32, icon: i32, spacing: i32| -> i32 {
+        let items: i32 = slots
+            .iter()
+            .map(|kind| match kind {
+                SlotKind::Desktop => dot,
+                SlotKind::Space => icon,
+            })
+            .sum();
+        items + spacing * (count - 1).max(0)
+    };
+
+    let mut dot = config.dot_size;
+    let mut icon = config.icon_size;
+    let mut spacing = config.spacing;
+
+    let available = screen.width() - padding * 2;
+    let needed = natural_width(dot, icon, spacing);
+    if needed > available && needed > 0 {
+        let scale = available.max(0) as f64 / needed as f64;
+        dot = ((dot as f64 * scale) as i32).max(1);
+        icon = ((icon as f64 * scale) as i32).max(1);
+        spacing = (spacing as f64 * scale) as i32;
+    }
+
+    let row_height = slots
+        .iter()
+        .map(|kind| match kind {
+            SlotKind::Desktop => dot,
+            SlotKind::Space => icon,
+        })
+        .max()
+        .unwrap_or(dot);
+
+    let row_width = natural_width(dot, icon, spacing);
+    let label = label.map(|(width, height)| (width.min(available.max(0)), height));
+    let content_width = label.map_or(row_width, |(width, _)| row_width.max(width));
+    let label_height = label.map_or(0, |(_, height)| height + spacing);
+
+    let width = content_width + padding * 2;
+    let height = row_height + label_height + padding * 2;
+    let left = screen.left + (screen.width() - width) / 2;
+    let top = match config.position {
+        IndicatorPosition::Top => screen.top + config.margin,
+        IndicatorPosition::Bottom => screen.bottom - config.margin - height,
+    };
+    let panel = Rect::new(left, top, left + width, top + height);
+
+    let center_y = top + padding + row_height / 2;
+    let mut x = left + padding + (content_width - row_width) / 2;
+    let rects = slots
+        .iter()
+        .map(|kind| {
+            let size = match kind {
+                SlotKind::Desktop => dot,
+                SlotKind::Space => icon,
+            };
+            let rect = Rect::new(x, center_y - size / 2, x + size, center_y - size / 2 + size);
+            x += size + spacing;
+            rect
+        })
+        .collect();
+
+    let label = label.map(|(label_width, label_height)| {
+        let label_left = left + (width - label_width) / 2;
+        let label_top = top + padding + row_height + spacing;
+        Rect::new(label_left, label_top, label_left + label_width, label_top + label_height)
+    });
+
+    IndicatorLayout {
+        panel,
+        slots: rects,
+        label,
+    }
+}
+
+/// 显示开始后 `elapsed_ms` 时的不透明度，已完全淡出时返回 None
+///
+/// 前 `display_ms` 保持 `max_alpha`，之后在 `fade_ms` 内线性降到 0
+pub fn fade_alpha(elapsed_ms: u64, display_ms: u64, fade_ms: u64, max_alpha: u8) -> Option<u8> {
+    if elapsed_ms < display_ms {
+        return Some(max_alpha);
+    }
+
+    let fading = elapsed_ms - display_ms;
+    if fading >= fade_ms {
+        return None;
+    }
+
+    let remaining = (fade_ms - fading) as f64 / fade_ms as f64;
+    Some((max_alpha as f64 * remaining).round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect::new(0, 0, 1920, 1080);
+
+    fn config() -> IndicatorConfig {
+        IndicatorConfig {
+            margin: 20,
+            dot_size: 10,
+            icon_size: 20,
+            spacing: 10,
+            padding: 8,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_dots_are_centered_at_top() {
+        let result = layout(SCREEN, &[SlotKind::Desktop; 3], None, &config());
+
+        // 3 * 10 + 2 * 10 + 2 * 8 = 66
+        assert_eq!(result.panel, Rect::new(927, 20, 993, 46));
+        assert_eq!(
+            result.slots,
+            vec![
+                Rect::new(935, 28, 945, 38),
+                Rect::new(955, 28, 965, 38),
+                Rect::new(975, 28, 985, 38),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bottom_position_respects_margin() {
+        let config = IndicatorConfig {
+            position: IndicatorPosition::Bottom,
+            ..config()
+        };
+        let screen = Rect::new(1920, 0, 3840, 1040); // 副屏，底部是任务栏
+        let result = layout(screen, &[SlotKind::Desktop], None, &config);
+
+        assert_eq!(result.panel, Rect::new(2867, 994, 2893, 1020));
+        assert_eq!(result.slots, vec![Rect::new(2875, 1002, 2885, 1012)]);
+    }
+
+    #[test]
+    fn test_space_icons_are_vertically_centered() {
+        let result = layout(
+            SCREEN,
+            &[SlotKind::Desktop, SlotKind::Space, SlotKind::Desktop],
+            None,
+            &config(),
+        );
+
+        // 10 + 20 + 10 + 2 * 10 + 2 * 8 = 76，行高取图标的 20
+        assert_eq!(result.panel, Rect::new(922, 20, 998, 56));
+        assert_eq!(result.slots[0], Rect::new(930, 33, 940, 43));
+        assert_eq!(result.slots[1], Rect::new(950, 28, 970, 48));
+        assert_eq!(result.slots[2], Rect::new(980, 33, 990, 43));
+    }
+
+    #[test]
+    fn test_many_desktops_shrink_to_fit() {
+        let screen = Rect::new(0, 0, 400, 300);
+        let result = layout(screen, &[SlotKind::Desktop; 50], None, &config());
+
+        assert!(result.panel.left >= screen.left);
+        assert!(result.panel.right <= screen.right);
+        assert_eq!(result.slots.len(), 50);
+        assert!(result.slots.windows(2).all(|w| w[0].right <= w[1].left));
+        assert!(result.slots.iter().all(|r| r.width() >= 1));
+    }
+
+    #[test]
+    fn test_empty_layout() {
+        let result = layout(SCREEN, &[], None, &config());
+        assert!(result.slots.is_empty());
+        assert_eq!(result.panel.width(), 16);
+    }
+
+    #[test]
+    fn test_label_below_dots_widens_panel() {
+        let result = layout(SCREEN, &[SlotKind::Desktop; 2], Some((100, 16)), &config());
+
+        // 名称比圆点行（2 * 10 + 10 = 30）宽，背景按名称加宽：100 + 2 * 8 = 116
+        // 高度：10 + (16 + 10) + 2 * 8 = 52
+        assert_eq!(result.panel, Rect::new(902, 20, 1018, 72));
+        assert_eq!(result.slots[0], Rect::new(945, 28, 955, 38));
+        assert_eq!(result.slots[1], Rect::new(965, 28, 975, 38));
+        assert_eq!(result.label, Some(Rect::new(910, 48, 1010, 64)));
+    }
+
+    #[test]
+    fn test_long_label_is_clipped_to_screen() {
+        let screen = Rect::new(0, 0, 400, 300);
+        let result = layout(screen, &[SlotKind::Desktop], Some((2000, 16)), &config());
+
+        assert!(result.panel.left >= screen.left);
+        assert!(result.panel.right <= screen.right);
+        assert_eq!(result.label.unwrap().width(), 400 - 2 * 8);
+    }
+
+    #[test]
+    fn test_relative_rect() {
+        let panel = Rect::new(100, 50, 200, 80);
+        assert_eq!(
+            Rect::new(110, 60, 120, 70).relative_to(&panel),
+            Rect::new(10, 10, 20, 20)
+        );
+    }
+
+    #[test]
+    fn test_snapshot_marks_space_desktops() {
+        use crate::backend::fake::{hwnd, FakeBackend};
+        use crate::backend::WindowDetails;
+        use crate::registry::SpaceInfo;
+
+        let backend = FakeBackend::new(3);
+        backend.go_to_desktop(2);
+        let mut registry = SpaceRegistry::new();
+        registry.register(SpaceInfo::new(
+            hwnd(0x100),
+            WindowDetails::default(),
+            backend.get_desktop_id(0).unwrap(),
+            backend.get_desktop_id(1).unwrap(),
+        ));
+
+        let snapshot = IndicatorSnapshot::capture(&backend, &registry);
+
+        assert_eq!(snapshot.current, 2);
+        assert_eq!(snapshot.spaces, vec![None, Some(0x100), None]);
+        assert_eq!(snapshot.current_name(), None);
+
+        backend.rename_desktop(2, "工作");
+        let snapshot = IndicatorSnapshot::capture(&backend, &registry);
+        assert_eq!(snapshot.current_name(), Some("工作"));
+    }
+
+    #[test]
+    fn test_fade_alpha() {
+        assert_eq!(fade_alpha(0, 800, 300, 200), Some(200));
+        assert_eq!(fade_alpha(799, 800, 300, 200), Some(200));
+        assert_eq!(fade_alpha(800, 800, 300, 200), Some(200));
+        assert_eq!(fade_alpha(950, 800, 300, 200), Some(100));
+        assert_eq!(fade_alpha(1100, 800, 300, 200), None);
+        assert_eq!(fade_alpha(800, 800, 0, 200), None);
+    }
+}
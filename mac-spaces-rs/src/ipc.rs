@@ -0,0 +1,447 @@
+//! 命令行控制
+//!
+//! 运行中的实例监听一个本机连接（Windows 上为命名管道，见 pipe.rs），接受 JSON 命令，
+//! 用于脚本控制；`mac_spaces ctl ...` 把命令行参数转换为命令发送过去并打印结果。
+//!
+//! 每个连接只收发一条消息：客户端发送一行 JSON 命令，服务端执行后回复一行 JSON 结果。
+//! 传输方式抽象为 `Transport`，Linux 上的测试使用 Unix 套接字。
+
+use crate::desktop::Direction;
+use crate::executor::{Action, Report};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::thread::{self, JoinHandle};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+/// 单条消息的最大长度（字节），防止异常的客户端占满内存
+const MAX_MESSAGE_LEN: u64 = 64 * 1024;
+
+/// `mac_spaces ctl` 的用法说明
+pub const USAGE: &str = "\
+用法: mac_spaces ctl <命令>
+
+命令:
+  switch left|right                切换到左边/右边的桌面
+  goto <N>                         切换到第 N 个桌面（从 1 开始）
+  fullscreen toggle [--hwnd <句柄>]  切换活动窗口（或指定窗口）的全屏空间，句柄可以是十进制或 0x 开头的十六进制
+  list spaces                      列出全屏空间";
+
+#[derive(Error, Debug)]
+pub enum IpcError {
+    #[error("{0}\n\n{usage}", usage = USAGE)]
+    Usage(String),
+
+    #[error("MacSpaces 没有在运行")]
+    NotRunning,
+
+    #[error("无法解析消息: {0}")]
+    Protocol(String),
+
+    #[error("连接失败: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// 客户端发送的命令
+///
+/// JSON 形式以 `command` 字段区分，例如 `{"command":"switch","direction":"left"}`、
+/// `{"command":"goto","desktop":3}`、`{"command":"fullscreen_toggle","hwnd":4660}`、
+/// `{"command":"list_spaces"}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Command {
+    /// 切换到相邻桌面
+    Switch { direction: Direction },
+    /// 切换到第 N 个桌面（从 1 开始）
+    Goto { desktop: i32 },
+    /// 切换全屏空间（未指定窗口时为活动窗口）
+    FullscreenToggle {
+        #[serde(default)]
+        hwnd: Option<isize>,
+    },
+    /// 列出全屏空间
+    ListSpaces,
+}
+
+impl Command {
+    /// 解析 `mac_spaces ctl` 之后的参数
+    pub fn from_args(args: &[String]) -> Result<Self, IpcError> {
+        let words: Vec<&str> = args.iter().map(String::as_str).collect();
+        match words.as_slice() {
+            ["switch", "left"] => Ok(Command::Switch { direction: Direction::Left }),
+            ["switch", "right"] => Ok(Command::Switch { direction: Direction::Right }),
+            ["switch", other] => Err(IpcError::Usage(format!("未知的方向: {}", other))),
+            ["goto", n] => match n.parse::<i32>() {
+                Ok(desktop) if desktop >= 1 => Ok(Command::Goto { desktop }),
+                _ => Err(IpcError::Usage(format!("桌面编号必须是正整数: {}", n))),
+            },
+            ["fullscreen", "toggle"] => Ok(Command::FullscreenToggle { hwnd: None }),
+            ["fullscreen", "toggle", "--hwnd", hwnd] => match parse_hwnd(hwnd) {
+                Some(hwnd) => Ok(Command::FullscreenToggle { hwnd: Some(hwnd) }),
+                None => Err(IpcError::Usage(format!("无效的窗口句柄: {}", hwnd))),
+            },
+            ["list", "spaces"] => Ok(Command::ListSpaces),
+            [] => Err(IpcError::Usage("缺少命令".to_string())),
+            _ => Err(IpcError::Usage(format!("未知的命令: {}", words.join(" ")))),
+        }
+    }
+
+    /// 对应的后台动作
+    pub fn action(&self) -> Action {
+        match *self {
+            Command::Switch { direction: Direction::Left } => Action::Switch { steps: -1 },
+            Command::Switch { direction: Direction::Right } => Action::Switch { steps: 1 },
+            Command::Goto { desktop } => Action::GoTo(desktop - 1),
+            Command::FullscreenToggle { hwnd: None } => Action::ToggleFullscreen,
+            Command::FullscreenToggle { hwnd: Some(hwnd) } => Action::ToggleWindowFullscreen(hwnd),
+            Command::ListSpaces => Action::ListSpaces,
+        }
+    }
+}
+
+/// 解析窗口句柄：十进制或 `0x` 开头的十六进制
+fn parse_hwnd(text: &str) -> Option<isize> {
+    let hwnd = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => isize::from_str_radix(hex, 16).ok()?,
+        None => text.parse().ok()?,
+    };
+    (hwnd != 0).then_some(hwnd)
+}
+
+/// 服务端的回复
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Response {
+    /// 命令是否执行成功
+    pub ok: bool,
+    /// 输出内容（失败时为错误信息）
+    #[serde(default)]
+    pub output: String,
+}
+
+impl Response {
+    pub fn success(output: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            output: output.into(),
+        }
+    }
+
+    pub fn failure(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            output: message.into(),
+        }
+    }
+
+    /// 根据动作产生的结果生成回复：有失败时报告失败，否则输出 `Info` 的内容
+    ///
+    /// `reports` 为 None 表示后台线程已退出
+    pub fn from_reports(reports: Option<Vec<Report>>) -> Self {
+        let Some(reports) = reports else {
+            return Response::failure("MacSpaces 正在退出");
+        };
+        let failures: Vec<&str> = reports
+            .iter()
+            .filter_map(|r| match r {
+                Report::Failed(message) => Some(message.as_str()),
+                _ => None,
+            })
+            .collect();
+        if !failures.is_empty() {
+            return Response::failure(failures.join("\n"));
+        }
+        let output: Vec<&str> = reports
+            .iter()
+            .filter_map(|r| match r {
+                Report::Info { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        Response::success(output.join("\n"))
+    }
+}
+
+/// 一个连接（命名管道或 Unix 套接字）
+pub trait Connection: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Connection for T {}
+
+/// 等待客户端连接
+pub trait Listener: Send {
+    fn accept(&mut self) -> io::Result<Box<dyn Connection>>;
+}
+
+/// 传输方式
+pub trait Transport {
+    /// 开始监听（同一个地址已有实例在监听时返回错误）
+    fn listen(&self) -> io::Result<Box<dyn Listener>>;
+
+    /// 连接到正在监听的实例
+    fn connect(&self) -> io::Result<Box<dyn Connection>>;
+}
+
+/// 读取一行消息（不含换行符）
+fn read_message(connection: &mut dyn Connection) -> Result<String, IpcError> {
+    let mut line = String::new();
+    BufReader::new(connection.take(MAX_MESSAGE_LEN)).read_line(&mut line)?;
+    if !line.ends_with('\n') {
+        return Err(IpcError::Protocol("消息不完整或过长".to_string()));
+    }
+    Ok(line.trim_end().to_string())
+}
+
+/// 发送一行消息
+fn write_message(connection: &mut dyn Connection, message: &impl Serialize) -> Result<(), IpcError> {
+    let mut line = serde_json::to_string(message).map_err(|e| IpcError::Protocol(e.to_string()))?;
+    line.push('\n');
+    connection.write_all(line.as_bytes())?;
+    connection.flush()?;
+    Ok(())
+}
+
+/// 发送命令并等待回复
+pub fn send(transport: &dyn Transport, command: &Command) -> Result<Response, IpcError> {
+    let mut connection = transport.connect().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => IpcError::NotRunning,
+        _ => IpcError::Io(e),
+    })?;
+    write_message(connection.as_mut(), command)?;
+    let line = read_message(connection.as_mut())?;
+    serde_json::from_str(&line).map_err(|e| IpcError::Protocol(e.to_string()))
+}
+
+/// 处理一个连接：读取命令，交给 `handler` 执行，回复结果
+fn serve_connection(
+    connection: &mut dyn Connection,
+    handler: &mut dyn FnMut(Command) -> Response,
+) -> Result<(), IpcError> {
+    let line = read_message(connection)?;
+    let response = match serde_json::from_str::<Command>(&line) {
+        Ok(command) => {
+            info!("收到控制命令: {:?}", command);
+            handler(command)
+        }
+        Err(e) => {
+            debug!("无法解析的控制命令: {}", line);
+            Response::failure(format!("无法解析命令: {}", e))
+        }
+    };
+    write_message(connection, &response)
+}
+
+/// 逐个处理客户端连接，等待连接失败时返回
+pub fn serve(listener: &mut dyn Listener, handler: &mut dyn FnMut(Command) -> Response) -> io::Error {
+    loop {
+        let mut connection = match listener.accept() {
+            Ok(connection) => connection,
+            Err(e) => return e,
+        };
+        if let Err(e) = serve_connection(connection.as_mut(), handler) {
+            warn!("处理控制命令失败: {}", e);
+        }
+    }
+}
+
+/// 开始监听并在后台线程中处理连接
+///
+/// 监听在调用线程中完成，地址被占用等错误直接返回
+pub fn spawn_server<H>(transport: &dyn Transport, mut handler: H) -> io::Result<JoinHandle<()>>
+where
+    H: FnMut(Command) -> Response + Send + 'static,
+{
+    let mut listener = transport.listen()?;
+    thread::Builder::new()
+        .name("mac-spaces-ipc".to_string())
+        .spawn(move || {
+            let e = serve(listener.as_mut(), &mut handler);
+            warn!("命令行控制已停止: {}", e);
+        })
+}
+
+/// `mac_spaces ctl` 的入口：发送命令并打印结果，返回进程退出码
+///
+/// 0 成功；1 命令执行失败或无法连接；2 参数错误
+pub fn run_ctl(transport: &dyn Transport, args: &[String]) -> i32 {
+    let command = match Command::from_args(args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    match send(transport, &command) {
+        Ok(response) if response.ok => {
+            if !response.output.is_empty() {
+                println!("{}", response.output);
+            }
+            0
+        }
+        Ok(response) => {
+            eprintln!("{}", response.output);
+            1
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+/// Unix 套接字（Linux 上测试用，也便于以后移植）
+#[cfg(unix)]
+pub struct UnixSocketTransport {
+    path: std::path::PathBuf,
+}
+
+#[cfg(unix)]
+impl UnixSocketTransport {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[cfg(unix)]
+impl Listener for std::os::unix::net::UnixListener {
+    fn accept(&mut self) -> io::Result<Box<dyn Connection>> {
+        let (stream, _) = std::os::unix::net::UnixListener::accept(self)?;
+        Ok(Box::new(stream))
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixSocketTransport {
+    fn listen(&self) -> io::Result<Box<dyn Listener>> {
+        Ok(Box::new(std::os::unix::net::UnixListener::bind(&self.path)?))
+    }
+
+    fn connect(&self) -> io::Result<Box<dyn Connection>> {
+        Ok(Box::new(std::os::unix::net::UnixStream::connect(&self.path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_command_line() {
+        assert_eq!(
+            Command::from_args(&args("switch left")).unwrap(),
+            Command::Switch { direction: Direction::Left }
+        );
+        assert_eq!(Command::from_args(&args("goto 3")).unwrap(), Command::Goto { desktop: 3 });
+        assert_eq!(
+            Command::from_args(&args("fullscreen toggle")).unwrap(),
+            Command::FullscreenToggle { hwnd: None }
+        );
+        assert_eq!(
+            Command::from_args(&args("fullscreen toggle --hwnd 0x1A2B")).unwrap(),
+            Command::FullscreenToggle { hwnd: Some(0x1A2B) }
+        );
+        assert_eq!(
+            Command::from_args(&args("fullscreen toggle --hwnd 4660")).unwrap(),
+            Command::FullscreenToggle { hwnd: Some(4660) }
+        );
+        assert_eq!(Command::from_args(&args("list spaces")).unwrap(), Command::ListSpaces);
+
+        for bad in ["", "switch up", "goto 0", "goto x", "fullscreen toggle --hwnd", "fullscreen toggle --hwnd 0", "list"] {
+            assert!(matches!(Command::from_args(&args(bad)), Err(IpcError::Usage(_))), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_command_json_and_action() {
+        let command = Command::Switch { direction: Direction::Right };
+        assert_eq!(serde_json::to_string(&command).unwrap(), r#"{"command":"switch","direction":"right"}"#);
+        assert_eq!(command.action(), Action::Switch { steps: 1 });
+
+        let command: Command = serde_json::from_str(r#"{"command":"goto","desktop":3}"#).unwrap();
+        assert_eq!(command.action(), Action::GoTo(2));
+
+        let command: Command = serde_json::from_str(r#"{"command":"fullscreen_toggle"}"#).unwrap();
+        assert_eq!(command.action(), Action::ToggleFullscreen);
+        let command: Command = serde_json::from_str(r#"{"command":"fullscreen_toggle","hwnd":4660}"#).unwrap();
+        assert_eq!(command.action(), Action::ToggleWindowFullscreen(4660));
+
+        let command: Command = serde_json::from_str(r#"{"command":"list_spaces"}"#).unwrap();
+        assert_eq!(command.action(), Action::ListSpaces);
+
+        assert!(serde_json::from_str::<Command>(r#"{"command":"reboot"}"#).is_err());
+    }
+
+    #[test]
+    fn test_response_from_reports() {
+        let info = Report::Info {
+            title: "MacSpaces 全屏空间",
+            text: "#2 App — 全屏".to_string(),
+        };
+        assert_eq!(
+            Response::from_reports(Some(vec![Report::CurrentDesktop("#2".to_string()), info])),
+            Response::success("#2 App — 全屏")
+        );
+        assert_eq!(
+            Response::from_reports(Some(vec![Report::Failed("窗口不存在: 0x1".to_string())])),
+            Response::failure("窗口不存在: 0x1")
+        );
+        assert_eq!(Response::from_reports(Some(Vec::new())), Response::success(""));
+        assert!(!Response::from_reports(None).ok);
+    }
+
+    #[cfg(unix)]
+    fn socket_path(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("mac_spaces_ipc_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.join("ctl.sock")
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commands_over_unix_socket() {
+        let path = socket_path("round_trip");
+        let transport = UnixSocketTransport::new(&path);
+
+        // 实例未运行
+        assert!(matches!(
+            send(&transport, &Command::ListSpaces),
+            Err(IpcError::NotRunning)
+        ));
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        spawn_server(&transport, move |command: Command| {
+            tx.send(command).unwrap();
+            match command {
+                Command::ListSpaces => Response::success("#2 App — 全屏"),
+                _ => Response::failure("不支持"),
+            }
+        })
+        .unwrap();
+
+        assert_eq!(
+            send(&transport, &Command::ListSpaces).unwrap(),
+            Response::success("#2 App — 全屏")
+        );
+        assert_eq!(
+            send(&transport, &Command::Goto { desktop: 3 }).unwrap(),
+            Response::failure("不支持")
+        );
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![Command::ListSpaces, Command::Goto { desktop: 3 }]);
+
+        // 无法解析的消息得到失败的回复，服务端继续处理下一个连接
+        let mut connection = transport.connect().unwrap();
+        connection.write_all(b"{\"command\":\"reboot\"}\n").unwrap();
+        let reply: Response = serde_json::from_str(&read_message(connection.as_mut()).unwrap()).unwrap();
+        assert!(!reply.ok);
+        assert_eq!(run_ctl(&transport, &args("list spaces")), 0);
+        assert_eq!(run_ctl(&transport, &args("switch left")), 1);
+        assert_eq!(run_ctl(&transport, &args("switch")), 2);
+
+        // 同一个地址不能再次监听
+        assert!(transport.listen().is_err());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,547 @@
+//! 快捷键映射
+//!
+//! 解析 `"Win+Ctrl+Shift+3"`、`"Alt+Win+Left"` 这样的组合键字符串，
+//! 并维护组合键到 `HotkeyEvent` 的绑定表。本模块不依赖 Win32，可在任意平台测试。
+
+use crate::config::{ConfigError, HotkeyConfig};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// `GoTo(n)` 编码为 WPARAM 时的起始值
+const WPARAM_GOTO_BASE: usize = 100;
+
+/// 快捷键事件（通过 WPARAM 传递）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyEvent {
+    /// 切换到左边的桌面（默认 Win+Left）
+    SwitchLeft,
+    /// 切换到右边的桌面（默认 Win+Right）
+    SwitchRight,
+    /// 切换全屏空间（默认 Win+F）
+    ToggleFullscreen,
+    /// 将活动窗口移动到左边的桌面（默认 Win+Shift+Left）
+    MoveWindowLeft,
+    /// 将活动窗口移动到右边的桌面（默认 Win+Shift+Right）
+    MoveWindowRight,
+    /// 将活动窗口加入全屏空间（默认 Win+Shift+F）
+    SendToSpace,
+    /// 活动窗口和它下面的窗口进入分屏（默认 Win+Alt+F）
+    SplitView,
+    /// 分屏的分隔线左移（默认 Win+Alt+Shift+Left）
+    SplitLeft,
+    /// 分屏的分隔线右移（默认 Win+Alt+Shift+Right）
+    SplitRight,
+    /// 直接切换到第 n 个桌面（从 1 开始，默认 Win+1..9）
+    GoTo(usize),
+}
+
+impl HotkeyEvent {
+    pub fn to_wparam(self) -> usize {
+        match self {
+            HotkeyEvent::SwitchLeft => 1,
+            HotkeyEvent::SwitchRight => 2,
+            HotkeyEvent::ToggleFullscreen => 3,
+            HotkeyEvent::MoveWindowLeft => 4,
+            HotkeyEvent::MoveWindowRight => 5,
+            HotkeyEvent::SendToSpace => 6,
+            HotkeyEvent::SplitView => 7,
+            HotkeyEvent::SplitLeft => 8,
+            HotkeyEvent::SplitRight => 9,
+            HotkeyEvent::GoTo(n) => WPARAM_GOTO_BASE + n,
+        }
+    }
+
+    pub fn from_wparam(wparam: usize) -> Option<Self> {
+        match wparam {
+            1 => Some(HotkeyEvent::SwitchLeft),
+            2 => Some(HotkeyEvent::SwitchRight),
+            3 => Some(HotkeyEvent::ToggleFullscreen),
+            4 => Some(HotkeyEvent::MoveWindowLeft),
+            5 => Some(HotkeyEvent::MoveWindowRight),
+            6 => Some(HotkeyEvent::SendToSpace),
+            7 => Some(HotkeyEvent::SplitView),
+            8 => Some(HotkeyEvent::SplitLeft),
+            9 => Some(HotkeyEvent::SplitRight),
+            n if n > WPARAM_GOTO_BASE => Some(HotkeyEvent::GoTo(n - WPARAM_GOTO_BASE)),
+            _ => None,
+        }
+    }
+
+    /// 是否阻止按键继续传递给系统（避免触发 Windows Snap、跨显示器移动窗口等默认行为）
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn suppresses_default(&self) -> bool {
+        matches!(
+            self,
+            HotkeyEvent::SwitchLeft
+                | HotkeyEvent::SwitchRight
+                | HotkeyEvent::MoveWindowLeft
+                | HotkeyEvent::MoveWindowRight
+                | HotkeyEvent::GoTo(_)
+        )
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ChordError {
+    #[error("快捷键为空")]
+    Empty,
+
+    #[error("\"{0}\" 缺少按键（不能只有修饰键）")]
+    MissingKey(String),
+
+    #[error("\"{0}\" 包含多个非修饰键")]
+    MultipleKeys(String),
+
+    #[error("\"{0}\" 中重复出现修饰键 {1}")]
+    DuplicateModifier(String, String),
+
+    #[error("\"{0}\" 中的按键 \"{1}\" 无法识别")]
+    UnknownKey(String, String),
+
+    #[error("\"{0}\" 至少需要一个修饰键（Win/Ctrl/Shift/Alt），F1-F24 除外")]
+    NoModifier(String),
+}
+
+/// 修饰键集合
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    pub win: bool,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 组合键：修饰键集合 + 虚拟键码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub modifiers: Modifiers,
+    pub vk: u16,
+}
+
+impl Chord {
+    /// F11（多数应用切换全屏模式的按键）
+    pub const F11: Chord = Chord {
+        modifiers: Modifiers {
+            win: false,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        },
+        vk: 0x7A,
+    };
+
+    pub fn new(modifiers: Modifiers, vk: u16) -> Self {
+        Self { modifiers, vk }
+    }
+}
+
+impl FromStr for Chord {
+    type Err = ChordError;
+
+    /// 解析组合键字符串（不区分大小写，修饰键顺序任意）
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.trim().is_empty() {
+            return Err(ChordError::Empty);
+        }
+
+        let mut modifiers = Modifiers::default();
+        let mut vk = None;
+
+        for token in text.split('+').map(str::trim) {
+            if token.is_empty() {
+                return Err(ChordError::MissingKey(text.to_string()));
+            }
+
+            if let Some(flag) = modifier_flag(&mut modifiers, token) {
+                if *flag {
+                    return Err(ChordError::DuplicateModifier(text.to_string(), token.to_string()));
+                }
+                *flag = true;
+                continue;
+            }
+
+            if vk.is_some() {
+                return Err(ChordError::MultipleKeys(text.to_string()));
+            }
+            vk = Some(
+                key_code(token)
+                    .ok_or_else(|| ChordError::UnknownKey(text.to_string(), token.to_string()))?,
+            );
+        }
+
+        let vk = vk.ok_or_else(|| ChordError::MissingKey(text.to_string()))?;
+
+        if modifiers.is_empty() && !is_function_key(vk) {
+            return Err(ChordError::NoModifier(text.to_string()));
+        }
+
+        Ok(Self { modifiers, vk })
+    }
+}
+
+impl fmt::Display for Chord {
+    /// 规范形式，例如 `Win+Ctrl+Shift+3`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let m = self.modifiers;
+        for (pressed, name) in [(m.win, "Win"), (m.ctrl, "Ctrl"), (m.shift, "Shift"), (m.alt, "Alt")] {
+            if pressed {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match key_name(self.vk) {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "0x{:02X}", self.vk),
+        }
+    }
+}
+
+/// 快捷键绑定表
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: Vec<(Chord, HotkeyEvent)>,
+}
+
+impl Keymap {
+    pub const fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// 根据配置构建绑定表，解析失败或冲突时返回对应配置项的错误
+    pub fn from_config(hotkeys: &HotkeyConfig) -> Result<Self, ConfigError> {
+        let mut entries = vec![
+            ("hotkeys.switch_left", &hotkeys.switch_left, HotkeyEvent::SwitchLeft),
+            ("hotkeys.switch_right", &hotkeys.switch_right, HotkeyEvent::SwitchRight),
+            (
+                "hotkeys.toggle_fullscreen",
+                &hotkeys.toggle_fullscreen,
+                HotkeyEvent::ToggleFullscreen,
+            ),
+            (
+                "hotkeys.move_window_left",
+                &hotkeys.move_window_left,
+                HotkeyEvent::MoveWindowLeft,
+            ),
+            (
+                "hotkeys.move_window_right",
+                &hotkeys.move_window_right,
+                HotkeyEvent::MoveWindowRight,
+            ),
+            ("hotkeys.send_to_space", &hotkeys.send_to_space, HotkeyEvent::SendToSpace),
+            ("hotkeys.split_view", &hotkeys.split_view, HotkeyEvent::SplitView),
+            ("hotkeys.split_left", &hotkeys.split_left, HotkeyEvent::SplitLeft),
+            ("hotkeys.split_right", &hotkeys.split_right, HotkeyEvent::SplitRight),
+        ];
+        for (i, text) in hotkeys.go_to.iter().enumerate() {
+            entries.push(("hotkeys.go_to", text, HotkeyEvent::GoTo(i + 1)));
+        }
+
+        let mut keymap = Self::new();
+        let mut fields: Vec<&'static str> = Vec::new();
+
+        for (field, text, event) in entries {
+            let chord: Chord = text.parse().map_err(|e: ChordError| ConfigError::Invalid {
+                field,
+                message: match event {
+                    HotkeyEvent::GoTo(n) => format!("第 {} 项 {}", n, e),
+                    _ => e.to_string(),
+                },
+            })?;
+
+            if let Some(index) = keymap.bindings.iter().position(|(c, _)| *c == chord) {
+                return Err(ConfigError::Invalid {
+                    field,
+                    message: format!("与 {} 使用了相同的快捷键 {}", fields[index], chord),
+                });
+            }
+
+            keymap.bindings.push((chord, event));
+            fields.push(field);
+        }
+
+        Ok(keymap)
+    }
+
+    /// 查找按下的组合键对应的事件（修饰键必须完全一致）
+    pub fn lookup(&self, modifiers: Modifiers, vk: u16) -> Option<HotkeyEvent> {
+        let pressed = Chord::new(modifiers, vk);
+        self.bindings
+            .iter()
+            .find(|(chord, _)| *chord == pressed)
+            .map(|(_, event)| *event)
+    }
+
+    /// 所有绑定
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn bindings(&self) -> &[(Chord, HotkeyEvent)] {
+        &self.bindings
+    }
+}
+
+/// 如果 token 是修饰键，返回对应的标志位
+fn modifier_flag<'a>(modifiers: &'a mut Modifiers, token: &str) -> Option<&'a mut bool> {
+    match token.to_ascii_lowercase().as_str() {
+        "win" | "windows" | "super" | "meta" | "cmd" => Some(&mut modifiers.win),
+        "ctrl" | "control" | "ctl" => Some(&mut modifiers.ctrl),
+        "shift" => Some(&mut modifiers.shift),
+        "alt" | "option" | "menu" => Some(&mut modifiers.alt),
+        _ => None,
+    }
+}
+
+/// 具名按键（规范名称在前，别名在后）
+const NAMED_KEYS: &[(&str, u16)] = &[
+    ("Left", 0x25),
+    ("Up", 0x26),
+    ("Right", 0x27),
+    ("Down", 0x28),
+    ("Space", 0x20),
+    ("Enter", 0x0D),
+    ("Tab", 0x09),
+    ("Esc", 0x1B),
+    ("Backspace", 0x08),
+    ("Insert", 0x2D),
+    ("Delete", 0x2E),
+    ("Home", 0x24),
+    ("End", 0x23),
+    ("PageUp", 0x21),
+    ("PageDown", 0x22),
+    ("Minus", 0xBD),
+    ("Equals", 0xBB),
+    ("Comma", 0xBC),
+    ("Period", 0xBE),
+    ("Semicolon", 0xBA),
+    ("Slash", 0xBF),
+    ("Backquote", 0xC0),
+    ("LeftBracket", 0xDB),
+    ("Backslash", 0xDC),
+    ("RightBracket", 0xDD),
+    ("Quote", 0xDE),
+    // 别名
+    ("ArrowLeft", 0x25),
+    ("ArrowUp", 0x26),
+    ("ArrowRight", 0x27),
+    ("ArrowDown", 0x28),
+    ("Return", 0x0D),
+    ("Escape", 0x1B),
+    ("Ins", 0x2D),
+    ("Del", 0x2E),
+    ("PgUp", 0x21),
+    ("PgDn", 0x22),
+    ("-", 0xBD),
+    ("=", 0xBB),
+    (",", 0xBC),
+    (".", 0xBE),
+    (";", 0xBA),
+    ("/", 0xBF),
+    ("`", 0xC0),
+    ("[", 0xDB),
+    ("\\", 0xDC),
+    ("]", 0xDD),
+    ("'", 0xDE),
+];
+
+/// 按键名称转虚拟键码（不区分大小写）
+fn key_code(name: &str) -> Option<u16> {
+    if let Some((_, vk)) = NAMED_KEYS.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)) {
+        return Some(*vk);
+    }
+
+    let upper = name.to_ascii_uppercase();
+    let bytes = upper.as_bytes();
+    if bytes.len() == 1 && (bytes[0].is_ascii_uppercase() || bytes[0].is_ascii_digit()) {
+        // 'A'-'Z' 和 '0'-'9' 的虚拟键码与 ASCII 相同
+        return Some(bytes[0] as u16);
+    }
+
+    if let Some(n) = upper.strip_prefix("NUMPAD").and_then(|n| n.parse::<u16>().ok()) {
+        if n <= 9 {
+            return Some(0x60 + n);
+        }
+    }
+
+    if let Some(n) = upper.strip_prefix('F').and_then(|n| n.parse::<u16>().ok()) {
+        if (1..=24).contains(&n) {
+            return Some(0x70 + n - 1);
+        }
+    }
+
+    None
+}
+
+/// 虚拟键码转规范名称
+fn key_name(vk: u16) -> Option<String> {
+    if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, code)| *code == vk) {
+        return Some(name.to_string());
+    }
+    match vk {
+        0x30..=0x39 | 0x41..=0x5A => Some((vk as u8 as char).to_string()),
+        0x60..=0x69 => Some(format!("Numpad{}", vk - 0x60)),
+        0x70..=0x87 => Some(format!("F{}", vk - 0x70 + 1)),
+        _ => None,
+    }
+}
+
+fn is_function_key(vk: u16) -> bool {
+    (0x70..=0x87).contains(&vk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIN: Modifiers = Modifiers { win: true, ctrl: false, shift: false, alt: false };
+
+    fn chord(text: &str) -> Chord {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_modifiers_in_any_order() {
+        let a = chord("Win+Ctrl+Shift+3");
+        assert_eq!(
+            a.modifiers,
+            Modifiers { win: true, ctrl: true, shift: true, alt: false }
+        );
+        assert_eq!(a.vk, 0x33);
+
+        let b = chord("Alt+Win+Left");
+        assert_eq!(b, chord("win + alt + left"));
+        assert_eq!(b.modifiers, Modifiers { win: true, alt: true, ..Default::default() });
+        assert_eq!(b.vk, 0x25);
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        assert_eq!(chord("Super+Control+Escape"), chord("Win+Ctrl+Esc"));
+        assert_eq!(chord("Cmd+Option+PgUp"), chord("Win+Alt+PageUp"));
+        assert_eq!(chord("Win+ArrowRight"), chord("Win+Right"));
+        assert_eq!(chord("Win+-").vk, 0xBD);
+        assert_eq!(chord("Win+Numpad7").vk, 0x67);
+        assert_eq!(chord("F13").vk, 0x7C);
+    }
+
+    #[test]
+    fn test_parse_invalid_input() {
+        assert_eq!("".parse::<Chord>(), Err(ChordError::Empty));
+        assert!(matches!("Win+Ctrl".parse::<Chord>(), Err(ChordError::MissingKey(_))));
+        assert!(matches!("Win+".parse::<Chord>(), Err(ChordError::MissingKey(_))));
+        assert!(matches!("Win+A+B".parse::<Chord>(), Err(ChordError::MultipleKeys(_))));
+        assert!(matches!(
+            "Win+Super+A".parse::<Chord>(),
+            Err(ChordError::DuplicateModifier(_, _))
+        ));
+        assert!(matches!("Win+Foo".parse::<Chord>(), Err(ChordError::UnknownKey(_, _))));
+        assert!(matches!("Win+F25".parse::<Chord>(), Err(ChordError::UnknownKey(_, _))));
+        assert!(matches!("A".parse::<Chord>(), Err(ChordError::NoModifier(_))));
+    }
+
+    #[test]
+    fn test_display_is_canonical() {
+        assert_eq!(chord("shift+alt+ctrl+win+f11").to_string(), "Win+Ctrl+Shift+Alt+F11");
+        assert_eq!(chord("Win+Return").to_string(), "Win+Enter");
+        assert_eq!(chord("Win+7").to_string(), "Win+7");
+    }
+
+    #[test]
+    fn test_keymap_from_default_config() {
+        let keymap = Keymap::from_config(&HotkeyConfig::default()).unwrap();
+
+        assert_eq!(keymap.lookup(WIN, 0x25), Some(HotkeyEvent::SwitchLeft));
+        assert_eq!(keymap.lookup(WIN, 0x27), Some(HotkeyEvent::SwitchRight));
+        assert_eq!(keymap.lookup(WIN, 0x46), Some(HotkeyEvent::ToggleFullscreen));
+
+        let win_shift = Modifiers { win: true, shift: true, ..Default::default() };
+        assert_eq!(keymap.lookup(win_shift, 0x25), Some(HotkeyEvent::MoveWindowLeft));
+        assert_eq!(keymap.lookup(win_shift, 0x27), Some(HotkeyEvent::MoveWindowRight));
+        assert_eq!(keymap.lookup(win_shift, 0x46), Some(HotkeyEvent::SendToSpace));
+
+        let win_alt = Modifiers { win: true, alt: true, ..Default::default() };
+        assert_eq!(keymap.lookup(win_alt, 0x46), Some(HotkeyEvent::SplitView));
+        let win_alt_shift = Modifiers { shift: true, ..win_alt };
+        assert_eq!(keymap.lookup(win_alt_shift, 0x25), Some(HotkeyEvent::SplitLeft));
+        assert_eq!(keymap.lookup(win_alt_shift, 0x27), Some(HotkeyEvent::SplitRight));
+
+        assert_eq!(keymap.lookup(WIN, 0x31), Some(HotkeyEvent::GoTo(1)));
+        assert_eq!(keymap.lookup(WIN, 0x39), Some(HotkeyEvent::GoTo(9)));
+        assert_eq!(keymap.lookup(WIN, 0x30), None);
+    }
+
+    #[test]
+    fn test_keymap_custom_go_to_list() {
+        let hotkeys = HotkeyConfig {
+            go_to: vec!["Win+Ctrl+1".to_string(), "Win+Ctrl+2".to_string()],
+            ..Default::default()
+        };
+        let keymap = Keymap::from_config(&hotkeys).unwrap();
+        let win_ctrl = Modifiers { win: true, ctrl: true, ..Default::default() };
+
+        assert_eq!(keymap.lookup(win_ctrl, 0x32), Some(HotkeyEvent::GoTo(2)));
+        assert_eq!(keymap.lookup(WIN, 0x31), None);
+
+        let hotkeys = HotkeyConfig {
+            go_to: vec!["Win+1".to_string(), "Win+Nope".to_string()],
+            ..Default::default()
+        };
+        match Keymap::from_config(&hotkeys) {
+            Err(ConfigError::Invalid { field, message }) => {
+                assert_eq!(field, "hotkeys.go_to");
+                assert!(message.starts_with("第 2 项"));
+            }
+            other => panic!("应当报告第 2 项无效: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_keymap_requires_exact_modifiers() {
+        let keymap = Keymap::from_config(&HotkeyConfig::default()).unwrap();
+        let win_ctrl = Modifiers { win: true, ctrl: true, ..Default::default() };
+
+        assert_eq!(keymap.lookup(win_ctrl, 0x25), None);
+        assert_eq!(keymap.lookup(Modifiers::default(), 0x25), None);
+    }
+
+    #[test]
+    fn test_keymap_reports_conflicts() {
+        let hotkeys = HotkeyConfig {
+            toggle_fullscreen: "Windows+LEFT".to_string(),
+            ..Default::default()
+        };
+
+        match Keymap::from_config(&hotkeys) {
+            Err(ConfigError::Invalid { field, message }) => {
+                assert_eq!(field, "hotkeys.toggle_fullscreen");
+                assert!(message.contains("hotkeys.switch_left"));
+            }
+            other => panic!("应当报告冲突: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_wparam_round_trip() {
+        for event in [
+            HotkeyEvent::SwitchLeft,
+            HotkeyEvent::SwitchRight,
+            HotkeyEvent::ToggleFullscreen,
+            HotkeyEvent::MoveWindowLeft,
+            HotkeyEvent::MoveWindowRight,
+            HotkeyEvent::SendToSpace,
+            HotkeyEvent::SplitView,
+            HotkeyEvent::SplitLeft,
+            HotkeyEvent::SplitRight,
+            HotkeyEvent::GoTo(1),
+            HotkeyEvent::GoTo(9),
+        ] {
+            assert_eq!(HotkeyEvent::from_wparam(event.to_wparam()), Some(event));
+        }
+        assert_eq!(HotkeyEvent::from_wparam(0), None);
+    }
+}
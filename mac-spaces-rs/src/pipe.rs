@@ -0,0 +1,140 @@
+//! 命名管道传输（命令行控制，见 ipc.rs）
+//!
+//! 服务端为每个客户端创建一个管道实例，处理完后断开；只接受本机连接。
+
+use crate::ipc::{Connection, Listener, Transport};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::windows::io::{AsRawHandle, FromRawHandle};
+use tracing::debug;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, HANDLE, INVALID_HANDLE_VALUE};
+use windows::Win32::Storage::FileSystem::{
+    FlushFileBuffers, FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+};
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, WaitNamedPipeW,
+    PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES,
+    PIPE_WAIT,
+};
+
+/// 管道名称
+pub const PIPE_NAME: &str = r"\\.\pipe\MacSpaces";
+
+/// 管道缓冲区大小
+const BUFFER_SIZE: u32 = 4096;
+
+/// 所有实例都忙时客户端最多等待的时间（毫秒）
+const BUSY_TIMEOUT_MS: u32 = 2000;
+
+/// 命名管道 `\\.\pipe\MacSpaces`
+pub struct NamedPipeTransport;
+
+impl Transport for NamedPipeTransport {
+    fn listen(&self) -> io::Result<Box<dyn Listener>> {
+        // 第一个实例带上 FILE_FLAG_FIRST_PIPE_INSTANCE，已有进程在监听时失败
+        let first = create_instance(true)?;
+        Ok(Box::new(PipeListener { pending: Some(first) }))
+    }
+
+    fn connect(&self) -> io::Result<Box<dyn Connection>> {
+        match open_pipe() {
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY.0 as i32) => {
+                debug!("命名管道忙，等待空闲实例");
+                let name = wide_string(PIPE_NAME);
+                unsafe {
+                    let _ = WaitNamedPipeW(PCWSTR(name.as_ptr()), BUSY_TIMEOUT_MS);
+                }
+                Ok(Box::new(open_pipe()?))
+            }
+            result => Ok(Box::new(result?)),
+        }
+    }
+}
+
+/// 等待客户端连接的服务端
+struct PipeListener {
+    /// 已创建、还没有客户端连接的实例
+    pending: Option<File>,
+}
+
+impl Listener for PipeListener {
+    fn accept(&mut self) -> io::Result<Box<dyn Connection>> {
+        let pipe = match self.pending.take() {
+            Some(pipe) => pipe,
+            None => create_instance(false)?,
+        };
+        let handle = HANDLE(pipe.as_raw_handle());
+        match unsafe { ConnectNamedPipe(handle, None) } {
+            Ok(()) => {}
+            // 客户端在 CreateNamedPipe 和 ConnectNamedPipe 之间已经连上
+            Err(e) if e.code() == ERROR_PIPE_CONNECTED.to_hresult() => {}
+            Err(e) => return Err(io::Error::from_raw_os_error(e.code().0 & 0xFFFF)),
+        }
+        Ok(Box::new(ServerPipe { pipe }))
+    }
+}
+
+/// 服务端的一个连接，关闭前等客户端读完回复再断开
+struct ServerPipe {
+    pipe: File,
+}
+
+impl Read for ServerPipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.pipe.read(buf)
+    }
+}
+
+impl Write for ServerPipe {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pipe.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        unsafe { FlushFileBuffers(HANDLE(self.pipe.as_raw_handle())) }
+            .map_err(|e| io::Error::from_raw_os_error(e.code().0 & 0xFFFF))
+    }
+}
+
+impl Drop for ServerPipe {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DisconnectNamedPipe(HANDLE(self.pipe.as_raw_handle()));
+        }
+    }
+}
+
+/// 创建一个管道实例
+fn create_instance(first: bool) -> io::Result<File> {
+    let name = wide_string(PIPE_NAME);
+    let mut open_mode = PIPE_ACCESS_DUPLEX;
+    if first {
+        open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+    }
+    let handle = unsafe {
+        CreateNamedPipeW(
+            PCWSTR(name.as_ptr()),
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            None,
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_handle(handle.0) })
+}
+
+/// 客户端打开管道
+fn open_pipe() -> io::Result<File> {
+    OpenOptions::new().read(true).write(true).open(PIPE_NAME)
+}
+
+fn wide_string(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
@@ -1,26 +1,244 @@
 //! 空间注册表
 //! 
-//! 管理全屏空间的状态，记录窗口与桌面的映射关系
+//! 管理全屏空间的状态，记录窗口与桌面的映射关系。
+//! 空间以所创建桌面的 GUID 为键，用户在系统中增删或重排桌面不会使记录失效；
+//! 需要调用 DLL 时再通过后端把 GUID 解析为当前索引。
+//! 空间属于进入全屏的主窗口，之后还可以把其他窗口加入空间（按加入顺序记录）；
+//! 主窗口关闭时由最早加入的窗口接替，最后一个窗口关闭时空间才被删除。
+//! 分屏空间由两个窗口左右并排组成（主窗口在左），其中任一窗口离开时整个空间结束。
+//! 窗口最小化或隐藏时空间可以收起：删除桌面但记住空间，窗口恢复显示时重新创建。
+//! 窗口句柄会被系统复用，每个空间还记录窗口身份（进程 ID、进程创建时间和类名），
+//! 按句柄查找时核对身份，句柄已属于其他窗口时视为找不到；`sweep` 清理这样失效的空间。
+//! 关联了状态文件时，每次修改后都会立即保存（见 state.rs）。
 
+use crate::backend::{
+    DesktopId, WindowBackend, WindowDetails, WindowFrame, WindowIdentity, WindowPlacement, HWND,
+};
+use crate::state::{SavedMember, SavedSpace, SavedState, StateStore, STATE_VERSION};
 use std::collections::HashMap;
-use windows::Win32::Foundation::HWND;
-use tracing::debug;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+/// 加入空间的其他窗口
+#[derive(Debug, Clone)]
+pub struct SpaceMember {
+    /// 窗口句柄
+    pub hwnd: HWND,
+    /// 窗口描述信息
+    pub window: WindowDetails,
+    /// 窗口身份
+    pub identity: WindowIdentity,
+    /// 加入前窗口的位置、大小和最大化状态（分屏时记录，结束分屏时恢复）
+    pub placement: Option<WindowPlacement>,
+}
+
+impl SpaceMember {
+    /// 记录窗口当前的描述信息和身份
+    pub fn new(windows: &dyn WindowBackend, hwnd: HWND) -> Self {
+        Self {
+            hwnd,
+            window: windows.details(hwnd),
+            identity: windows.identity(hwnd).unwrap_or_default(),
+            placement: None,
+        }
+    }
+
+    /// 句柄是否仍指向登记的窗口
+    pub fn is_same_window(&self, windows: &dyn WindowBackend) -> bool {
+        is_same_window(windows, self.hwnd, &self.identity)
+    }
+
+    fn to_saved(&self) -> SavedMember {
+        SavedMember {
+            hwnd: self.hwnd.0 as i64,
+            window: self.window.clone(),
+            identity: self.identity.clone(),
+            placement: self.placement,
+        }
+    }
+
+    fn from_saved(saved: &SavedMember) -> Self {
+        Self {
+            hwnd: HWND(saved.hwnd as isize as *mut std::ffi::c_void),
+            window: saved.window.clone(),
+            identity: saved.identity.clone(),
+            placement: saved.placement,
+        }
+    }
+}
 
 /// 空间信息
 #[derive(Debug, Clone)]
 pub struct SpaceInfo {
-    /// 窗口句柄
+    /// 主窗口句柄（进入全屏的窗口）
     pub hwnd: HWND,
-    /// 原始桌面索引（用于退出时返回）
-    pub original_desktop: i32,
-    /// 创建的桌面索引（用于删除）
-    pub created_desktop: i32,
+    /// 原始桌面（用于退出时返回）
+    pub original_desktop: DesktopId,
+    /// 创建的桌面（用于删除）
+    pub created_desktop: DesktopId,
+    /// 窗口描述信息（进程路径、类名、标题）
+    pub window: WindowDetails,
+    /// 窗口身份（与句柄一起确定窗口，未知时不核对）
+    pub identity: WindowIdentity,
+    /// 创建的桌面的名称（未命名时为空）
+    pub name: String,
+    /// 主窗口不是由本程序全屏的（应用自己进入的全屏，如视频播放器、游戏；
+    /// 或主窗口关闭后接替的窗口），退出时不还原窗口
+    pub app_initiated: bool,
+    /// 无边框全屏前的窗口样式（退出时恢复，其他方式进入时为 None）
+    pub frame: Option<WindowFrame>,
+    /// 进入前窗口的位置、大小和最大化状态（退出时恢复，旧版本的状态文件中没有）
+    pub placement: Option<WindowPlacement>,
+    /// 之后加入空间的其他窗口（按加入顺序）
+    pub members: Vec<SpaceMember>,
+    /// 分屏时左侧（主窗口）占的比例，右侧是第一个加入的窗口；不是分屏空间时为 None
+    pub split: Option<f64>,
+    /// 创建时间（Unix 秒）
+    pub created_at: u64,
+    /// 最后更新时间（Unix 秒）
+    pub updated_at: u64,
+}
+
+impl SpaceInfo {
+    /// 新建的空间（未命名，创建时间为当前时间）
+    pub fn new(
+        hwnd: HWND,
+        window: WindowDetails,
+        original_desktop: DesktopId,
+        created_desktop: DesktopId,
+    ) -> Self {
+        let timestamp = now();
+        Self {
+            hwnd,
+            original_desktop,
+            created_desktop,
+            window,
+            identity: WindowIdentity::default(),
+            name: String::new(),
+            app_initiated: false,
+            frame: None,
+            placement: None,
+            members: Vec::new(),
+            split: None,
+            created_at: timestamp,
+            updated_at: timestamp,
+        }
+    }
+
+    /// 转换为可保存的形式
+    pub fn to_saved(&self) -> SavedSpace {
+        SavedSpace {
+            hwnd: self.hwnd.0 as i64,
+            original_desktop: self.original_desktop,
+            created_desktop: self.created_desktop,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            name: self.name.clone(),
+            app_initiated: self.app_initiated,
+            split: self.split,
+            window: self.window.clone(),
+            identity: self.identity.clone(),
+            frame: self.frame,
+            placement: self.placement,
+            members: self.members.iter().map(SpaceMember::to_saved).collect(),
+        }
+    }
+
+    /// 从保存的形式恢复
+    pub fn from_saved(saved: &SavedSpace) -> Self {
+        Self {
+            hwnd: HWND(saved.hwnd as isize as *mut std::ffi::c_void),
+            original_desktop: saved.original_desktop,
+            created_desktop: saved.created_desktop,
+            window: saved.window.clone(),
+            identity: saved.identity.clone(),
+            name: saved.name.clone(),
+            app_initiated: saved.app_initiated,
+            frame: saved.frame,
+            placement: saved.placement,
+            members: saved.members.iter().map(SpaceMember::from_saved).collect(),
+            split: saved.split,
+            created_at: saved.created_at,
+            updated_at: saved.updated_at,
+        }
+    }
+}
+
+impl SpaceInfo {
+    /// 主窗口的句柄是否仍指向登记的窗口
+    pub fn is_same_window(&self, windows: &dyn WindowBackend) -> bool {
+        is_same_window(windows, self.hwnd, &self.identity)
+    }
+
+    /// 空间中的所有窗口：主窗口在前，其他窗口按加入顺序
+    pub fn hwnds(&self) -> Vec<HWND> {
+        std::iter::once(self.hwnd)
+            .chain(self.members.iter().map(|m| m.hwnd))
+            .collect()
+    }
+
+    /// 窗口是否在这个空间中（核对窗口身份）
+    pub fn has_window(&self, windows: &dyn WindowBackend, hwnd: HWND) -> bool {
+        (self.hwnd == hwnd && self.is_same_window(windows))
+            || self
+                .members
+                .iter()
+                .any(|m| m.hwnd == hwnd && m.is_same_window(windows))
+    }
+
+    /// 空间中是否有窗口已不存在或句柄已属于其他窗口
+    fn has_stale_window(&self, windows: &dyn WindowBackend) -> bool {
+        is_stale(windows, self.hwnd, &self.identity)
+            || self.members.iter().any(|m| is_stale(windows, m.hwnd, &m.identity))
+    }
+
+    /// 主窗口离开空间，由最早加入的窗口接替（没有其他窗口时返回 false）
+    ///
+    /// 接替的窗口没有被全屏过，退出时和应用自己全屏的窗口一样不还原。
+    fn promote_member(&mut self) -> bool {
+        if self.members.is_empty() {
+            return false;
+        }
+        let member = self.members.remove(0);
+        self.hwnd = member.hwnd;
+        self.window = member.window;
+        self.identity = member.identity;
+        self.app_initiated = true;
+        self.frame = None;
+        self.placement = None;
+        true
+    }
+}
+
+/// 句柄是否仍指向登记的窗口（窗口已销毁时句柄还没有被复用，仍算同一个窗口）
+fn is_same_window(windows: &dyn WindowBackend, hwnd: HWND, identity: &WindowIdentity) -> bool {
+    match windows.identity(hwnd) {
+        Some(current) => identity.matches(&current),
+        None => true,
+    }
+}
+
+/// 窗口已不存在，或句柄已属于其他窗口
+fn is_stale(windows: &dyn WindowBackend, hwnd: HWND, identity: &WindowIdentity) -> bool {
+    !windows.is_valid(hwnd) || !is_same_window(windows, hwnd, identity)
+}
+
+/// 当前 Unix 时间（秒）
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
 }
 
 /// 空间注册表
 pub struct SpaceRegistry {
-    /// hwnd.0 -> SpaceInfo
-    spaces: HashMap<isize, SpaceInfo>,
+    /// 创建的桌面 -> SpaceInfo
+    spaces: HashMap<DesktopId, SpaceInfo>,
+    /// 已收起的空间（桌面已删除，`created_desktop` 不再有效）
+    collapsed: Vec<SpaceInfo>,
+    /// 状态文件（None 时只保存在内存中）
+    store: Option<StateStore>,
 }
 
 impl SpaceRegistry {
@@ -28,89 +246,297 @@ impl SpaceRegistry {
     pub fn new() -> Self {
         Self {
             spaces: HashMap::new(),
+            collapsed: Vec::new(),
+            store: None,
+        }
+    }
+    
+    /// 创建关联状态文件的注册表，之后每次修改都会写入该文件
+    pub fn with_store(store: StateStore) -> Self {
+        Self {
+            spaces: HashMap::new(),
+            collapsed: Vec::new(),
+            store: Some(store),
         }
     }
     
     /// 注册一个全屏空间
-    pub fn register(&mut self, hwnd: HWND, original_desktop: i32, created_desktop: i32) {
-        let info = SpaceInfo {
-            hwnd,
-            original_desktop,
-            created_desktop,
-        };
-        
+    pub fn register(&mut self, info: SpaceInfo) {
         debug!(
             "注册空间: hwnd={:?}, original={}, created={}",
-            hwnd, original_desktop, created_desktop
+            info.hwnd, info.original_desktop, info.created_desktop
         );
         
-        self.spaces.insert(hwnd.0 as isize, info);
+        self.spaces.insert(info.created_desktop, info);
+        self.persist();
     }
     
-    /// 检查窗口是否是全屏空间
-    pub fn is_fullscreen_space(&self, hwnd: HWND) -> bool {
-        self.spaces.contains_key(&(hwnd.0 as isize))
+    /// 重新接管上次运行时创建的空间（保留原来的创建时间）
+    pub fn adopt(&mut self, mut info: SpaceInfo) {
+        debug!(
+            "接管空间: hwnd={:?}, original={}, created={}",
+            info.hwnd, info.original_desktop, info.created_desktop
+        );
+        
+        info.updated_at = now();
+        self.spaces.insert(info.created_desktop, info);
+        self.persist();
+    }
+    
+    /// 查找窗口所在空间的键（主窗口或加入的窗口，核对窗口身份）
+    fn key_of(&self, windows: &dyn WindowBackend, hwnd: HWND) -> Option<DesktopId> {
+        self.spaces
+            .values()
+            .find(|s| s.has_window(windows, hwnd))
+            .map(|s| s.created_desktop)
+    }
+    
+    /// 检查窗口是否是某个全屏空间的主窗口
+    pub fn is_fullscreen_space(&self, windows: &dyn WindowBackend, hwnd: HWND) -> bool {
+        self.get(windows, hwnd).is_some_and(|s| s.hwnd == hwnd)
     }
     
-    /// 获取空间信息
-    pub fn get(&self, hwnd: HWND) -> Option<&SpaceInfo> {
-        self.spaces.get(&(hwnd.0 as isize))
+    /// 获取窗口所在空间的信息
+    pub fn get(&self, windows: &dyn WindowBackend, hwnd: HWND) -> Option<&SpaceInfo> {
+        self.key_of(windows, hwnd).and_then(|key| self.spaces.get(&key))
     }
     
-    /// 移除空间
-    pub fn remove(&mut self, hwnd: HWND) -> Option<SpaceInfo> {
-        let info = self.spaces.remove(&(hwnd.0 as isize));
-        if info.is_some() {
-            debug!("移除空间: hwnd={:?}", hwnd);
+    /// 获取某个桌面对应的空间
+    pub fn get_by_desktop(&self, desktop: DesktopId) -> Option<&SpaceInfo> {
+        self.spaces.get(&desktop)
+    }
+    
+    /// 记录桌面的新名称（用户在系统中重命名了全屏空间的桌面），不是全屏空间时返回 false
+    pub fn rename(&mut self, desktop: DesktopId, name: &str) -> bool {
+        let Some(space) = self.spaces.get_mut(&desktop) else {
+            return false;
+        };
+        if space.name != name {
+            space.name = name.to_string();
+            space.updated_at = now();
+            self.persist();
         }
-        info
+        true
     }
     
-    /// 更新桌面索引（当删除桌面后，后面的索引需要减 1）
-    pub fn update_indices_after_delete(&mut self, deleted_index: i32) {
-        for info in self.spaces.values_mut() {
-            if info.original_desktop > deleted_index {
-                info.original_desktop -= 1;
-            }
-            if info.created_desktop > deleted_index {
-                info.created_desktop -= 1;
+    /// 记录分屏的新比例，不是分屏空间时返回 false
+    pub fn set_split_ratio(&mut self, desktop: DesktopId, ratio: f64) -> bool {
+        let Some(space) = self.spaces.get_mut(&desktop).filter(|s| s.split.is_some()) else {
+            return false;
+        };
+        space.split = Some(ratio);
+        space.updated_at = now();
+        self.persist();
+        true
+    }
+    
+    /// 把窗口加入空间（`desktop` 为空间的桌面），空间不存在时返回 false
+    pub fn add_member(&mut self, desktop: DesktopId, member: SpaceMember) -> bool {
+        let Some(space) = self.spaces.get_mut(&desktop) else {
+            return false;
+        };
+        debug!("窗口加入空间: hwnd={:?}, 桌面 {}", member.hwnd, desktop);
+        space.members.push(member);
+        space.updated_at = now();
+        self.persist();
+        true
+    }
+    
+    /// 窗口离开所在的空间，空间里还有其他窗口时保留空间（主窗口离开时由最早加入的窗口接替）
+    ///
+    /// 窗口是空间中唯一的窗口、在分屏空间中或不在任何空间中时返回 false，此时调用方应删除整个空间。
+    pub fn leave(&mut self, windows: &dyn WindowBackend, hwnd: HWND) -> bool {
+        let Some(space) = self
+            .key_of(windows, hwnd)
+            .and_then(|key| self.spaces.get_mut(&key))
+            .filter(|s| s.split.is_none())
+        else {
+            return false;
+        };
+        if space.hwnd == hwnd {
+            if !space.promote_member() {
+                return false;
             }
+            debug!("主窗口离开空间，由 hwnd={:?} 接替", space.hwnd);
+        } else {
+            space.members.retain(|m| m.hwnd != hwnd);
+            debug!("窗口离开空间: hwnd={:?}", hwnd);
         }
-        debug!("更新桌面索引: 删除了索引 {}", deleted_index);
+        space.updated_at = now();
+        self.persist();
+        true
     }
     
-    /// 获取所有注册的窗口句柄
-    pub fn all_hwnds(&self) -> Vec<HWND> {
-        self.spaces.values().map(|s| s.hwnd).collect()
+    /// 移除窗口所在的整个空间
+    pub fn remove(&mut self, windows: &dyn WindowBackend, hwnd: HWND) -> Option<SpaceInfo> {
+        self.key_of(windows, hwnd).and_then(|key| self.remove_by_desktop(key))
     }
     
-    /// 检查窗口是否在注册表中
-    pub fn contains(&self, hwnd: HWND) -> bool {
-        self.spaces.contains_key(&(hwnd.0 as isize))
+    /// 移除某个桌面对应的空间
+    pub fn remove_by_desktop(&mut self, desktop: DesktopId) -> Option<SpaceInfo> {
+        let info = self.spaces.remove(&desktop);
+        if let Some(info) = &info {
+            debug!("移除空间: hwnd={:?}", info.hwnd);
+            self.persist();
+        }
+        info
+    }
+    
+    /// 收起窗口的空间（调用方负责删除桌面），不是全屏空间时返回 false
+    pub fn collapse(&mut self, windows: &dyn WindowBackend, hwnd: HWND) -> bool {
+        let key = self.key_of(windows, hwnd);
+        let Some(mut info) = key.and_then(|key| self.spaces.remove(&key)) else {
+            return false;
+        };
+        debug!("收起空间: hwnd={:?}", hwnd);
+        info.updated_at = now();
+        self.collapsed.push(info);
+        self.persist();
+        true
+    }
+    
+    /// 重新记住上次运行时收起的空间
+    pub fn adopt_collapsed(&mut self, mut info: SpaceInfo) {
+        debug!("接管收起的空间: hwnd={:?}", info.hwnd);
+        info.updated_at = now();
+        self.collapsed.push(info);
+        self.persist();
+    }
+    
+    /// 查找窗口收起的空间（核对窗口身份）
+    fn collapsed_position(&self, windows: &dyn WindowBackend, hwnd: HWND) -> Option<usize> {
+        self.collapsed
+            .iter()
+            .position(|s| s.hwnd == hwnd && s.is_same_window(windows))
+    }
+    
+    /// 窗口的空间是否已收起
+    pub fn is_collapsed(&self, windows: &dyn WindowBackend, hwnd: HWND) -> bool {
+        self.collapsed_position(windows, hwnd).is_some()
+    }
+    
+    /// 取出收起的空间（用于重新展开，或窗口关闭后丢弃）
+    pub fn take_collapsed(&mut self, windows: &dyn WindowBackend, hwnd: HWND) -> Option<SpaceInfo> {
+        let position = self.collapsed_position(windows, hwnd)?;
+        let info = self.collapsed.remove(position);
+        self.persist();
+        Some(info)
     }
     
-    /// 获取注册的空间数量
-    pub fn len(&self) -> usize {
-        self.spaces.len()
+    /// 清理窗口已不存在或句柄已被其他窗口复用的空间（错过了窗口销毁事件）
+    ///
+    /// 失效的窗口离开空间，空间中没有窗口时移除；分屏空间只要有一个窗口失效就整个移除。返回被移除的空间（调用方负责删除它们的桌面），
+    /// 收起的空间没有桌面，直接丢弃
+    pub fn sweep(&mut self, windows: &dyn WindowBackend) -> Vec<SpaceInfo> {
+        let mut changed = false;
+        let mut stale = Vec::new();
+        for space in self.spaces.values_mut() {
+            if space.split.is_some() {
+                if space.has_stale_window(windows) {
+                    changed = true;
+                    stale.push(space.created_desktop);
+                }
+                continue;
+            }
+            
+            let members = space.members.len();
+            space.members.retain(|m| !is_stale(windows, m.hwnd, &m.identity));
+            changed |= space.members.len() != members;
+            
+            if is_stale(windows, space.hwnd, &space.identity) {
+                changed = true;
+                if !space.promote_member() {
+                    stale.push(space.created_desktop);
+                }
+            }
+        }
+        let removed: Vec<SpaceInfo> = stale.iter().filter_map(|key| self.spaces.remove(key)).collect();
+        let collapsed = self.collapsed.len();
+        self.collapsed.retain(|s| !is_stale(windows, s.hwnd, &s.identity));
+        
+        if changed || self.collapsed.len() != collapsed {
+            debug!(
+                "清理失效空间: {} 个空间, {} 个收起的空间",
+                removed.len(),
+                collapsed - self.collapsed.len()
+            );
+            self.persist();
+        }
+        removed
+    }
+    
+    /// 检查窗口是否在注册表中
+    pub fn contains(&self, windows: &dyn WindowBackend, hwnd: HWND) -> bool {
+        self.key_of(windows, hwnd).is_some()
     }
     
     /// 检查是否为空
+    #[cfg(test)]
     pub fn is_empty(&self) -> bool {
         self.spaces.is_empty()
     }
     
+    /// 导出当前状态（按创建的桌面排序，便于比较和阅读）
+    pub fn snapshot(&self) -> SavedState {
+        let mut spaces: Vec<SavedSpace> = self.spaces.values().map(SpaceInfo::to_saved).collect();
+        spaces.sort_by_key(|s| (s.created_desktop, s.hwnd));
+        let mut collapsed: Vec<SavedSpace> = self.collapsed.iter().map(SpaceInfo::to_saved).collect();
+        collapsed.sort_by_key(|s| s.hwnd);
+        SavedState {
+            version: STATE_VERSION,
+            spaces,
+            collapsed,
+        }
+    }
+    
+    /// 写入状态文件（失败只记录日志，不影响内存中的状态）
+    pub fn persist(&self) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(&self.snapshot()) {
+                warn!("保存状态失败: {}", e);
+            }
+        }
+    }
+    
     /// 调试输出
     pub fn debug_info(&self) -> String {
-        if self.spaces.is_empty() {
+        if self.spaces.is_empty() && self.collapsed.is_empty() {
             return "空间注册表为空".to_string();
         }
         
         let mut info = format!("已注册 {} 个空间:\n", self.spaces.len());
-        for (_, space) in &self.spaces {
+        for space in self.spaces.values() {
             info.push_str(&format!(
-                "  - hwnd={:?}, 原桌面={}, 创建桌面={}\n",
-                space.hwnd, space.original_desktop, space.created_desktop
+                "  - hwnd={:?}, 名称={}, 原桌面={}, 创建桌面={}, 类名={}, 标题={}\n",
+                space.hwnd,
+                space.name,
+                space.original_desktop,
+                space.created_desktop,
+                space.window.class_name,
+                space.window.title
             ));
+            if let Some(ratio) = space.split {
+                info.push_str(&format!("      分屏: 左侧占 {:.0}%\n", ratio * 100.0));
+            }
+            for member in &space.members {
+                info.push_str(&format!(
+                    "      + hwnd={:?}, 类名={}, 标题={}\n",
+                    member.hwnd, member.window.class_name, member.window.title
+                ));
+            }
+        }
+        if !self.collapsed.is_empty() {
+            info.push_str(&format!("已收起 {} 个空间:\n", self.collapsed.len()));
+            for space in &self.collapsed {
+                info.push_str(&format!(
+                    "  - hwnd={:?}, 名称={}, 原桌面={}, 类名={}, 标题={}\n",
+                    space.hwnd,
+                    space.name,
+                    space.original_desktop,
+                    space.window.class_name,
+                    space.window.title
+                ));
+            }
         }
         info
     }
@@ -121,3 +547,177 @@ impl Default for SpaceRegistry {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{hwnd, FakeBackend};
+    use crate::backend::WindowBackend;
+
+    fn temp_store(name: &str) -> StateStore {
+        let dir = std::env::temp_dir().join(format!("mac_spaces_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        StateStore::new(dir.join("state.toml"))
+    }
+
+    #[test]
+    fn test_every_mutation_is_persisted() {
+        let windows = FakeBackend::new(1);
+        let store = temp_store("registry_persist");
+        let mut registry = SpaceRegistry::with_store(store.clone());
+        let window = WindowDetails {
+            process_path: r"C:\Apps\app.exe".to_string(),
+            class_name: "AppWindow".to_string(),
+            title: "App".to_string(),
+        };
+        
+        registry.register(SpaceInfo::new(hwnd(0x100), window.clone(), DesktopId(1), DesktopId(3)));
+        registry.register(SpaceInfo::new(hwnd(0x200), window.clone(), DesktopId(2), DesktopId(4)));
+        let saved = store.load().unwrap().unwrap();
+        assert_eq!(saved.spaces.len(), 2);
+        assert_eq!(saved.spaces[0].created_desktop, DesktopId(3));
+        assert_eq!(saved.spaces[1].window, window);
+        
+        registry.remove(&windows, hwnd(0x100));
+        let saved = store.load().unwrap().unwrap();
+        assert_eq!(saved.spaces.len(), 1);
+        assert_eq!(saved.spaces[0].hwnd, 0x200);
+        
+        let _ = std::fs::remove_dir_all(store.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_lookup_by_window_and_desktop() {
+        let windows = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        registry.register(SpaceInfo {
+            name: "App — 全屏".to_string(),
+            ..SpaceInfo::new(hwnd(0x100), WindowDetails::default(), DesktopId(1), DesktopId(4))
+        });
+        
+        assert!(registry.contains(&windows, hwnd(0x100)));
+        assert!(!registry.contains(&windows, hwnd(0x200)));
+        assert_eq!(registry.get_by_desktop(DesktopId(4)).unwrap().hwnd, hwnd(0x100));
+        assert!(registry.get_by_desktop(DesktopId(1)).is_none());
+        
+        let saved = registry.snapshot().spaces[0].clone();
+        let info = SpaceInfo::from_saved(&saved);
+        assert_eq!(info.hwnd, hwnd(0x100));
+        assert_eq!(info.name, "App — 全屏");
+        assert_eq!(info.to_saved(), saved);
+    }
+
+    #[test]
+    fn test_collapsed_space_is_remembered_and_persisted() {
+        let windows = FakeBackend::new(1);
+        let store = temp_store("registry_collapse");
+        let mut registry = SpaceRegistry::with_store(store.clone());
+        registry.register(SpaceInfo::new(hwnd(0x100), WindowDetails::default(), DesktopId(1), DesktopId(4)));
+        
+        assert!(registry.collapse(&windows, hwnd(0x100)));
+        assert!(!registry.collapse(&windows, hwnd(0x100)));
+        assert!(!registry.contains(&windows, hwnd(0x100)));
+        assert!(registry.is_collapsed(&windows, hwnd(0x100)));
+        let saved = store.load().unwrap().unwrap();
+        assert!(saved.spaces.is_empty());
+        assert_eq!(saved.collapsed.len(), 1);
+        assert!(registry.debug_info().contains("已收起 1 个空间"));
+        
+        let info = registry.take_collapsed(&windows, hwnd(0x100)).unwrap();
+        assert_eq!(info.original_desktop, DesktopId(1));
+        assert!(!registry.is_collapsed(&windows, hwnd(0x100)));
+        assert!(store.load().unwrap().unwrap().collapsed.is_empty());
+        
+        let _ = std::fs::remove_dir_all(store.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_rename_only_affects_spaces() {
+        let windows = FakeBackend::new(1);
+        let mut registry = SpaceRegistry::new();
+        registry.register(SpaceInfo::new(hwnd(0x100), WindowDetails::default(), DesktopId(1), DesktopId(4)));
+        
+        assert!(registry.rename(DesktopId(4), "看电影"));
+        assert!(!registry.rename(DesktopId(1), "工作"));
+        assert_eq!(registry.get(&windows, hwnd(0x100)).unwrap().name, "看电影");
+        assert!(registry.debug_info().contains("名称=看电影"));
+    }
+
+    #[test]
+    fn test_lookup_verifies_window_identity() {
+        let windows = FakeBackend::new(1);
+        let app = hwnd(0x100);
+        windows.add_window(app, 0);
+        let mut registry = SpaceRegistry::new();
+        registry.register(SpaceInfo {
+            identity: windows.identity(app).unwrap(),
+            ..SpaceInfo::new(app, windows.details(app), DesktopId(1), DesktopId(4))
+        });
+        assert!(registry.contains(&windows, app));
+        assert!(registry.sweep(&windows).is_empty());
+        
+        // 窗口关闭后句柄被另一个进程的窗口复用：不再是这个空间
+        windows.close_window(app);
+        windows.add_window(app, 0);
+        assert!(!registry.contains(&windows, app));
+        assert!(registry.remove(&windows, app).is_none());
+        
+        let stale = registry.sweep(&windows);
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].created_desktop, DesktopId(4));
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_sweep_drops_closed_windows() {
+        let windows = FakeBackend::new(1);
+        let (live, closed) = (hwnd(0x100), hwnd(0x200));
+        windows.add_window(live, 0);
+        let mut registry = SpaceRegistry::new();
+        registry.register(SpaceInfo::new(live, WindowDetails::default(), DesktopId(1), DesktopId(4)));
+        registry.register(SpaceInfo::new(closed, WindowDetails::default(), DesktopId(1), DesktopId(5)));
+        registry.register(SpaceInfo::new(hwnd(0x300), WindowDetails::default(), DesktopId(1), DesktopId(6)));
+        registry.collapse(&windows, hwnd(0x300));
+        
+        let stale = registry.sweep(&windows);
+        
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].hwnd, closed);
+        assert!(registry.contains(&windows, live));
+        assert!(registry.snapshot().collapsed.is_empty());
+    }
+
+    #[test]
+    fn test_members_leave_in_order() {
+        let windows = FakeBackend::new(1);
+        let (app, second, third) = (hwnd(0x100), hwnd(0x200), hwnd(0x300));
+        for hwnd in [app, second, third] {
+            windows.add_window(hwnd, 0);
+        }
+        let mut registry = SpaceRegistry::new();
+        registry.register(SpaceInfo {
+            identity: windows.identity(app).unwrap(),
+            ..SpaceInfo::new(app, windows.details(app), DesktopId(1), DesktopId(4))
+        });
+        assert!(registry.add_member(DesktopId(4), SpaceMember::new(&windows, second)));
+        assert!(registry.add_member(DesktopId(4), SpaceMember::new(&windows, third)));
+        assert!(!registry.add_member(DesktopId(5), SpaceMember::new(&windows, third)));
+        assert!(registry.is_fullscreen_space(&windows, app));
+        assert!(!registry.is_fullscreen_space(&windows, second));
+        assert!(registry.contains(&windows, third));
+        assert_eq!(registry.snapshot().spaces[0].members.len(), 2);
+        
+        // 主窗口离开后由最早加入的窗口接替
+        assert!(registry.leave(&windows, app));
+        let space = registry.get_by_desktop(DesktopId(4)).unwrap();
+        assert_eq!(space.hwnds(), vec![second, third]);
+        assert!(space.app_initiated);
+        
+        // 错过了销毁事件的窗口由清理移除，最后一个窗口不能离开（调用方删除整个空间）
+        windows.close_window(second);
+        assert!(registry.sweep(&windows).is_empty());
+        assert_eq!(registry.get_by_desktop(DesktopId(4)).unwrap().hwnds(), vec![third]);
+        assert!(!registry.leave(&windows, third));
+        assert!(registry.contains(&windows, third));
+    }
+}
@@ -0,0 +1,339 @@
+//! 应用规则
+//!
+//! 不同应用进入全屏的方式不同：多数浏览器和播放器用 F11，有的应用用别的组合键，
+//! 有的应用 F11 另有用途（如 IDE 的书签），只适合最大化。规则表按进程名、窗口类名
+//! 和标题匹配窗口，决定全屏方式、是否排除以及各步骤的等待时间。
+//! 本模块只做匹配和取值，不依赖 Win32，可在任意平台测试。
+
+use crate::backend::{exe_stem, WindowDetails};
+use crate::config::{ConfigError, FullscreenMethodConfig, RuleConfig, TimingConfig};
+use crate::keymap::{Chord, ChordError};
+use regex::Regex;
+use std::time::Duration;
+
+/// 发送全屏按键后默认留给应用处理的时间（应用全屏状态无法从外部确认）
+pub const DEFAULT_SETTLE: Duration = Duration::from_millis(100);
+
+/// 单条规则中等待时间的上限（毫秒）
+const MAX_DELAY_MS: u64 = 2000;
+
+/// 全屏方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullscreenMethod {
+    /// 最大化后发送 F11
+    F11,
+    /// 最大化后发送指定的组合键
+    Keys(Chord),
+    /// 只最大化
+    Maximize,
+    /// 去掉标题栏和边框，铺满显示器
+    Borderless,
+}
+
+impl FullscreenMethod {
+    /// 进入和退出应用全屏时发送的组合键（不需要按键的方式返回 None）
+    pub fn chord(&self) -> Option<Chord> {
+        match self {
+            FullscreenMethod::F11 => Some(Chord::F11),
+            FullscreenMethod::Keys(chord) => Some(*chord),
+            FullscreenMethod::Maximize | FullscreenMethod::Borderless => None,
+        }
+    }
+}
+
+/// 规则匹配后某个窗口的全屏行为
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppBehavior {
+    /// 全屏方式
+    pub method: FullscreenMethod,
+    /// 不为该窗口创建全屏空间
+    pub excluded: bool,
+    /// 切换桌面后的等待时间
+    pub switch_delay: Duration,
+    /// 发送全屏按键后的等待时间
+    pub settle: Duration,
+}
+
+/// 编译后的单条规则
+#[derive(Debug, Clone)]
+struct Rule {
+    process: Option<String>,
+    class: Option<String>,
+    title: Option<Regex>,
+    exclude: bool,
+    method: FullscreenMethod,
+    switch_delay_ms: Option<u64>,
+    settle_ms: Option<u64>,
+}
+
+impl Rule {
+    /// 指定的条件是否全部满足
+    fn matches(&self, window: &WindowDetails) -> bool {
+        self.process
+            .as_deref()
+            .is_none_or(|process| same_process(process, window.process_name()))
+            && self
+                .class
+                .as_deref()
+                .is_none_or(|class| class == window.class_name)
+            && self
+                .title
+                .as_ref()
+                .is_none_or(|title| title.is_match(&window.title))
+    }
+}
+
+/// 比较进程名（不区分大小写，`.exe` 可省略）
+fn same_process(pattern: &str, process_name: &str) -> bool {
+    !process_name.is_empty()
+        && exe_stem(pattern.trim()).eq_ignore_ascii_case(exe_stem(process_name))
+}
+
+/// 应用规则表
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// 根据配置编译规则表，取值无效时返回对应配置项的错误
+    pub fn from_config(rules: &[RuleConfig]) -> Result<Self, ConfigError> {
+        let rules = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| compile(rule).map_err(|(field, message)| ConfigError::Invalid {
+                field,
+                message: format!("第 {} 条规则{}", i + 1, message),
+            }))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// 窗口的全屏行为：第一条匹配的规则生效，没有匹配时使用 F11 和全局时序
+    pub fn resolve(&self, window: &WindowDetails, timing: &TimingConfig) -> AppBehavior {
+        let rule = self.rules.iter().find(|rule| rule.matches(window));
+        AppBehavior {
+            method: rule.map_or(FullscreenMethod::F11, |rule| rule.method),
+            excluded: rule.is_some_and(|rule| rule.exclude),
+            switch_delay: Duration::from_millis(
+                rule.and_then(|rule| rule.switch_delay_ms)
+                    .unwrap_or(timing.switch_delay_ms),
+            ),
+            settle: rule
+                .and_then(|rule| rule.settle_ms)
+                .map_or(DEFAULT_SETTLE, Duration::from_millis),
+        }
+    }
+}
+
+/// 编译单条规则，失败时返回配置项名和原因
+fn compile(rule: &RuleConfig) -> Result<Rule, (&'static str, String)> {
+    let non_empty = |value: &Option<String>| {
+        value
+            .as_deref()
+            .filter(|v| !v.trim().is_empty())
+            .map(str::to_string)
+    };
+    let process = non_empty(&rule.process);
+    let class = non_empty(&rule.class);
+    let title = non_empty(&rule.title)
+        .map(|pattern| Regex::new(&pattern))
+        .transpose()
+        .map_err(|e| ("rules.title", format!("的正则表达式无效: {}", e)))?;
+
+    if process.is_none() && class.is_none() && title.is_none() {
+        return Err(("rules", "至少需要指定 process、class、title 中的一项".to_string()));
+    }
+
+    let method = match (rule.method, rule.keys.as_deref()) {
+        (FullscreenMethodConfig::Keys, Some(keys)) => FullscreenMethod::Keys(
+            keys.parse()
+                .map_err(|e: ChordError| ("rules.keys", format!(" {}", e)))?,
+        ),
+        (FullscreenMethodConfig::Keys, None) => {
+            return Err(("rules.keys", "使用 method = \"keys\" 时必须指定 keys".to_string()));
+        }
+        (_, Some(_)) => {
+            return Err(("rules.keys", "只有 method = \"keys\" 时才能指定 keys".to_string()));
+        }
+        (FullscreenMethodConfig::F11, None) => FullscreenMethod::F11,
+        (FullscreenMethodConfig::Maximize, None) => FullscreenMethod::Maximize,
+        (FullscreenMethodConfig::Borderless, None) => FullscreenMethod::Borderless,
+    };
+
+    for (field, value) in [
+        ("rules.switch_delay_ms", rule.switch_delay_ms),
+        ("rules.settle_ms", rule.settle_ms),
+    ] {
+        if value.is_some_and(|ms| ms > MAX_DELAY_MS) {
+            return Err((field, format!("不能超过 {} 毫秒", MAX_DELAY_MS)));
+        }
+    }
+
+    Ok(Rule {
+        process,
+        class,
+        title,
+        exclude: rule.exclude,
+        method,
+        switch_delay_ms: rule.switch_delay_ms,
+        settle_ms: rule.settle_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Modifiers;
+
+    fn window(process_path: &str, class_name: &str, title: &str) -> WindowDetails {
+        WindowDetails {
+            process_path: process_path.to_string(),
+            class_name: class_name.to_string(),
+            title: title.to_string(),
+        }
+    }
+
+    fn rule_set(rules: Vec<RuleConfig>) -> RuleSet {
+        RuleSet::from_config(&rules).unwrap()
+    }
+
+    fn timing() -> TimingConfig {
+        TimingConfig {
+            switch_delay_ms: 150,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_no_match_uses_defaults() {
+        let rules = rule_set(vec![RuleConfig {
+            process: Some("code.exe".to_string()),
+            method: FullscreenMethodConfig::Maximize,
+            ..Default::default()
+        }]);
+
+        let behavior = rules.resolve(&window(r"C:\Apps\chrome.exe", "Chrome", "Google"), &timing());
+
+        assert_eq!(
+            behavior,
+            AppBehavior {
+                method: FullscreenMethod::F11,
+                excluded: false,
+                switch_delay: Duration::from_millis(150),
+                settle: DEFAULT_SETTLE,
+            }
+        );
+    }
+
+    #[test]
+    fn test_process_name_ignores_case_and_extension() {
+        let rules = rule_set(vec![RuleConfig {
+            process: Some("Code".to_string()),
+            method: FullscreenMethodConfig::Maximize,
+            ..Default::default()
+        }]);
+
+        let code = window(r"C:\Users\me\AppData\Local\Programs\VS Code\CODE.EXE", "", "");
+        assert_eq!(rules.resolve(&code, &timing()).method, FullscreenMethod::Maximize);
+
+        // 只比较文件名，路径中的目录名不算
+        let other = window(r"C:\Code\other.exe", "", "");
+        assert_eq!(rules.resolve(&other, &timing()).method, FullscreenMethod::F11);
+    }
+
+    #[test]
+    fn test_all_given_conditions_must_match() {
+        let rules = rule_set(vec![RuleConfig {
+            process: Some("chrome.exe".to_string()),
+            title: Some(r"(?i)\byoutube\b".to_string()),
+            exclude: true,
+            ..Default::default()
+        }]);
+
+        let youtube = window(r"C:\Apps\chrome.exe", "Chrome_WidgetWin_1", "视频 - YouTube - Google Chrome");
+        let other_tab = window(r"C:\Apps\chrome.exe", "Chrome_WidgetWin_1", "新标签页");
+        let edge = window(r"C:\Apps\msedge.exe", "Chrome_WidgetWin_1", "YouTube");
+
+        assert!(rules.resolve(&youtube, &timing()).excluded);
+        assert!(!rules.resolve(&other_tab, &timing()).excluded);
+        assert!(!rules.resolve(&edge, &timing()).excluded);
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = rule_set(vec![
+            RuleConfig {
+                class: Some("mintty".to_string()),
+                method: FullscreenMethodConfig::Keys,
+                keys: Some("Alt+Enter".to_string()),
+                switch_delay_ms: Some(300),
+                settle_ms: Some(250),
+                ..Default::default()
+            },
+            RuleConfig {
+                title: Some(".*".to_string()),
+                method: FullscreenMethodConfig::Borderless,
+                ..Default::default()
+            },
+        ]);
+
+        let terminal = rules.resolve(&window(r"C:\msys\mintty.exe", "mintty", "~"), &timing());
+        let alt = Modifiers { alt: true, ..Default::default() };
+        assert_eq!(terminal.method, FullscreenMethod::Keys(Chord::new(alt, 0x0D)));
+        assert_eq!(terminal.method.chord(), Some(Chord::new(alt, 0x0D)));
+        assert_eq!(terminal.switch_delay, Duration::from_millis(300));
+        assert_eq!(terminal.settle, Duration::from_millis(250));
+
+        let other = rules.resolve(&window(r"C:\Apps\app.exe", "App", "标题"), &timing());
+        assert_eq!(other.method, FullscreenMethod::Borderless);
+        assert_eq!(other.method.chord(), None);
+        assert_eq!(other.switch_delay, Duration::from_millis(150));
+    }
+
+    #[test]
+    fn test_invalid_rules_are_reported() {
+        let rule = |process: Option<&str>| RuleConfig {
+            process: process.map(str::to_string),
+            ..Default::default()
+        };
+        let invalid_field = |rules: &[RuleConfig]| match RuleSet::from_config(rules) {
+            Err(ConfigError::Invalid { field, message }) => (field, message),
+            other => panic!("应当报错: {:?}", other.map(|_| ())),
+        };
+
+        assert_eq!(invalid_field(&[rule(None)]).0, "rules");
+
+        let (field, message) = invalid_field(&[
+            rule(Some("a.exe")),
+            RuleConfig {
+                title: Some("(未闭合".to_string()),
+                ..Default::default()
+            },
+        ]);
+        assert_eq!(field, "rules.title");
+        assert!(message.starts_with("第 2 条规则"));
+
+        let keys_without_chord = RuleConfig {
+            method: FullscreenMethodConfig::Keys,
+            ..rule(Some("a.exe"))
+        };
+        let chord_without_keys_method = RuleConfig {
+            keys: Some("Alt+Enter".to_string()),
+            ..rule(Some("a.exe"))
+        };
+        let chord_without_modifier = RuleConfig {
+            method: FullscreenMethodConfig::Keys,
+            keys: Some("Enter".to_string()),
+            ..rule(Some("a.exe"))
+        };
+        let long_settle = RuleConfig {
+            settle_ms: Some(5000),
+            ..rule(Some("a.exe"))
+        };
+        assert_eq!(invalid_field(&[keys_without_chord]).0, "rules.keys");
+        assert_eq!(invalid_field(&[chord_without_keys_method]).0, "rules.keys");
+        assert_eq!(invalid_field(&[chord_without_modifier]).0, "rules.keys");
+        assert_eq!(invalid_field(&[long_settle]).0, "rules.settle_ms");
+    }
+}
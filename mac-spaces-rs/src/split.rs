@@ -0,0 +1,108 @@
+//! 分屏的布局计算
+//!
+//! 分屏空间中两个窗口左右并排铺满显示器的工作区，分隔线的位置由比例决定（左侧窗口占的宽度）。
+//! 这里只做几何计算，与平台无关，便于测试；窗口的移动由 desktop.rs 完成。
+
+use crate::backend::WindowRect;
+
+/// 默认比例：左右各占一半
+pub const DEFAULT_RATIO: f64 = 0.5;
+
+/// 左侧窗口至少占的比例（右侧同理），避免某一侧窄到无法使用
+pub const MIN_RATIO: f64 = 0.2;
+
+/// 左侧窗口最多占的比例
+pub const MAX_RATIO: f64 = 1.0 - MIN_RATIO;
+
+/// 把比例限制在 `MIN_RATIO..=MAX_RATIO` 之内（NaN 视为默认比例）
+pub fn clamp_ratio(ratio: f64) -> f64 {
+    if ratio.is_nan() {
+        DEFAULT_RATIO
+    } else {
+        ratio.clamp(MIN_RATIO, MAX_RATIO)
+    }
+}
+
+/// 按比例把工作区分成左右两块，返回 (左, 右)
+///
+/// 两块高度与工作区相同，宽度之和等于工作区宽度（左侧宽度四舍五入），中间没有缝隙。
+pub fn split_rects(area: &WindowRect, ratio: f64) -> (WindowRect, WindowRect) {
+    let width = area.right - area.left;
+    let divider = area.left + (width as f64 * clamp_ratio(ratio)).round() as i32;
+    let left = WindowRect {
+        right: divider,
+        ..*area
+    };
+    let right = WindowRect {
+        left: divider,
+        ..*area
+    };
+    (left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1920x1080 显示器去掉 40 像素高的任务栏
+    const WORK_AREA: WindowRect = WindowRect {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> WindowRect {
+        WindowRect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn test_even_split() {
+        let (left, right) = split_rects(&WORK_AREA, DEFAULT_RATIO);
+
+        assert_eq!(left, rect(0, 0, 960, 1040));
+        assert_eq!(right, rect(960, 0, 1920, 1040));
+    }
+
+    #[test]
+    fn test_uneven_split_rounds_and_leaves_no_gap() {
+        let (left, right) = split_rects(&WORK_AREA, 1.0 / 3.0);
+        assert_eq!(left, rect(0, 0, 640, 1040));
+        assert_eq!(right, rect(640, 0, 1920, 1040));
+
+        // 奇数宽度：左侧四舍五入，两侧宽度之和仍等于工作区宽度
+        let area = rect(0, 0, 1365, 768);
+        let (left, right) = split_rects(&area, 0.5);
+        assert_eq!(left.right, 683);
+        assert_eq!(right.left, left.right);
+        assert_eq!((left.right - left.left) + (right.right - right.left), 1365);
+    }
+
+    #[test]
+    fn test_split_on_secondary_monitor() {
+        // 位于主屏左侧的副屏，坐标为负
+        let area = rect(-1280, 0, 0, 984);
+        let (left, right) = split_rects(&area, 0.25);
+
+        assert_eq!(left, rect(-1280, 0, -960, 984));
+        assert_eq!(right, rect(-960, 0, 0, 984));
+    }
+
+    #[test]
+    fn test_ratio_is_clamped() {
+        assert_eq!(clamp_ratio(0.05), MIN_RATIO);
+        assert_eq!(clamp_ratio(0.95), MAX_RATIO);
+        assert_eq!(clamp_ratio(0.6), 0.6);
+        assert_eq!(clamp_ratio(f64::NAN), DEFAULT_RATIO);
+
+        let (left, _) = split_rects(&WORK_AREA, 0.0);
+        assert_eq!(left.right, 384);
+        let (_, right) = split_rects(&WORK_AREA, 1.0);
+        assert_eq!(right.left, 1536);
+    }
+}
@@ -0,0 +1,316 @@
+//! 状态文件
+//!
+//! 将空间注册表保存到 `%APPDATA%\MacSpaces\state.toml`，程序崩溃或重启后
+//! 据此找回之前创建的全屏空间：仍然存在的窗口重新接管，失效的空间删除其桌面。
+//!
+//! 写入时先写临时文件再重命名，避免写到一半崩溃留下损坏的文件。
+
+use crate::backend::{DesktopId, WindowDetails, WindowFrame, WindowIdentity, WindowPlacement};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// 当前状态文件版本（版本 2 起桌面以 GUID 记录）
+pub const STATE_VERSION: u32 = 2;
+
+/// 状态文件名
+pub const STATE_FILE_NAME: &str = "state.toml";
+
+#[derive(Error, Debug)]
+pub enum StateError {
+    #[error("无法读写状态文件 {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("状态文件 {path} 格式错误: {message}")]
+    Parse { path: PathBuf, message: String },
+
+    #[error("无法序列化状态: {0}")]
+    Serialize(String),
+}
+
+/// 保存的全部状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedState {
+    /// 状态文件版本
+    pub version: u32,
+    /// 已注册的全屏空间
+    #[serde(default)]
+    pub spaces: Vec<SavedSpace>,
+    /// 窗口最小化或隐藏时收起的空间（窗口恢复显示时重新创建）
+    #[serde(default)]
+    pub collapsed: Vec<SavedSpace>,
+}
+
+/// 保存的单个全屏空间
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSpace {
+    /// 窗口句柄（重启后可能已被其他窗口复用，需要结合 `identity` 和 `window` 校验）
+    pub hwnd: i64,
+    /// 原始桌面
+    pub original_desktop: DesktopId,
+    /// 创建的桌面
+    pub created_desktop: DesktopId,
+    /// 创建时间（Unix 秒）
+    pub created_at: u64,
+    /// 最后更新时间（Unix 秒）
+    pub updated_at: u64,
+    /// 创建的桌面的名称（旧版本的状态文件中没有，视为未命名）
+    #[serde(default)]
+    pub name: String,
+    /// 窗口是应用自己进入的全屏（旧版本的状态文件中没有）
+    #[serde(default)]
+    pub app_initiated: bool,
+    /// 分屏时左侧窗口占的比例（不是分屏空间时没有）
+    #[serde(default)]
+    pub split: Option<f64>,
+    /// 窗口描述信息
+    pub window: WindowDetails,
+    /// 窗口身份（旧版本的状态文件中没有，视为未知）
+    #[serde(default)]
+    pub identity: WindowIdentity,
+    /// 之后加入空间的其他窗口（按加入顺序）
+    #[serde(default)]
+    pub members: Vec<SavedMember>,
+    /// 无边框全屏前的窗口样式（其他方式进入的空间没有）
+    #[serde(default)]
+    pub frame: Option<WindowFrame>,
+    /// 进入前窗口的位置、大小和最大化状态（旧版本的状态文件中没有）
+    #[serde(default)]
+    pub placement: Option<WindowPlacement>,
+}
+
+/// 加入空间的其他窗口
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedMember {
+    /// 窗口句柄
+    pub hwnd: i64,
+    /// 窗口描述信息
+    pub window: WindowDetails,
+    /// 窗口身份
+    #[serde(default)]
+    pub identity: WindowIdentity,
+    /// 加入前窗口的位置、大小和最大化状态（分屏时记录，离开时恢复）
+    #[serde(default)]
+    pub placement: Option<WindowPlacement>,
+}
+
+impl Default for SavedState {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            spaces: Vec::new(),
+            collapsed: Vec::new(),
+        }
+    }
+}
+
+/// 状态文件读写
+#[derive(Debug, Clone)]
+pub struct StateStore {
+    path: PathBuf,
+}
+
+impl StateStore {
+    /// 使用指定路径的状态文件
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// 状态文件路径
+    #[cfg(test)]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取状态，文件不存在时返回 None
+    pub fn load(&self) -> Result<Option<SavedState>, StateError> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => {
+                return Err(StateError::Io {
+                    path: self.path.clone(),
+                    source,
+                })
+            }
+        };
+
+        let state: SavedState = toml::from_str(&text).map_err(|e| StateError::Parse {
+            path: self.path.clone(),
+            message: e.to_string().trim_end().to_string(),
+        })?;
+
+        if state.version != STATE_VERSION {
+            return Err(StateError::Parse {
+                path: self.path.clone(),
+                message: format!("不支持的版本 {}", state.version),
+            });
+        }
+
+        Ok(Some(state))
+    }
+
+    /// 写入状态（先写临时文件再替换）
+    pub fn save(&self, state: &SavedState) -> Result<(), StateError> {
+        let text = toml::to_string(state).map_err(|e| StateError::Serialize(e.to_string()))?;
+        let io_error = |source| StateError::Io {
+            path: self.path.clone(),
+            source,
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let tmp = self.path.with_extension("toml.tmp");
+        fs::write(&tmp, text).map_err(io_error)?;
+        fs::rename(&tmp, &self.path).map_err(io_error)?;
+        Ok(())
+    }
+}
+
+/// 状态文件的默认路径：优先 `%APPDATA%\MacSpaces`，否则放在程序目录下
+pub fn default_path(appdata: Option<&Path>, exe_dir: Option<&Path>) -> Option<PathBuf> {
+    appdata
+        .map(|dir| dir.join("MacSpaces"))
+        .or_else(|| exe_dir.map(Path::to_path_buf))
+        .map(|dir| dir.join(STATE_FILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{WindowPoint, WindowRect};
+
+    fn temp_store(name: &str) -> StateStore {
+        let dir = std::env::temp_dir().join(format!("mac_spaces_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        StateStore::new(dir.join(STATE_FILE_NAME))
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let store = temp_store("round_trip");
+        assert!(store.load().unwrap().is_none());
+
+        let state = SavedState {
+            version: STATE_VERSION,
+            spaces: vec![SavedSpace {
+                hwnd: 0x1234,
+                original_desktop: DesktopId(0x1111),
+                created_desktop: DesktopId(0xFFFF_0000_0000_0000_0000_0000_0000_2222),
+                created_at: 1_700_000_000,
+                updated_at: 1_700_000_100,
+                name: "App — 全屏".to_string(),
+                app_initiated: false,
+                split: Some(0.6),
+                window: WindowDetails {
+                    process_path: r"C:\Program Files\App\app.exe".to_string(),
+                    class_name: "Chrome_WidgetWin_1".to_string(),
+                    title: "标题 \"引号\"".to_string(),
+                },
+                identity: WindowIdentity {
+                    pid: 4242,
+                    process_started: 133_500_000_000_000_000,
+                    class_name: "Chrome_WidgetWin_1".to_string(),
+                },
+                members: vec![SavedMember {
+                    hwnd: 0x5678,
+                    window: WindowDetails {
+                        process_path: r"C:\Windows\notepad.exe".to_string(),
+                        class_name: "Notepad".to_string(),
+                        title: "笔记".to_string(),
+                    },
+                    identity: WindowIdentity {
+                        pid: 4343,
+                        process_started: 133_500_000_000_000_001,
+                        class_name: "Notepad".to_string(),
+                    },
+                    placement: Some(WindowPlacement {
+                        maximized: false,
+                        flags: 0,
+                        min_position: WindowPoint { x: -1, y: -1 },
+                        max_position: WindowPoint { x: -1, y: -1 },
+                        normal_position: WindowRect {
+                            left: 100,
+                            top: 100,
+                            right: 900,
+                            bottom: 700,
+                        },
+                        monitor: WindowRect {
+                            left: 0,
+                            top: 0,
+                            right: 1920,
+                            bottom: 1080,
+                        },
+                        work_area: WindowRect {
+                            left: 0,
+                            top: 0,
+                            right: 1920,
+                            bottom: 1040,
+                        },
+                    }),
+                }],
+                frame: Some(WindowFrame {
+                    style: 0x14CF_0000,
+                    ex_style: 0x100,
+                }),
+                placement: Some(WindowPlacement {
+                    maximized: true,
+                    flags: 2,
+                    min_position: WindowPoint { x: -1, y: -1 },
+                    max_position: WindowPoint { x: -8, y: -8 },
+                    normal_position: WindowRect {
+                        left: -1200,
+                        top: 40,
+                        right: -80,
+                        bottom: 720,
+                    },
+                    monitor: WindowRect {
+                        left: -1280,
+                        top: 0,
+                        right: 0,
+                        bottom: 1024,
+                    },
+                    work_area: WindowRect {
+                        left: -1280,
+                        top: 0,
+                        right: 0,
+                        bottom: 984,
+                    },
+                }),
+            }],
+            collapsed: Vec::new(),
+        };
+        store.save(&state).unwrap();
+
+        assert_eq!(store.load().unwrap(), Some(state));
+        let _ = fs::remove_dir_all(store.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_corrupt_file_is_reported() {
+        let store = temp_store("corrupt");
+        fs::create_dir_all(store.path().parent().unwrap()).unwrap();
+        fs::write(store.path(), "spaces = 3").unwrap();
+
+        assert!(matches!(store.load(), Err(StateError::Parse { .. })));
+        let _ = fs::remove_dir_all(store.path().parent().unwrap());
+    }
+
+    #[test]
+    fn test_default_path() {
+        assert_eq!(
+            default_path(Some(Path::new("appdata")), Some(Path::new("exe"))),
+            Some(Path::new("appdata").join("MacSpaces").join(STATE_FILE_NAME))
+        );
+        assert_eq!(
+            default_path(None, Some(Path::new("exe"))),
+            Some(Path::new("exe").join(STATE_FILE_NAME))
+        );
+        assert_eq!(default_path(None, None), None);
+    }
+}
@@ -0,0 +1,192 @@
+//! 全屏空间切换事务
+//!
+//! 进入/退出全屏空间由多个步骤组成（新建桌面、移动窗口、切换桌面……），
+//! 任何一步都可能失败：窗口中途关闭、以管理员权限运行的窗口拒绝移动等。
+//! 每一步成功后登记对应的补偿操作，之后某一步失败时按相反顺序执行补偿，
+//! 把桌面和窗口恢复到开始前的样子，并返回说明失败位置的错误。
+
+use crate::wait::WaitError;
+use std::fmt;
+use thiserror::Error;
+use tracing::warn;
+
+/// 切换类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// 进入全屏空间
+    Enter,
+    /// 退出全屏空间
+    Exit,
+}
+
+impl fmt::Display for TransitionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransitionKind::Enter => write!(f, "进入全屏空间"),
+            TransitionKind::Exit => write!(f, "退出全屏空间"),
+        }
+    }
+}
+
+/// 单个步骤失败的原因
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum StepError {
+    #[error(transparent)]
+    Wait(#[from] WaitError),
+
+    #[error("窗口已关闭")]
+    WindowClosed,
+
+    #[error("无法获取{0}")]
+    Unavailable(&'static str),
+
+    #[error("窗口拒绝了{0}")]
+    Rejected(&'static str),
+}
+
+/// 切换失败
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{kind}失败（{step}）: {cause}")]
+pub struct TransitionError {
+    /// 切换类型
+    pub kind: TransitionKind,
+    /// 失败的步骤
+    pub step: &'static str,
+    /// 失败原因
+    pub cause: StepError,
+    /// 回滚时失败的补偿操作（为空表示已完全恢复）
+    pub rollback_failures: Vec<&'static str>,
+}
+
+impl TransitionError {
+    /// 是否已完全恢复到切换前的状态
+    pub fn rolled_back(&self) -> bool {
+        self.rollback_failures.is_empty()
+    }
+}
+
+type Compensation<'a> = Box<dyn FnOnce() -> Result<(), StepError> + 'a>;
+
+/// 切换事务
+///
+/// 用 `step` 执行每一步，用 `compensate` 登记撤销已完成步骤的操作，
+/// 全部完成后调用 `commit`。未提交就丢弃的事务不会执行补偿。
+pub struct Transaction<'a> {
+    kind: TransitionKind,
+    compensations: Vec<(&'static str, Compensation<'a>)>,
+}
+
+impl<'a> Transaction<'a> {
+    pub fn new(kind: TransitionKind) -> Self {
+        Self {
+            kind,
+            compensations: Vec::new(),
+        }
+    }
+
+    /// 执行一步，失败时回滚已完成的步骤并返回错误
+    pub fn step<T>(
+        &mut self,
+        name: &'static str,
+        action: impl FnOnce() -> Result<T, StepError>,
+    ) -> Result<T, TransitionError> {
+        action().map_err(|cause| self.rollback(name, cause))
+    }
+
+    /// 登记补偿操作，回滚时按登记的相反顺序执行
+    pub fn compensate(
+        &mut self,
+        name: &'static str,
+        undo: impl FnOnce() -> Result<(), StepError> + 'a,
+    ) {
+        self.compensations.push((name, Box::new(undo)));
+    }
+
+    /// 全部步骤完成，放弃补偿操作
+    pub fn commit(self) {}
+
+    /// 执行全部补偿操作（补偿失败时继续执行其余补偿）
+    fn rollback(&mut self, step: &'static str, cause: StepError) -> TransitionError {
+        warn!("{}失败（{}）: {}，开始回滚", self.kind, step, cause);
+
+        let mut rollback_failures = Vec::new();
+        while let Some((name, undo)) = self.compensations.pop() {
+            if let Err(e) = undo() {
+                warn!("回滚步骤「{}」失败: {}", name, e);
+                rollback_failures.push(name);
+            }
+        }
+
+        TransitionError {
+            kind: self.kind,
+            step,
+            cause,
+            rollback_failures,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_failure_runs_compensations_in_reverse() {
+        let log = RefCell::new(Vec::new());
+        let mut tx = Transaction::new(TransitionKind::Enter);
+
+        tx.step("第一步", || Ok(())).unwrap();
+        tx.compensate("撤销第一步", || {
+            log.borrow_mut().push(1);
+            Ok(())
+        });
+        tx.step("第二步", || Ok(())).unwrap();
+        tx.compensate("撤销第二步", || {
+            log.borrow_mut().push(2);
+            Ok(())
+        });
+        let err = tx
+            .step("第三步", || Err::<(), _>(StepError::WindowClosed))
+            .unwrap_err();
+
+        assert_eq!(*log.borrow(), vec![2, 1]);
+        assert_eq!(err.step, "第三步");
+        assert_eq!(err.cause, StepError::WindowClosed);
+        assert!(err.rolled_back());
+        assert_eq!(err.to_string(), "进入全屏空间失败（第三步）: 窗口已关闭");
+    }
+
+    #[test]
+    fn test_failed_compensation_is_reported_and_others_still_run() {
+        let log = RefCell::new(Vec::new());
+        let mut tx = Transaction::new(TransitionKind::Exit);
+
+        tx.compensate("撤销 A", || {
+            log.borrow_mut().push("A");
+            Ok(())
+        });
+        tx.compensate("撤销 B", || Err(StepError::Unavailable("桌面")));
+        let err = tx
+            .step("C", || Err::<(), _>(StepError::Unavailable("窗口")))
+            .unwrap_err();
+
+        assert_eq!(*log.borrow(), vec!["A"]);
+        assert_eq!(err.rollback_failures, vec!["撤销 B"]);
+        assert!(!err.rolled_back());
+    }
+
+    #[test]
+    fn test_commit_discards_compensations() {
+        let ran = RefCell::new(false);
+        let mut tx = Transaction::new(TransitionKind::Enter);
+
+        tx.compensate("撤销", || {
+            *ran.borrow_mut() = true;
+            Ok(())
+        });
+        tx.commit();
+
+        assert!(!*ran.borrow());
+    }
+}
@@ -4,22 +4,17 @@ use muda::{Menu, MenuItem, PredefinedMenuItem};
 use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
 use tracing::{debug, warn};
 
-/// 托盘菜单事件
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TrayEvent {
-    ShowInfo,
-    ShowRegistry,
-    ToggleDebug,
-    Reload,
-    Exit,
-}
+const DEFAULT_TOOLTIP: &str = "MacSpaces - 虚拟桌面空间化";
 
 /// 托盘图标管理器
 pub struct TrayManager {
-    _tray: TrayIcon,
+    tray: TrayIcon,
+    /// 当前桌面及其名称（只用于显示，禁用状态）
+    menu_current_desktop: MenuItem,
+    /// 最近一次失败的操作（只用于显示，禁用状态）
+    menu_last_error: MenuItem,
     pub menu_show_info: MenuItem,
     pub menu_show_registry: MenuItem,
-    pub menu_toggle_debug: MenuItem,
     pub menu_reload: MenuItem,
     pub menu_exit: MenuItem,
 }
@@ -34,6 +29,9 @@ impl TrayManager {
         let title = MenuItem::new("MacSpaces v0.3.0", false, None);
         menu.append(&title)?;
         
+        let menu_current_desktop = MenuItem::new("当前桌面: -", false, None);
+        menu.append(&menu_current_desktop)?;
+        
         menu.append(&PredefinedMenuItem::separator())?;
         
         // 功能菜单项
@@ -43,10 +41,8 @@ impl TrayManager {
         menu.append(&menu_show_info)?;
         menu.append(&menu_show_registry)?;
         
-        menu.append(&PredefinedMenuItem::separator())?;
-        
-        let menu_toggle_debug = MenuItem::new("调试模式", true, None);
-        menu.append(&menu_toggle_debug)?;
+        let menu_last_error = MenuItem::new("最近没有失败的操作", false, None);
+        menu.append(&menu_last_error)?;
         
         menu.append(&PredefinedMenuItem::separator())?;
         
@@ -61,7 +57,7 @@ impl TrayManager {
         
         // 创建托盘图标
         let mut builder = TrayIconBuilder::new()
-            .with_tooltip("MacSpaces - 虚拟桌面空间化")
+            .with_tooltip(DEFAULT_TOOLTIP)
             .with_menu(Box::new(menu));
         
         if let Some(icon) = icon {
@@ -73,14 +69,26 @@ impl TrayManager {
         debug!("托盘图标已创建");
         
         Ok(Self {
-            _tray: tray,
+            tray,
+            menu_current_desktop,
+            menu_last_error,
             menu_show_info,
             menu_show_registry,
-            menu_toggle_debug,
             menu_reload,
             menu_exit,
         })
     }
+    
+    /// 在菜单中显示当前桌面（如 `#3 Chrome — 全屏`）
+    pub fn show_current_desktop(&self, label: &str) {
+        self.menu_current_desktop.set_text(format!("当前桌面: {}", label));
+    }
+    
+    /// 在托盘提示和菜单中显示最近一次操作失败的原因
+    pub fn report_error(&self, message: &str) {
+        let _ = self.tray.set_tooltip(Some(format!("MacSpaces - {}", message)));
+        self.menu_last_error.set_text(format!("最近失败: {}", message));
+    }
 }
 
 /// 创建默认图标（16x16 蓝色方块）
@@ -1,129 +1,353 @@
 //! VirtualDesktopAccessor.dll FFI 封装
-//! 
-//! 封装对 VirtualDesktopAccessor.dll 的调用，提供类型安全的 Rust 接口
+//!
+//! 封装对 VirtualDesktopAccessor.dll 的调用，提供类型安全的 Rust 接口。
+//! 所有导出函数在加载时一次性解析并缓存，缺少必需函数时加载失败，
+//! 缺少可选函数时只禁用对应的功能。
 
-use libloading::{Library, Symbol};
+use crate::backend::{DesktopBackend, DesktopId, HWND};
+use libloading::Library;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::ffi::CString;
 use std::path::Path;
 use thiserror::Error;
-use windows::Win32::Foundation::HWND;
+use windows::core::GUID;
 
 #[derive(Error, Debug)]
 pub enum VdaError {
     #[error("DLL 文件未找到: {0}")]
     DllNotFound(String),
-    
+
     #[error("无法加载 DLL: {0}")]
     LoadError(#[from] libloading::Error),
-    
-    #[error("函数调用失败: {0}")]
-    CallError(String),
+
+    #[error("DLL 缺少必需的导出函数（可能与当前 Windows 版本不匹配）: {}", .0.join(", "))]
+    MissingExports(Vec<&'static str>),
+}
+
+/// 可选能力（依赖较新版本 DLL 的导出函数）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Capability {
+    /// 桌面名称：GetDesktopName / SetDesktopName
+    DesktopNames,
+    /// 窗口固定：IsPinnedWindow / PinWindow / UnPinWindow
+    Pinning,
+    /// 桌面变化通知：RegisterPostMessageHook / UnregisterPostMessageHook
+    Notifications,
+    /// 调整桌面顺序：MoveDesktop（随附的 DLL 尚未提供，不支持时新桌面只能追加在最后）
+    DesktopOrdering,
+}
+
+/// 必需的导出函数
+struct Exports {
+    get_desktop_count: unsafe extern "C" fn() -> i32,
+    get_current_desktop_number: unsafe extern "C" fn() -> i32,
+    go_to_desktop_number: unsafe extern "C" fn(i32),
+    create_desktop: unsafe extern "C" fn(),
+    remove_desktop: unsafe extern "C" fn(i32, i32),
+    move_window_to_desktop_number: unsafe extern "C" fn(isize, i32),
+    get_window_desktop_number: unsafe extern "C" fn(isize) -> i32,
+    get_desktop_id_by_number: unsafe extern "C" fn(i32) -> GUID,
+    get_desktop_number_by_id: unsafe extern "C" fn(GUID) -> i32,
+}
+
+/// 可选的导出函数
+struct OptionalExports {
+    get_desktop_name: Option<unsafe extern "C" fn(i32, *mut u8, usize) -> i32>,
+    set_desktop_name: Option<unsafe extern "C" fn(i32, *const u8) -> i32>,
+    is_pinned_window: Option<unsafe extern "C" fn(isize) -> i32>,
+    pin_window: Option<unsafe extern "C" fn(isize) -> i32>,
+    unpin_window: Option<unsafe extern "C" fn(isize) -> i32>,
+    register_post_message_hook: Option<unsafe extern "C" fn(isize, u32) -> i32>,
+    unregister_post_message_hook: Option<unsafe extern "C" fn(isize) -> i32>,
+    move_desktop: Option<unsafe extern "C" fn(i32, i32) -> i32>,
+}
+
+impl OptionalExports {
+    /// 汇总可选能力（一组函数全部存在才视为支持）
+    fn capabilities(&self) -> BTreeMap<Capability, bool> {
+        BTreeMap::from([
+            (
+                Capability::DesktopNames,
+                self.get_desktop_name.is_some() && self.set_desktop_name.is_some(),
+            ),
+            (
+                Capability::Pinning,
+                self.is_pinned_window.is_some()
+                    && self.pin_window.is_some()
+                    && self.unpin_window.is_some(),
+            ),
+            (
+                Capability::Notifications,
+                self.register_post_message_hook.is_some()
+                    && self.unregister_post_message_hook.is_some(),
+            ),
+            (Capability::DesktopOrdering, self.move_desktop.is_some()),
+        ])
+    }
+}
+
+/// 导出函数解析器，记录所有缺失的必需函数
+struct Resolver<'a> {
+    lib: &'a Library,
+    missing: Vec<&'static str>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(lib: &'a Library) -> Self {
+        Self {
+            lib,
+            missing: Vec::new(),
+        }
+    }
+
+    /// 解析必需函数，缺失时记录下来（不立即失败，以便一次报告全部缺失项）
+    fn required<T: Copy>(&mut self, name: &'static str) -> Option<T> {
+        let func = self.optional(name);
+        if func.is_none() {
+            self.missing.push(name);
+        }
+        func
+    }
+
+    /// 解析可选函数
+    fn optional<T: Copy>(&self, name: &'static str) -> Option<T> {
+        unsafe { self.lib.get::<T>(name.as_bytes()).ok().map(|func| *func) }
+    }
 }
 
 /// VirtualDesktopAccessor DLL 封装
 pub struct VirtualDesktopAccessor {
-    lib: Library,
+    exports: Exports,
+    #[allow(dead_code)] // 由依赖较新导出函数的功能按需使用
+    optional: OptionalExports,
+    capabilities: BTreeMap<Capability, bool>,
+    /// 已注册桌面切换通知的窗口，释放时自动取消
+    hook_hwnd: Cell<Option<isize>>,
+    /// 函数指针只在 DLL 加载期间有效，必须与其一起持有
+    _lib: Library,
 }
 
 impl VirtualDesktopAccessor {
-    /// 加载 DLL
+    /// 加载 DLL 并解析所有导出函数
     pub fn new<P: AsRef<Path>>(dll_path: P) -> Result<Self, VdaError> {
         let path = dll_path.as_ref();
-        
+
         if !path.exists() {
             return Err(VdaError::DllNotFound(path.display().to_string()));
         }
-        
+
         let lib = unsafe { Library::new(path)? };
-        
-        Ok(Self { lib })
+        let mut resolver = Resolver::new(&lib);
+
+        let exports = match (
+            resolver.required("GetDesktopCount"),
+            resolver.required("GetCurrentDesktopNumber"),
+            resolver.required("GoToDesktopNumber"),
+            resolver.required("CreateDesktop"),
+            resolver.required("RemoveDesktop"),
+            resolver.required("MoveWindowToDesktopNumber"),
+            resolver.required("GetWindowDesktopNumber"),
+            resolver.required("GetDesktopIdByNumber"),
+            resolver.required("GetDesktopNumberById"),
+        ) {
+            (
+                Some(get_desktop_count),
+                Some(get_current_desktop_number),
+                Some(go_to_desktop_number),
+                Some(create_desktop),
+                Some(remove_desktop),
+                Some(move_window_to_desktop_number),
+                Some(get_window_desktop_number),
+                Some(get_desktop_id_by_number),
+                Some(get_desktop_number_by_id),
+            ) => Exports {
+                get_desktop_count,
+                get_current_desktop_number,
+                go_to_desktop_number,
+                create_desktop,
+                remove_desktop,
+                move_window_to_desktop_number,
+                get_window_desktop_number,
+                get_desktop_id_by_number,
+                get_desktop_number_by_id,
+            },
+            _ => return Err(VdaError::MissingExports(resolver.missing)),
+        };
+
+        let optional = OptionalExports {
+            get_desktop_name: resolver.optional("GetDesktopName"),
+            set_desktop_name: resolver.optional("SetDesktopName"),
+            is_pinned_window: resolver.optional("IsPinnedWindow"),
+            pin_window: resolver.optional("PinWindow"),
+            unpin_window: resolver.optional("UnPinWindow"),
+            register_post_message_hook: resolver.optional("RegisterPostMessageHook"),
+            unregister_post_message_hook: resolver.optional("UnregisterPostMessageHook"),
+            move_desktop: resolver.optional("MoveDesktop"),
+        };
+        let capabilities = optional.capabilities();
+
+        Ok(Self {
+            exports,
+            optional,
+            capabilities,
+            hook_hwnd: Cell::new(None),
+            _lib: lib,
+        })
     }
-    
-    /// 获取桌面总数
-    pub fn get_desktop_count(&self) -> i32 {
-        unsafe {
-            let func: Symbol<unsafe extern "C" fn() -> i32> = 
-                self.lib.get(b"GetDesktopCount").expect("GetDesktopCount not found");
-            func()
+
+    /// 可选能力表
+    pub fn capabilities(&self) -> &BTreeMap<Capability, bool> {
+        &self.capabilities
+    }
+
+    /// 检查是否支持某项可选能力
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.get(&capability).copied().unwrap_or(false)
+    }
+
+    /// 注册桌面切换通知：当前桌面变化时 DLL 向 `hwnd` 投递 `message`
+    /// （wParam 为旧桌面索引，lParam 为新桌面索引）。不支持时返回 false
+    /// 释放时自动取消
+    pub fn register_post_message_hook(&self, hwnd: HWND, message: u32) -> bool {
+        match self.optional.register_post_message_hook {
+            Some(register) => {
+                unsafe { register(hwnd.0 as isize, message) };
+                self.hook_hwnd.set(Some(hwnd.0 as isize));
+                true
+            }
+            None => false,
         }
     }
-    
-    /// 获取当前桌面索引（0-based）
-    pub fn get_current_desktop(&self) -> i32 {
-        unsafe {
-            let func: Symbol<unsafe extern "C" fn() -> i32> = 
-                self.lib.get(b"GetCurrentDesktopNumber").expect("GetCurrentDesktopNumber not found");
-            func()
+
+    /// 取消桌面切换通知
+    pub fn unregister_post_message_hook(&self) {
+        if let (Some(unregister), Some(hwnd)) =
+            (self.optional.unregister_post_message_hook, self.hook_hwnd.take())
+        {
+            unsafe { unregister(hwnd) };
         }
     }
-    
+}
+
+impl Drop for VirtualDesktopAccessor {
+    fn drop(&mut self) {
+        self.unregister_post_message_hook();
+    }
+}
+
+impl DesktopBackend for VirtualDesktopAccessor {
+    /// 获取桌面总数
+    fn get_desktop_count(&self) -> i32 {
+        unsafe { (self.exports.get_desktop_count)() }
+    }
+
+    /// 获取当前桌面索引（0-based）
+    fn get_current_desktop(&self) -> i32 {
+        unsafe { (self.exports.get_current_desktop_number)() }
+    }
+
     /// 切换到指定桌面（0-based）
     /// 注意：这个方法实际上是有动画的，因为它内部调用的是 IVirtualDesktopManagerInternal::SwitchDesktop
-    pub fn go_to_desktop(&self, index: i32) {
-        unsafe {
-            let func: Symbol<unsafe extern "C" fn(i32)> = 
-                self.lib.get(b"GoToDesktopNumber").expect("GoToDesktopNumber not found");
-            func(index)
-        }
+    fn go_to_desktop(&self, index: i32) {
+        unsafe { (self.exports.go_to_desktop_number)(index) }
     }
-    
+
     /// 创建新桌面
-    pub fn create_desktop(&self) {
-        unsafe {
-            let func: Symbol<unsafe extern "C" fn()> = 
-                self.lib.get(b"CreateDesktop").expect("CreateDesktop not found");
-            func()
-        }
+    fn create_desktop(&self) {
+        unsafe { (self.exports.create_desktop)() }
     }
-    
+
     /// 删除指定桌面
-    /// 
+    ///
     /// # Arguments
     /// * `index` - 要删除的桌面索引
     /// * `fallback` - 窗口移动到的目标桌面索引
-    pub fn remove_desktop(&self, index: i32, fallback: i32) {
-        unsafe {
-            let func: Symbol<unsafe extern "C" fn(i32, i32)> = 
-                self.lib.get(b"RemoveDesktop").expect("RemoveDesktop not found");
-            func(index, fallback)
-        }
+    fn remove_desktop(&self, index: i32, fallback: i32) {
+        unsafe { (self.exports.remove_desktop)(index, fallback) }
     }
-    
+
     /// 将窗口移动到指定桌面
-    pub fn move_window_to_desktop(&self, hwnd: HWND, index: i32) {
-        unsafe {
-            let func: Symbol<unsafe extern "C" fn(isize, i32)> = 
-                self.lib.get(b"MoveWindowToDesktopNumber").expect("MoveWindowToDesktopNumber not found");
-            func(hwnd.0 as isize, index)
-        }
+    fn move_window_to_desktop(&self, hwnd: HWND, index: i32) {
+        unsafe { (self.exports.move_window_to_desktop_number)(hwnd.0 as isize, index) }
     }
-    
+
     /// 获取窗口所在的桌面索引
-    pub fn get_window_desktop(&self, hwnd: HWND) -> i32 {
-        unsafe {
-            let func: Symbol<unsafe extern "C" fn(isize) -> i32> = 
-                self.lib.get(b"GetWindowDesktopNumber").expect("GetWindowDesktopNumber not found");
-            func(hwnd.0 as isize)
+    fn get_window_desktop(&self, hwnd: HWND) -> i32 {
+        unsafe { (self.exports.get_window_desktop_number)(hwnd.0 as isize) }
+    }
+
+    /// 获取指定索引处桌面的 GUID（DLL 在索引无效时返回全零 GUID）
+    fn get_desktop_id(&self, index: i32) -> Option<DesktopId> {
+        if index < 0 || index >= self.get_desktop_count() {
+            return None;
         }
+        let guid = unsafe { (self.exports.get_desktop_id_by_number)(index) };
+        (guid != GUID::zeroed()).then(|| DesktopId(guid.to_u128()))
     }
-    
-    /// 检查窗口是否在当前桌面
-    pub fn is_window_on_current_desktop(&self, hwnd: HWND) -> bool {
-        unsafe {
-            let func: Symbol<unsafe extern "C" fn(isize) -> i32> = 
-                self.lib.get(b"IsWindowOnCurrentVirtualDesktop").expect("IsWindowOnCurrentVirtualDesktop not found");
-            func(hwnd.0 as isize) != 0
+
+    /// 获取桌面名称（需要 DesktopNames 能力，名称为 UTF-8）
+    fn get_desktop_name(&self, index: i32) -> Option<String> {
+        let get_desktop_name = self.optional.get_desktop_name?;
+        let mut buffer = [0u8; 512];
+        let result = unsafe { get_desktop_name(index, buffer.as_mut_ptr(), buffer.len()) };
+        if result <= 0 {
+            return None;
         }
+        let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+        Some(String::from_utf8_lossy(&buffer[..len]).into_owned())
+    }
+
+    /// 设置桌面名称（需要 DesktopNames 能力，名称以 UTF-8 传入）
+    fn set_desktop_name(&self, index: i32, name: &str) -> bool {
+        let Some(set_desktop_name) = self.optional.set_desktop_name else {
+            return false;
+        };
+        if !(0..self.get_desktop_count()).contains(&index) {
+            return false;
+        }
+        let Ok(name) = CString::new(name) else {
+            return false;
+        };
+        unsafe { set_desktop_name(index, name.as_ptr() as *const u8) > 0 }
+    }
+
+    /// 获取 GUID 对应桌面的当前索引（DLL 在找不到时返回 -1）
+    fn get_desktop_index(&self, id: DesktopId) -> Option<i32> {
+        let index = unsafe { (self.exports.get_desktop_number_by_id)(GUID::from_u128(id.0)) };
+        (index >= 0).then_some(index)
+    }
+
+    /// 调整桌面顺序（需要 DesktopOrdering 能力）
+    fn move_desktop(&self, index: i32, target: i32) -> bool {
+        let Some(move_desktop) = self.optional.move_desktop else {
+            return false;
+        };
+        let count = self.get_desktop_count();
+        if !(0..count).contains(&index) || !(0..count).contains(&target) {
+            return false;
+        }
+        unsafe { move_desktop(index, target) >= 0 }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     #[ignore] // 需要 DLL 文件才能运行
     fn test_load_dll() {
         let vda = VirtualDesktopAccessor::new("../assets/VirtualDesktopAccessor.dll");
         assert!(vda.is_ok());
     }
+
+    #[test]
+    #[ignore] // 需要 DLL 文件才能运行
+    fn test_bundled_dll_capabilities() {
+        let vda = VirtualDesktopAccessor::new("../assets/VirtualDesktopAccessor.dll").unwrap();
+        assert!(vda.supports(Capability::DesktopNames));
+        assert!(vda.supports(Capability::Pinning));
+        assert!(vda.supports(Capability::Notifications));
+    }
 }
@@ -0,0 +1,186 @@
+//! 状态确认等待
+//!
+//! DLL 和窗口的操作都是异步生效的。与其固定睡眠一段时间再"希望"状态已经就绪，
+//! 不如反复检查期望的状态（当前桌面、窗口所在桌面、是否最大化……），
+//! 满足后立即继续，超时则报告错误。时钟可替换，测试中使用不真正睡眠的模拟时钟。
+
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum WaitError {
+    #[error("等待{condition}超时（{timeout_ms} 毫秒）")]
+    Timeout {
+        condition: &'static str,
+        timeout_ms: u64,
+    },
+}
+
+/// 时钟
+pub trait Clock {
+    /// 当前时刻
+    fn now(&self) -> Instant;
+
+    /// 睡眠指定时长
+    fn sleep(&self, duration: Duration);
+}
+
+/// 系统时钟
+#[cfg_attr(not(windows), allow(dead_code))]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// 轮询等待器
+pub struct Waiter<'a> {
+    clock: &'a dyn Clock,
+    timeout: Duration,
+    interval: Duration,
+}
+
+impl<'a> Waiter<'a> {
+    /// 创建等待器：每隔 `interval` 检查一次，最多等待 `timeout`
+    pub fn new(clock: &'a dyn Clock, timeout: Duration, interval: Duration) -> Self {
+        Self {
+            clock,
+            timeout,
+            interval,
+        }
+    }
+
+    /// 等待条件成立（先立即检查一次，成立时不睡眠）
+    ///
+    /// `condition` 是条件的描述，用于超时时的错误信息，如"窗口最大化"
+    pub fn until(
+        &self,
+        condition: &'static str,
+        mut check: impl FnMut() -> bool,
+    ) -> Result<(), WaitError> {
+        let start = self.clock.now();
+        loop {
+            if check() {
+                return Ok(());
+            }
+            if self.clock.now().duration_since(start) >= self.timeout {
+                return Err(WaitError::Timeout {
+                    condition,
+                    timeout_ms: self.timeout.as_millis() as u64,
+                });
+            }
+            self.clock.sleep(self.interval);
+        }
+    }
+
+    /// 无条件睡眠（用于无法观察到的状态，如系统切换动画）
+    pub fn sleep(&self, duration: Duration) {
+        if !duration.is_zero() {
+            self.clock.sleep(duration);
+        }
+    }
+}
+
+/// 模拟时钟（测试用）：睡眠只推进时间，不真正等待
+#[cfg(test)]
+pub mod fake {
+    use super::Clock;
+    use std::cell::Cell;
+    use std::time::{Duration, Instant};
+
+    pub struct FakeClock {
+        start: Instant,
+        elapsed: Cell<Duration>,
+        sleeps: Cell<usize>,
+    }
+
+    impl FakeClock {
+        pub fn new() -> Self {
+            Self {
+                start: Instant::now(),
+                elapsed: Cell::new(Duration::ZERO),
+                sleeps: Cell::new(0),
+            }
+        }
+
+        /// 累计经过的（模拟）时间
+        pub fn elapsed(&self) -> Duration {
+            self.elapsed.get()
+        }
+
+        /// 睡眠次数
+        pub fn sleeps(&self) -> usize {
+            self.sleeps.get()
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.start + self.elapsed.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.elapsed.set(self.elapsed.get() + duration);
+            self.sleeps.set(self.sleeps.get() + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakeClock;
+    use super::*;
+    use std::cell::Cell;
+
+    fn waiter(clock: &FakeClock) -> Waiter<'_> {
+        Waiter::new(clock, Duration::from_millis(100), Duration::from_millis(10))
+    }
+
+    #[test]
+    fn test_ready_condition_does_not_sleep() {
+        let clock = FakeClock::new();
+        assert_eq!(waiter(&clock).until("就绪", || true), Ok(()));
+        assert_eq!(clock.sleeps(), 0);
+    }
+
+    #[test]
+    fn test_waits_until_condition_holds() {
+        let clock = FakeClock::new();
+        let polls = Cell::new(0);
+
+        let result = waiter(&clock).until("第三次检查", || {
+            polls.set(polls.get() + 1);
+            polls.get() == 3
+        });
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(polls.get(), 3);
+        assert_eq!(clock.elapsed(), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_timeout_is_reported() {
+        let clock = FakeClock::new();
+
+        let result = waiter(&clock).until("永远不会成立的条件", || false);
+
+        assert_eq!(
+            result,
+            Err(WaitError::Timeout {
+                condition: "永远不会成立的条件",
+                timeout_ms: 100
+            })
+        );
+        assert_eq!(clock.elapsed(), Duration::from_millis(100));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "等待永远不会成立的条件超时（100 毫秒）"
+        );
+    }
+}
@@ -1,59 +1,443 @@
 //! 窗口操作辅助模块
 
-use windows::Win32::Foundation::HWND;
+use crate::backend::{
+    WindowBackend, WindowDetails, WindowFrame, WindowIdentity, WindowPlacement, WindowPoint, WindowRect,
+};
+use crate::fullscreen::{WindowSnapshot, SHELL_CLASSES};
+use crate::keymap::Chord;
+use windows::core::PWSTR;
+use windows::Win32::Foundation::{CloseHandle, FILETIME, HWND, POINT, RECT};
+use windows::Win32::Graphics::Gdi::{
+    GetMonitorInfoW, MonitorFromRect, MonitorFromWindow, HMONITOR, MONITORINFO,
+    MONITOR_DEFAULTTONEAREST,
+};
+use windows::Win32::System::Threading::{
+    GetProcessTimes, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT, KEYBD_EVENT_FLAGS, KEYEVENTF_KEYUP,
+    VIRTUAL_KEY, VK_CONTROL, VK_LWIN, VK_MENU, VK_SHIFT,
+};
 use windows::Win32::UI::WindowsAndMessaging::{
-    GetForegroundWindow, GetWindowLongW, IsWindow, ShowWindow,
-    GWL_STYLE, SW_MAXIMIZE, SW_RESTORE, WS_MAXIMIZE,
+    GetClassNameW, GetForegroundWindow, GetWindow, GetWindowLongW, GetWindowPlacement, GetWindowRect,
+    GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindow, IsWindowVisible, SetWindowLongW, SetWindowPlacement, SetWindowPos,
+    ShowWindow, GWL_EXSTYLE, GWL_STYLE, GW_HWNDNEXT, GW_OWNER, HWND_TOP, SET_WINDOW_POS_FLAGS, SWP_FRAMECHANGED,
+    SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOOWNERZORDER, SWP_NOSIZE, SWP_NOZORDER, SW_MAXIMIZE,
+    SW_RESTORE, SW_SHOWMAXIMIZED, SW_SHOWNORMAL, WINDOWPLACEMENT, WINDOWPLACEMENT_FLAGS,
+    WS_CAPTION, WS_EX_TOOLWINDOW, WS_MAXIMIZE, WS_THICKFRAME,
 };
-use tracing::trace;
+use tracing::{debug, trace};
 
 /// 窗口辅助函数
 pub struct WindowHelper;
 
-impl WindowHelper {
+impl WindowBackend for WindowHelper {
     /// 获取当前活动窗口
-    pub fn get_active() -> HWND {
+    fn get_active(&self) -> HWND {
         unsafe { GetForegroundWindow() }
     }
-    
+
     /// 检查窗口是否有效
-    pub fn is_valid(hwnd: HWND) -> bool {
+    fn is_valid(&self, hwnd: HWND) -> bool {
         if hwnd.0.is_null() {
             return false;
         }
-        
+
         unsafe { IsWindow(hwnd).as_bool() }
     }
-    
+
     /// 检查窗口是否最大化
-    pub fn is_maximized(hwnd: HWND) -> bool {
-        if !Self::is_valid(hwnd) {
+    fn is_maximized(&self, hwnd: HWND) -> bool {
+        if !self.is_valid(hwnd) {
             return false;
         }
-        
+
         unsafe {
             let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
             (style & WS_MAXIMIZE.0) != 0
         }
     }
-    
+
     /// 最大化窗口
-    pub fn maximize(hwnd: HWND) {
-        if Self::is_valid(hwnd) {
+    fn maximize(&self, hwnd: HWND) {
+        if self.is_valid(hwnd) {
             trace!("最大化窗口: {:?}", hwnd);
             unsafe {
                 let _ = ShowWindow(hwnd, SW_MAXIMIZE);
             }
         }
     }
-    
+
     /// 还原窗口
-    pub fn restore(hwnd: HWND) {
-        if Self::is_valid(hwnd) {
+    fn restore(&self, hwnd: HWND) {
+        if self.is_valid(hwnd) {
             trace!("还原窗口: {:?}", hwnd);
             unsafe {
                 let _ = ShowWindow(hwnd, SW_RESTORE);
             }
         }
     }
+
+    /// 发送组合键：依次按下修饰键和按键，再按相反顺序松开
+    fn send_chord(&self, chord: Chord) {
+        let key = |vk: VIRTUAL_KEY, flags: KEYBD_EVENT_FLAGS| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: vk,
+                    wScan: 0,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        };
+
+        let m = chord.modifiers;
+        let mut keys: Vec<VIRTUAL_KEY> = [
+            (m.win, VK_LWIN),
+            (m.ctrl, VK_CONTROL),
+            (m.shift, VK_SHIFT),
+            (m.alt, VK_MENU),
+        ]
+        .into_iter()
+        .filter_map(|(pressed, vk)| pressed.then_some(vk))
+        .collect();
+        keys.push(VIRTUAL_KEY(chord.vk));
+
+        let inputs: Vec<INPUT> = keys
+            .iter()
+            .map(|vk| key(*vk, KEYBD_EVENT_FLAGS(0)))
+            .chain(keys.iter().rev().map(|vk| key(*vk, KEYEVENTF_KEYUP)))
+            .collect();
+
+        trace!("发送组合键: {}", chord);
+        unsafe {
+            SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+        }
+    }
+
+    /// 去掉标题栏和可调边框，铺满窗口所在的显示器
+    fn make_borderless(&self, hwnd: HWND) -> Option<WindowFrame> {
+        if !self.is_valid(hwnd) {
+            return None;
+        }
+
+        unsafe {
+            let style = GetWindowLongW(hwnd, GWL_STYLE) as u32;
+            let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32;
+            let monitor = monitor_rect(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST))?;
+
+            trace!("去掉窗口边框: {:?}, 原样式={:#010X}", hwnd, style);
+            SetWindowLongW(hwnd, GWL_STYLE, (style & !(WS_CAPTION.0 | WS_THICKFRAME.0)) as i32);
+            if set_rect(hwnd, monitor, SWP_FRAMECHANGED | SWP_NOOWNERZORDER) {
+                Some(WindowFrame { style, ex_style })
+            } else {
+                SetWindowLongW(hwnd, GWL_STYLE, style as i32);
+                None
+            }
+        }
+    }
+
+    /// 恢复原来的样式（位置和大小由 `set_placement` 恢复）
+    fn restore_frame(&self, hwnd: HWND, frame: &WindowFrame) -> bool {
+        if !self.is_valid(hwnd) {
+            return false;
+        }
+
+        trace!("恢复窗口边框: {:?}, 样式={:#010X}", hwnd, frame.style);
+        unsafe {
+            SetWindowLongW(hwnd, GWL_STYLE, frame.style as i32);
+            SetWindowLongW(hwnd, GWL_EXSTYLE, frame.ex_style as i32);
+        }
+        set_rect(
+            hwnd,
+            WindowRect::default(),
+            SWP_FRAMECHANGED
+                | SWP_NOMOVE
+                | SWP_NOSIZE
+                | SWP_NOZORDER
+                | SWP_NOOWNERZORDER
+                | SWP_NOACTIVATE,
+        )
+    }
+
+    /// 获取 `WINDOWPLACEMENT` 和窗口所在显示器
+    fn get_placement(&self, hwnd: HWND) -> Option<WindowPlacement> {
+        if !self.is_valid(hwnd) {
+            return None;
+        }
+
+        unsafe {
+            let mut wp = WINDOWPLACEMENT {
+                length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+                ..Default::default()
+            };
+            GetWindowPlacement(hwnd, &mut wp).ok()?;
+            let info = monitor_info(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST))?;
+
+            Some(WindowPlacement {
+                maximized: wp.showCmd == SW_SHOWMAXIMIZED.0 as u32,
+                flags: wp.flags.0,
+                min_position: from_point(wp.ptMinPosition),
+                max_position: from_point(wp.ptMaxPosition),
+                normal_position: from_rect(wp.rcNormalPosition),
+                monitor: from_rect(info.rcMonitor),
+                work_area: from_rect(info.rcWork),
+            })
+        }
+    }
+
+    /// 用 `SetWindowPlacement` 恢复位置、大小和最大化状态
+    fn set_placement(&self, hwnd: HWND, placement: &WindowPlacement) -> bool {
+        if !self.is_valid(hwnd) {
+            return false;
+        }
+
+        unsafe {
+            // 显示器布局变化后，原来的显示器可能已经断开、改变了分辨率或任务栏位置
+            let saved = to_rect(placement.monitor);
+            let placement = match monitor_info(MonitorFromRect(&saved, MONITOR_DEFAULTTONEAREST)) {
+                Some(info)
+                    if from_rect(info.rcMonitor) != placement.monitor
+                        || from_rect(info.rcWork) != placement.work_area =>
+                {
+                    let (monitor, work_area) = (from_rect(info.rcMonitor), from_rect(info.rcWork));
+                    debug!("原显示器已改变，窗口移到 {:?}（工作区 {:?}）", monitor, work_area);
+                    placement.moved_to(monitor, work_area)
+                }
+                _ => *placement,
+            };
+
+            let show = if placement.maximized { SW_SHOWMAXIMIZED } else { SW_SHOWNORMAL };
+            let wp = WINDOWPLACEMENT {
+                length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+                flags: WINDOWPLACEMENT_FLAGS(placement.flags),
+                showCmd: show.0 as u32,
+                ptMinPosition: to_point(placement.min_position),
+                ptMaxPosition: to_point(placement.max_position),
+                rcNormalPosition: to_rect(placement.normal_position),
+            };
+            trace!("恢复窗口位置: {:?}, {:?}", hwnd, placement);
+            SetWindowPlacement(hwnd, &wp).is_ok()
+        }
+    }
+
+    /// 获取窗口的进程路径、类名和标题
+    fn details(&self, hwnd: HWND) -> WindowDetails {
+        if !self.is_valid(hwnd) {
+            return WindowDetails::default();
+        }
+
+        let mut title = [0u16; 512];
+        unsafe {
+            let title_len = GetWindowTextW(hwnd, &mut title).max(0) as usize;
+
+            WindowDetails {
+                process_path: process_path(hwnd).unwrap_or_default(),
+                class_name: class_name(hwnd),
+                title: String::from_utf16_lossy(&title[..title_len]),
+            }
+        }
+    }
+
+    /// 获取样式、窗口矩形和所在显示器
+    fn snapshot(&self, hwnd: HWND) -> Option<WindowSnapshot> {
+        if !self.is_valid(hwnd) {
+            return None;
+        }
+
+        unsafe {
+            let mut rect = RECT::default();
+            GetWindowRect(hwnd, &mut rect).ok()?;
+
+            Some(WindowSnapshot {
+                class_name: class_name(hwnd),
+                style: GetWindowLongW(hwnd, GWL_STYLE) as u32,
+                ex_style: GetWindowLongW(hwnd, GWL_EXSTYLE) as u32,
+                visible: IsWindowVisible(hwnd).as_bool(),
+                minimized: IsIconic(hwnd).as_bool(),
+                rect: from_rect(rect),
+                monitor: monitor_rect(MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST))?,
+            })
+        }
+    }
+
+    /// 获取进程 ID、进程创建时间和类名
+    fn identity(&self, hwnd: HWND) -> Option<WindowIdentity> {
+        if !self.is_valid(hwnd) {
+            return None;
+        }
+
+        let pid = process_id(hwnd)?;
+        Some(WindowIdentity {
+            pid,
+            process_started: process_started(pid).unwrap_or(0),
+            class_name: class_name(hwnd),
+        })
+    }
+
+    /// 移动窗口并调整大小，不激活窗口
+    fn set_rect(&self, hwnd: HWND, rect: &WindowRect) -> bool {
+        if !self.is_valid(hwnd) {
+            return false;
+        }
+
+        trace!("移动窗口: {:?}, {:?}", hwnd, rect);
+        set_rect(hwnd, *rect, SWP_NOZORDER | SWP_NOOWNERZORDER | SWP_NOACTIVATE)
+    }
+
+    /// 获取窗口所在显示器的 `rcWork`
+    fn work_area(&self, hwnd: HWND) -> Option<WindowRect> {
+        if !self.is_valid(hwnd) {
+            return None;
+        }
+
+        let info = monitor_info(unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) })?;
+        Some(from_rect(info.rcWork))
+    }
+
+    /// 沿 Z 序向下找，跳过不可见、最小化、有所有者的窗口和工具窗口
+    fn next_window(&self, hwnd: HWND) -> Option<HWND> {
+        if !self.is_valid(hwnd) {
+            return None;
+        }
+
+        let mut current = hwnd;
+        unsafe {
+            while let Ok(next) = GetWindow(current, GW_HWNDNEXT) {
+                if next.0.is_null() {
+                    break;
+                }
+                current = next;
+
+                let ex_style = GetWindowLongW(next, GWL_EXSTYLE) as u32;
+                let owned = GetWindow(next, GW_OWNER).is_ok_and(|owner| !owner.0.is_null());
+                if IsWindowVisible(next).as_bool()
+                    && !IsIconic(next).as_bool()
+                    && !owned
+                    && ex_style & WS_EX_TOOLWINDOW.0 == 0
+                    && !SHELL_CLASSES.contains(&class_name(next).as_str())
+                {
+                    return Some(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// 获取窗口类名
+fn class_name(hwnd: HWND) -> String {
+    let mut class = [0u16; 256];
+    let len = unsafe { GetClassNameW(hwnd, &mut class) }.max(0) as usize;
+    String::from_utf16_lossy(&class[..len])
+}
+
+/// 获取显示器的矩形
+fn monitor_rect(monitor: HMONITOR) -> Option<WindowRect> {
+    monitor_info(monitor).map(|info| from_rect(info.rcMonitor))
+}
+
+/// 获取显示器信息（整个显示器和工作区的矩形）
+fn monitor_info(monitor: HMONITOR) -> Option<MONITORINFO> {
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    unsafe { GetMonitorInfoW(monitor, &mut info) }
+        .as_bool()
+        .then_some(info)
+}
+
+fn from_point(point: POINT) -> WindowPoint {
+    WindowPoint {
+        x: point.x,
+        y: point.y,
+    }
+}
+
+fn to_point(point: WindowPoint) -> POINT {
+    POINT {
+        x: point.x,
+        y: point.y,
+    }
+}
+
+fn to_rect(rect: WindowRect) -> RECT {
+    RECT {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    }
+}
+
+fn from_rect(rect: RECT) -> WindowRect {
+    WindowRect {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    }
+}
+
+/// 移动窗口并调整大小（样式改变后需要带上 `SWP_FRAMECHANGED` 让系统重新计算边框）
+fn set_rect(hwnd: HWND, rect: WindowRect, flags: SET_WINDOW_POS_FLAGS) -> bool {
+    unsafe {
+        SetWindowPos(
+            hwnd,
+            HWND_TOP,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            flags,
+        )
+        .is_ok()
+    }
+}
+
+/// 获取窗口所属进程的可执行文件路径
+fn process_path(hwnd: HWND) -> Option<String> {
+    unsafe {
+        let pid = process_id(hwnd)?;
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut len,
+        );
+        let _ = CloseHandle(process);
+
+        result.ok()?;
+        Some(String::from_utf16_lossy(&buffer[..len as usize]))
+    }
+}
+
+/// 获取窗口所属的进程 ID
+fn process_id(hwnd: HWND) -> Option<u32> {
+    let mut pid = 0u32;
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    (pid != 0).then_some(pid)
+}
+
+/// 获取进程的创建时间（以管理员权限运行的进程可能无法打开）
+fn process_started(pid: u32) -> Option<u64> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut created = FILETIME::default();
+        let mut exited = FILETIME::default();
+        let mut kernel = FILETIME::default();
+        let mut user = FILETIME::default();
+        let result = GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user);
+        let _ = CloseHandle(process);
+
+        result.ok()?;
+        Some((created.dwHighDateTime as u64) << 32 | created.dwLowDateTime as u64)
+    }
 }